@@ -0,0 +1,31 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use dyse_rust::{launch::launcher::*, socks::logging};
+use log::error;
+use std::env;
+
+fn main() {
+    logging::init_from_env();
+    let args: Vec<String> = env::args().collect();
+
+    let description = match args.get(1) {
+        Some(robot) => LaunchDescription::robot(robot),
+        None => LaunchDescription::from_env(),
+    };
+
+    match description {
+        Ok(description) => Launcher::new(description).spin(),
+        Err(e) => error!("{e}"),
+    }
+}
@@ -0,0 +1,496 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::{
+    socks::{
+        message::UDP_PACKET_SIZE,
+        names::{NAMESPACE_ARG, REMAP_SEPARATOR},
+        socks::Sock,
+    },
+    utilities::loaders::{BuffYamlUtil, ByuParseError},
+};
+use log::{error, info, warn};
+use std::{
+    env,
+    io::{BufRead, BufReader, Read},
+    process::{Child, Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+use yaml_rust::yaml::Yaml;
+
+/// First restart delay, doubled for every restart after that
+pub static LAUNCH_BACKOFF_MS: u64 = 500;
+pub static LAUNCH_MAX_BACKOFF_MS: u64 = 30_000;
+/// Time nodes get to exit after the shutdown message before they are killed
+pub static LAUNCH_SHUTDOWN_GRACE_MS: u128 = 3000;
+
+/// Replace `${VAR}` with the value of VAR, unset variables are left as is
+pub fn expand_env(arg: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = arg;
+
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        match rest[start..].find('}') {
+            Some(end) => {
+                let var = &rest[start + 2..start + end];
+                match env::var(var) {
+                    Ok(value) => expanded.push_str(&value),
+                    _ => expanded.push_str(&rest[start..start + end + 1]),
+                }
+                rest = &rest[start + end + 1..];
+            }
+            None => break,
+        }
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Where built dyse nodes are installed
+pub fn lib_path() -> String {
+    let project_root = env::var("PROJECT_ROOT").expect("Project root not set");
+    format!("{project_root}/dysepy/lib")
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RestartPolicy {
+    Never,
+    OnFailure,
+    Always,
+}
+
+impl RestartPolicy {
+    /// Read `respawn` (bool or never/on_failure/always) from a node,
+    /// falls back to the spawner's `required` flag
+    pub fn from_yaml(byu: &BuffYamlUtil, data: &Yaml) -> Result<RestartPolicy, ByuParseError> {
+        match &data["respawn"] {
+            Yaml::String(policy) => match policy.as_str() {
+                "never" => Ok(RestartPolicy::Never),
+                "on_failure" => Ok(RestartPolicy::OnFailure),
+                "always" => Ok(RestartPolicy::Always),
+                _ => Err(ByuParseError::string("respawn", &byu.yaml_path)),
+            },
+            Yaml::Boolean(true) => Ok(RestartPolicy::OnFailure),
+            Yaml::Boolean(false) => Ok(RestartPolicy::Never),
+            _ => match byu.parse_bool("required", data) {
+                Ok(true) => Ok(RestartPolicy::OnFailure),
+                _ => Ok(RestartPolicy::Never),
+            },
+        }
+    }
+
+    pub fn should_restart(&self, status: &ExitStatus) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => !status.success(),
+            RestartPolicy::Always => true,
+        }
+    }
+}
+
+/// One process in the graph
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeDescription {
+    pub name: String,
+    pub program: String,
+    pub args: Vec<String>,
+    pub namespace: Option<String>,
+    pub remaps: Vec<(String, String)>,
+    pub restart: RestartPolicy,
+    pub max_restarts: Option<usize>,
+    pub backoff_ms: u64,
+}
+
+impl NodeDescription {
+    pub fn new(name: &str, program: &str, args: Vec<String>) -> NodeDescription {
+        NodeDescription {
+            name: name.to_string(),
+            program: program.to_string(),
+            args,
+            namespace: None,
+            remaps: vec![],
+            restart: RestartPolicy::Never,
+            max_restarts: None,
+            backoff_ms: LAUNCH_BACKOFF_MS,
+        }
+    }
+
+    /// Reads the options shared by every file of a dyse or ros node entry
+    fn configure(
+        mut self,
+        byu: &BuffYamlUtil,
+        data: &Yaml,
+    ) -> Result<NodeDescription, ByuParseError> {
+        self.restart = RestartPolicy::from_yaml(byu, data)?;
+
+        if let Ok(namespace) = byu.parse_str("namespace", data) {
+            self.namespace = Some(namespace);
+        }
+
+        if let Ok(max) = byu.parse_int("max_respawns", data) {
+            self.max_restarts = Some(max.max(0) as usize);
+        }
+
+        if let Ok(delay) = byu.parse_int("respawn_delay", data) {
+            self.backoff_ms = delay.max(0) as u64;
+        }
+
+        self.remaps = match &data["remap"] {
+            Yaml::Hash(remaps) => remaps
+                .iter()
+                .map(|(from, to)| match (from.as_str(), to.as_str()) {
+                    (Some(from), Some(to)) => Ok((from.to_string(), to.to_string())),
+                    _ => Err(ByuParseError::string("remap", &byu.yaml_path)),
+                })
+                .collect::<Result<Vec<(String, String)>, ByuParseError>>()?,
+            Yaml::BadValue => vec![],
+            _ => return Err(ByuParseError::item("remap", &byu.yaml_path)),
+        };
+
+        Ok(self)
+    }
+
+    /// Arguments the process is started with, the namespace and
+    /// remaps are passed as `from:=to` (see [`crate::socks::names`])
+    pub fn command_args(&self) -> Vec<String> {
+        self.args
+            .iter()
+            .cloned()
+            .chain(
+                self.namespace
+                    .iter()
+                    .map(|ns| format!("{NAMESPACE_ARG}{REMAP_SEPARATOR}{ns}")),
+            )
+            .chain(
+                self.remaps
+                    .iter()
+                    .map(|(from, to)| format!("{from}{REMAP_SEPARATOR}{to}")),
+            )
+            .collect()
+    }
+
+    /// Delay before restart number `restarts` (from 0)
+    pub fn backoff(&self, restarts: usize) -> Duration {
+        let delay = self
+            .backoff_ms
+            .saturating_mul(1 << restarts.min(16))
+            .min(LAUNCH_MAX_BACKOFF_MS.max(self.backoff_ms));
+
+        Duration::from_millis(delay)
+    }
+}
+
+/// Every node a robot runs, read from its nodes.yaml
+#[derive(Clone, Debug, PartialEq)]
+pub struct LaunchDescription {
+    pub robot: Option<String>,
+    pub nodes: Vec<NodeDescription>,
+}
+
+impl LaunchDescription {
+    /// Nodes without a `namespace` go in the top level `namespace` (if there is one)
+    pub fn from_byu(byu: &BuffYamlUtil) -> Result<LaunchDescription, ByuParseError> {
+        let mut nodes = vec![];
+
+        if let Yaml::Hash(dyse_nodes) = &byu.data()["dyse_nodes"] {
+            for (name, data) in dyse_nodes {
+                let name = name.as_str().unwrap_or_default();
+                let args = byu.parse_strs("args", data).unwrap_or_default();
+
+                for file in byu.parse_strs("files", data)? {
+                    let program = format!("{}/{file}", lib_path());
+                    let (program, args) = match file.ends_with(".py") {
+                        true => (
+                            "python3".to_string(),
+                            [vec![program], args.clone()].concat(),
+                        ),
+                        false => (program, args.clone()),
+                    };
+
+                    nodes.push(
+                        NodeDescription::new(
+                            &format!("{name}/{file}"),
+                            &program,
+                            args.iter().map(|arg| expand_env(arg)).collect(),
+                        )
+                        .configure(byu, data)?,
+                    );
+                }
+            }
+        }
+
+        if let Yaml::Hash(ros_nodes) = &byu.data()["ros_nodes"] {
+            for (name, data) in ros_nodes {
+                let name = name.as_str().unwrap_or_default();
+                let package = byu.parse_str("package", data)?;
+                let args = byu.parse_strs("args", data).unwrap_or_default();
+
+                for file in byu.parse_strs("files", data)? {
+                    nodes.push(
+                        NodeDescription::new(
+                            &format!("{name}/{file}"),
+                            "rosrun",
+                            [vec![package.clone(), file], args.clone()]
+                                .concat()
+                                .iter()
+                                .map(|arg| expand_env(arg))
+                                .collect(),
+                        )
+                        .configure(byu, data)?,
+                    );
+                }
+            }
+        }
+
+        if let Ok(namespace) = byu.parse_str("namespace", byu.data()) {
+            nodes
+                .iter_mut()
+                .filter(|node| node.namespace.is_none())
+                .for_each(|node| node.namespace = Some(namespace.clone()));
+        }
+
+        Ok(LaunchDescription { robot: None, nodes })
+    }
+
+    /// Load a robot's nodes.yaml, nodes are started with ROBOT_NAME set to `name`
+    pub fn robot(name: &str) -> Result<LaunchDescription, ByuParseError> {
+        let mut description = LaunchDescription::from_byu(&BuffYamlUtil::robot(name, "nodes"))?;
+        description.robot = Some(name.to_string());
+        Ok(description)
+    }
+
+    /// Load nodes.yaml of ROBOT_NAME or self.txt
+    pub fn from_env() -> Result<LaunchDescription, ByuParseError> {
+        LaunchDescription::from_byu(&BuffYamlUtil::default("nodes"))
+    }
+}
+
+/// Log each line of a node's output with the node's name as the target
+/// (so `DYSE_LOG` can filter nodes), stderr lines are warnings
+fn forward_output<R: Read + Send + 'static>(name: String, stream: R, stderr: bool) {
+    thread::spawn(move || {
+        BufReader::new(stream)
+            .lines()
+            .map_while(Result::ok)
+            .for_each(|line| match stderr {
+                true => warn!(target: &name, "{line}"),
+                false => info!(target: &name, "{line}"),
+            });
+    });
+}
+
+/// A node and the state of its process
+pub struct NodeProcess {
+    pub node: NodeDescription,
+    pub child: Option<Child>,
+    pub status: Option<ExitStatus>,
+    pub restarts: usize,
+    pub restart_at: Option<Instant>,
+}
+
+impl NodeProcess {
+    pub fn new(node: NodeDescription) -> NodeProcess {
+        NodeProcess {
+            node,
+            child: None,
+            status: None,
+            restarts: 0,
+            restart_at: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.child.is_some()
+    }
+
+    /// Not running and not going to be restarted
+    pub fn is_done(&self) -> bool {
+        self.child.is_none() && self.restart_at.is_none()
+    }
+
+    pub fn start(&mut self, robot: &Option<String>) {
+        let mut command = Command::new(&self.node.program);
+        command
+            .args(self.node.command_args())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        if let Some(robot) = robot {
+            command.env("ROBOT_NAME", robot);
+        }
+
+        match command.spawn() {
+            Ok(mut child) => {
+                info!("started {} ({})", self.node.name, child.id());
+                if let Some(stdout) = child.stdout.take() {
+                    forward_output(self.node.name.clone(), stdout, false);
+                }
+                if let Some(stderr) = child.stderr.take() {
+                    forward_output(self.node.name.clone(), stderr, true);
+                }
+                self.child = Some(child);
+            }
+            Err(e) => {
+                error!("failed to start {}: {e}", self.node.name);
+                self.schedule(self.node.restart != RestartPolicy::Never);
+            }
+        }
+    }
+
+    /// Collect the exit status if the process has finished
+    pub fn reap(&mut self) -> Option<ExitStatus> {
+        let status = match &mut self.child {
+            Some(child) => match child.try_wait() {
+                Ok(status) => status,
+                Err(e) => {
+                    error!("lost {}: {e}", self.node.name);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        if let Some(status) = status {
+            info!("{} exited ({status})", self.node.name);
+            self.child = None;
+            self.status = Some(status);
+        }
+
+        status
+    }
+
+    fn schedule(&mut self, restart: bool) {
+        let allowed = match self.node.max_restarts {
+            Some(max) => self.restarts < max,
+            None => true,
+        };
+
+        if restart && allowed {
+            let delay = self.node.backoff(self.restarts);
+            info!("restarting {} in {}ms", self.node.name, delay.as_millis());
+            self.restart_at = Some(Instant::now() + delay);
+        }
+    }
+
+    /// Reap the process and restart it when its policy and backoff allow
+    pub fn supervise(&mut self, robot: &Option<String>) {
+        if let Some(status) = self.reap() {
+            self.schedule(self.node.restart.should_restart(&status));
+        }
+
+        if let Some(t) = self.restart_at {
+            if Instant::now() >= t {
+                self.restart_at = None;
+                self.restarts += 1;
+                self.start(robot);
+            }
+        }
+    }
+
+    /// Ask the process to exit (SIGTERM), it's reaped by [`NodeProcess::reap`]
+    pub fn terminate(&self) {
+        if let Some(child) = &self.child {
+            unsafe {
+                libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+            }
+        }
+    }
+
+    pub fn kill(&mut self) {
+        self.restart_at = None;
+        if let Some(mut child) = self.child.take() {
+            warn!("killing {}", self.node.name);
+            let _ = child.kill();
+            self.status = child.wait().ok();
+        }
+    }
+}
+
+/// Starts and supervises a graph of nodes.
+///
+/// Each node's output is logged with its name as the target. Nodes that exit are
+/// restarted by their [`RestartPolicy`] with exponential backoff. The graph is
+/// torn down when a shutdown message is seen on the socks network (see
+/// [`crate::socks::sockapi::shutdown`]) or when no node is left running, only
+/// the nodes the launcher started are stopped.
+pub struct Launcher {
+    pub description: LaunchDescription,
+    pub processes: Vec<NodeProcess>,
+}
+
+impl Launcher {
+    pub fn new(description: LaunchDescription) -> Launcher {
+        Launcher {
+            processes: description
+                .nodes
+                .iter()
+                .cloned()
+                .map(NodeProcess::new)
+                .collect(),
+            description,
+        }
+    }
+
+    pub fn start(&mut self) {
+        let robot = self.description.robot.clone();
+        self.processes.iter_mut().for_each(|p| p.start(&robot));
+    }
+
+    /// Check every node once, returns false when all nodes are done
+    pub fn supervise(&mut self) -> bool {
+        let robot = self.description.robot.clone();
+        self.processes.iter_mut().for_each(|p| p.supervise(&robot));
+        !self.processes.iter().all(|p| p.is_done())
+    }
+
+    /// Signal the nodes this launcher started, then kill whatever is left
+    /// after [`LAUNCH_SHUTDOWN_GRACE_MS`]. Other socks on the network keep running.
+    pub fn teardown(&mut self) {
+        self.processes.iter_mut().for_each(|p| p.restart_at = None);
+
+        if self.processes.iter().any(|p| p.is_running()) {
+            info!("shutting down");
+            self.processes.iter().for_each(|p| p.terminate());
+        }
+
+        let t = Instant::now();
+        while t.elapsed().as_millis() < LAUNCH_SHUTDOWN_GRACE_MS
+            && self.processes.iter_mut().any(|p| {
+                p.reap();
+                p.is_running()
+            })
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        self.processes.iter_mut().for_each(|p| p.kill());
+    }
+
+    pub fn spin(&mut self) {
+        let mut sock = Sock::source("launch");
+        let mut buffer = [0u8; UDP_PACKET_SIZE];
+
+        self.start();
+
+        // try_rx times out, so it also paces supervision
+        while !*sock.shutdown.read().unwrap() && self.supervise() {
+            sock.try_rx(&mut buffer);
+        }
+
+        self.teardown();
+    }
+}
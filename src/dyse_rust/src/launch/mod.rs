@@ -0,0 +1,3 @@
+pub mod test;
+
+pub mod launcher;
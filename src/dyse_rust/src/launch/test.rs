@@ -0,0 +1,144 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+#![allow(unused_imports)]
+use crate::launch::launcher::*;
+use crate::utilities::loaders::*;
+use std::{
+    env,
+    os::unix::process::ExitStatusExt,
+    time::{Duration, Instant},
+};
+
+#[cfg(test)]
+pub mod launch {
+    use super::*;
+
+    #[test]
+    pub fn launch_from_yaml() {
+        env::set_var("LAUNCH_TEST_ARG", "value");
+
+        let byu = BuffYamlUtil::new(
+            "namespace: penguin\ndyse_nodes:\n  dyse_rust:\n    files: [comms, plot.py]\n    args: ['--arg', '${LAUNCH_TEST_ARG}']\n    required: true\n    remap:\n      imu: /penguin/imu\n  tools:\n    files: [hz]\n    namespace: /tools\n    respawn: always\n    max_respawns: 3\n    respawn_delay: 100\nros_nodes:\n  rqt_plot:\n    files: [rqt_gui]\n    package: rqt_gui",
+        );
+
+        let description = LaunchDescription::from_byu(&byu).unwrap();
+        assert_eq!(description.nodes.len(), 4);
+
+        let comms = &description.nodes[0];
+        assert_eq!(comms.name, "dyse_rust/comms");
+        assert_eq!(comms.program, format!("{}/comms", lib_path()));
+        assert_eq!(comms.restart, RestartPolicy::OnFailure);
+        assert_eq!(
+            comms.command_args(),
+            vec!["--arg", "value", "__ns:=penguin", "imu:=/penguin/imu"]
+        );
+
+        let plot = &description.nodes[1];
+        assert_eq!(plot.program, "python3");
+        assert_eq!(plot.args[0], format!("{}/plot.py", lib_path()));
+
+        let hz = &description.nodes[2];
+        assert_eq!(hz.namespace, Some("/tools".to_string()));
+        assert_eq!(hz.restart, RestartPolicy::Always);
+        assert_eq!(hz.max_restarts, Some(3));
+        assert_eq!(hz.backoff_ms, 100);
+
+        let rqt = &description.nodes[3];
+        assert_eq!(rqt.program, "rosrun");
+        assert_eq!(rqt.args, vec!["rqt_gui", "rqt_gui"]);
+        assert_eq!(rqt.restart, RestartPolicy::Never);
+    }
+
+    #[test]
+    pub fn launch_expand_env() {
+        env::set_var("LAUNCH_TEST_VAR", "dyse");
+
+        assert_eq!(expand_env("${LAUNCH_TEST_VAR}/data"), "dyse/data");
+        assert_eq!(
+            expand_env("a${LAUNCH_TEST_VAR}b${LAUNCH_TEST_VAR}"),
+            "adysebdyse"
+        );
+        assert_eq!(expand_env("${LAUNCH_TEST_UNSET}"), "${LAUNCH_TEST_UNSET}");
+        assert_eq!(expand_env("${LAUNCH_TEST_VAR"), "${LAUNCH_TEST_VAR");
+    }
+
+    #[test]
+    pub fn launch_backoff() {
+        let node = NodeDescription::new("node", "true", vec![]);
+
+        assert_eq!(node.backoff(0), Duration::from_millis(LAUNCH_BACKOFF_MS));
+        assert_eq!(
+            node.backoff(2),
+            Duration::from_millis(4 * LAUNCH_BACKOFF_MS)
+        );
+        assert_eq!(
+            node.backoff(64),
+            Duration::from_millis(LAUNCH_MAX_BACKOFF_MS)
+        );
+    }
+
+    #[test]
+    pub fn launch_respawn() {
+        let mut node = NodeDescription::new(
+            "fails",
+            "sh",
+            vec!["-c".to_string(), "echo failing; exit 1".to_string()],
+        );
+        node.restart = RestartPolicy::OnFailure;
+        node.max_restarts = Some(2);
+        node.backoff_ms = 1;
+
+        let mut launcher = Launcher::new(LaunchDescription {
+            robot: None,
+            nodes: vec![node, NodeDescription::new("passes", "true", vec![])],
+        });
+
+        launcher.start();
+
+        let t = Instant::now();
+        while launcher.supervise() {
+            assert!(t.elapsed().as_secs() < 10, "Nodes never finished");
+        }
+
+        assert_eq!(launcher.processes[0].restarts, 2);
+        assert!(!launcher.processes[0].status.unwrap().success());
+        assert_eq!(launcher.processes[1].restarts, 0);
+        assert!(launcher.processes[1].status.unwrap().success());
+    }
+
+    #[test]
+    pub fn launch_teardown() {
+        let mut launcher = Launcher::new(LaunchDescription {
+            robot: None,
+            nodes: vec![NodeDescription::new(
+                "sleeps",
+                "sleep",
+                vec!["30".to_string()],
+            )],
+        });
+
+        launcher.start();
+        assert!(launcher.supervise());
+
+        // only the launcher's own nodes are signalled, they don't need the grace period
+        let t = Instant::now();
+        launcher.teardown();
+        assert!(t.elapsed().as_millis() < LAUNCH_SHUTDOWN_GRACE_MS);
+        assert!(launcher.processes[0].is_done());
+        assert_eq!(
+            Some(libc::SIGTERM),
+            launcher.processes[0].status.unwrap().signal()
+        );
+    }
+}
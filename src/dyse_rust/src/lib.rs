@@ -1,6 +1,8 @@
 extern crate hidapi;
 extern crate socket2;
 
+pub mod launch;
+pub mod msgs;
 pub mod rid;
 // pub mod viz;
 // pub mod sandbox;
@@ -0,0 +1,147 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::{msgs::standard::Header, schema};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+schema! {
+    /// How healthy something is, from best to worst
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub enum DiagnosticLevel {
+        Ok,
+        Warn,
+        Error,
+        Stale,
+    }
+}
+
+impl fmt::Display for DiagnosticLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            DiagnosticLevel::Ok => "OK",
+            DiagnosticLevel::Warn => "WARN",
+            DiagnosticLevel::Error => "ERROR",
+            DiagnosticLevel::Stale => "STALE",
+        };
+        write!(f, "{name}")
+    }
+}
+
+schema! {
+    #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+    pub struct KeyValue {
+        pub key: String,
+        pub value: String,
+    }
+}
+
+schema! {
+    /// The state of one thing on the robot, `name` is a path
+    /// (`robot_fw/hid`) that the aggregator groups by
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct DiagnosticStatus {
+        pub level: DiagnosticLevel,
+        pub name: String,
+        pub message: String,
+        pub hardware_id: String,
+        pub values: Vec<KeyValue>,
+    }
+}
+
+impl DiagnosticStatus {
+    pub fn new(name: &str, level: DiagnosticLevel, message: &str) -> DiagnosticStatus {
+        DiagnosticStatus {
+            level,
+            name: name.to_string(),
+            message: message.to_string(),
+            hardware_id: String::new(),
+            values: vec![],
+        }
+    }
+
+    pub fn ok(name: &str, message: &str) -> DiagnosticStatus {
+        DiagnosticStatus::new(name, DiagnosticLevel::Ok, message)
+    }
+
+    pub fn warn(name: &str, message: &str) -> DiagnosticStatus {
+        DiagnosticStatus::new(name, DiagnosticLevel::Warn, message)
+    }
+
+    pub fn error(name: &str, message: &str) -> DiagnosticStatus {
+        DiagnosticStatus::new(name, DiagnosticLevel::Error, message)
+    }
+
+    /// Add a detail, replacing any value it already had
+    pub fn with<T: fmt::Display>(mut self, key: &str, value: T) -> DiagnosticStatus {
+        let value = value.to_string();
+        match self.values.iter_mut().find(|kv| kv.key == key) {
+            Some(kv) => kv.value = value,
+            None => self.values.push(KeyValue {
+                key: key.to_string(),
+                value,
+            }),
+        }
+        self
+    }
+
+    pub fn hardware(mut self, hardware_id: &str) -> DiagnosticStatus {
+        self.hardware_id = hardware_id.to_string();
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|kv| kv.key == key)
+            .map(|kv| kv.value.as_str())
+    }
+}
+
+impl fmt::Display for DiagnosticStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.level, self.name, self.message)?;
+        match self.values.is_empty() {
+            true => Ok(()),
+            false => write!(
+                f,
+                " ({})",
+                self.values
+                    .iter()
+                    .map(|kv| format!("{}: {}", kv.key, kv.value))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+schema! {
+    /// Statuses sent together, by one node or by the aggregator
+    #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+    pub struct DiagnosticArray {
+        pub header: Header,
+        pub status: Vec<DiagnosticStatus>,
+    }
+}
+
+impl DiagnosticArray {
+    /// The worst level in the array
+    pub fn level(&self) -> DiagnosticLevel {
+        self.status
+            .iter()
+            .map(|status| status.level)
+            .max()
+            .unwrap_or(DiagnosticLevel::Ok)
+    }
+}
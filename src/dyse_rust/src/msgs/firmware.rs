@@ -0,0 +1,39 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::schema;
+use serde::{Deserialize, Serialize};
+
+/// Sends TaskMarshalls for the whole firmware (i.e. [`TaskMarshallType::Save`])
+pub const FIRMWARE_CTRL_NAME: &str = "robot_fw/ctrl";
+
+schema! {
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub enum TaskMarshallType {
+        Input,
+        Output,
+        Parameter,
+        Save,
+    }
+}
+
+schema! {
+    /// Changes a firmware task's data (`name` is the task) or asks the whole
+    /// firmware to do something (`name` is [`FIRMWARE_CTRL_NAME`])
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct TaskMarshall {
+        pub name: String,
+        pub data: Vec<f64>,
+        pub mode: TaskMarshallType,
+    }
+}
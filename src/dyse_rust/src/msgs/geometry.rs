@@ -0,0 +1,103 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::schema;
+use serde::{Deserialize, Serialize};
+
+schema! {
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+    pub struct Vector3 {
+        pub x: f64,
+        pub y: f64,
+        pub z: f64,
+    }
+}
+
+impl Vector3 {
+    pub fn new(x: f64, y: f64, z: f64) -> Vector3 {
+        Vector3 { x, y, z }
+    }
+
+    /// The first three values of `data`
+    pub fn from_slice(data: &[f64]) -> Option<Vector3> {
+        match data {
+            [x, y, z, ..] => Some(Vector3::new(*x, *y, *z)),
+            _ => None,
+        }
+    }
+
+    pub fn scale(&self, s: f64) -> Vector3 {
+        Vector3::new(s * self.x, s * self.y, s * self.z)
+    }
+
+    pub fn norm(&self) -> f64 {
+        (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+}
+
+schema! {
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+    pub struct Quaternion {
+        pub x: f64,
+        pub y: f64,
+        pub z: f64,
+        pub w: f64,
+    }
+}
+
+impl Quaternion {
+    pub fn identity() -> Quaternion {
+        Quaternion {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            w: 1.0,
+        }
+    }
+
+    /// Rotation from roll, pitch and yaw (radians, applied in that order)
+    pub fn from_euler(roll: f64, pitch: f64, yaw: f64) -> Quaternion {
+        let (sr, cr) = (0.5 * roll).sin_cos();
+        let (sp, cp) = (0.5 * pitch).sin_cos();
+        let (sy, cy) = (0.5 * yaw).sin_cos();
+
+        Quaternion {
+            x: sr * cp * cy - cr * sp * sy,
+            y: cr * sp * cy + sr * cp * sy,
+            z: cr * cp * sy - sr * sp * cy,
+            w: cr * cp * cy + sr * sp * sy,
+        }
+    }
+}
+
+impl Default for Quaternion {
+    fn default() -> Quaternion {
+        Quaternion::identity()
+    }
+}
+
+schema! {
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+    pub struct Pose {
+        pub position: Vector3,
+        pub orientation: Quaternion,
+    }
+}
+
+schema! {
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+    pub struct Twist {
+        pub linear: Vector3,
+        pub angular: Vector3,
+    }
+}
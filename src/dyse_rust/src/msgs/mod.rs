@@ -0,0 +1,8 @@
+pub mod test;
+
+pub mod diagnostics;
+pub mod firmware;
+pub mod geometry;
+pub mod logging;
+pub mod sensors;
+pub mod standard;
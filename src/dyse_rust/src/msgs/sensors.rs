@@ -0,0 +1,101 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::{
+    msgs::{geometry::*, standard::*},
+    schema,
+};
+use serde::{Deserialize, Serialize};
+
+/// Row major 3x3 covariance, all zeros means unknown
+pub type Covariance3 = [f64; 9];
+
+schema! {
+    /// Like ROS, set `orientation_covariance[0]` to -1 when there is no
+    /// orientation estimate
+    #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+    pub struct Imu {
+        pub header: Header,
+        pub orientation: Quaternion,
+        pub orientation_covariance: Covariance3,
+        pub angular_velocity: Vector3,
+        pub angular_velocity_covariance: Covariance3,
+        pub linear_acceleration: Vector3,
+        pub linear_acceleration_covariance: Covariance3,
+    }
+}
+
+impl Imu {
+    /// Rates (rad/s) and acceleration (m/s^2) without an orientation
+    pub fn raw(header: Header, angular_velocity: Vector3, linear_acceleration: Vector3) -> Imu {
+        let mut orientation_covariance = [0.0; 9];
+        orientation_covariance[0] = -1.0;
+
+        Imu {
+            header,
+            orientation_covariance,
+            angular_velocity,
+            linear_acceleration,
+            ..Default::default()
+        }
+    }
+
+    pub fn has_orientation(&self) -> bool {
+        self.orientation_covariance[0] >= 0.0
+    }
+}
+
+schema! {
+    /// Field in tesla
+    #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+    pub struct MagneticField {
+        pub header: Header,
+        pub magnetic_field: Vector3,
+        pub magnetic_field_covariance: Covariance3,
+    }
+}
+
+schema! {
+    /// Joints are matched by index, missing vectors mean the value isn't measured
+    #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+    pub struct JointState {
+        pub header: Header,
+        pub name: Vec<String>,
+        pub position: Vec<f64>,
+        pub velocity: Vec<f64>,
+        pub effort: Vec<f64>,
+    }
+}
+
+impl JointState {
+    pub fn position_of(&self, joint: &str) -> Option<f64> {
+        self.name
+            .iter()
+            .position(|name| name == joint)
+            .and_then(|i| self.position.get(i).copied())
+    }
+}
+
+schema! {
+    /// Volts, amps and amp hours, `percentage` is 0 to 1 (NaN if unknown)
+    #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+    pub struct BatteryState {
+        pub header: Header,
+        pub voltage: f64,
+        pub current: f64,
+        pub charge: f64,
+        pub capacity: f64,
+        pub percentage: f64,
+        pub present: bool,
+    }
+}
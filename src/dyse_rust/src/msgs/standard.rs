@@ -0,0 +1,81 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::schema;
+use serde::{Deserialize, Serialize};
+
+schema! {
+    /// Who produced a message and when, `stamp` is micros since the UNIX
+    /// epoch (same as [`crate::socks::message::MessageStamp`])
+    #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+    pub struct Header {
+        pub seq: u32,
+        pub stamp: i64,
+        pub frame_id: String,
+    }
+}
+
+impl Header {
+    pub fn new(frame_id: &str, seq: u32, stamp: i64) -> Header {
+        Header {
+            seq,
+            stamp,
+            frame_id: frame_id.to_string(),
+        }
+    }
+
+    /// Stamp in seconds since the UNIX epoch
+    pub fn seconds(&self) -> f64 {
+        self.stamp as f64 * 1E-6
+    }
+}
+
+schema! {
+    /// A bag of floats where each value has a name
+    #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+    pub struct Float64Array {
+        pub header: Header,
+        pub labels: Vec<String>,
+        pub data: Vec<f64>,
+    }
+}
+
+impl Float64Array {
+    /// Values labeled by position ("0", "1", ...)
+    pub fn new(header: Header, data: Vec<f64>) -> Float64Array {
+        Float64Array {
+            header,
+            labels: (0..data.len()).map(|i| i.to_string()).collect(),
+            data,
+        }
+    }
+
+    /// None if there isn't a label for every value
+    pub fn labeled(header: Header, labels: Vec<&str>, data: Vec<f64>) -> Option<Float64Array> {
+        match labels.len() == data.len() {
+            true => Some(Float64Array {
+                header,
+                labels: labels.into_iter().map(|l| l.to_string()).collect(),
+                data,
+            }),
+            false => None,
+        }
+    }
+
+    pub fn get(&self, label: &str) -> Option<f64> {
+        self.labels
+            .iter()
+            .position(|l| l == label)
+            .and_then(|i| self.data.get(i).copied())
+    }
+}
@@ -0,0 +1,117 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+#![allow(unused_imports)]
+use crate::{
+    msgs::{geometry::*, sensors::*, standard::*},
+    rid::robot_firmware::*,
+    socks::{codec::*, loopback::*, schema::*, socks::*},
+};
+
+#[cfg(test)]
+pub mod msgs {
+    use super::*;
+    use crate::socks::sock_tests::loopback;
+
+    #[test]
+    pub fn msgs_geometry() {
+        let v = Vector3::from_slice(&[3.0, 4.0, 0.0, 9.0]).unwrap();
+        assert_eq!(v, Vector3::new(3.0, 4.0, 0.0));
+        assert_eq!(v.norm(), 5.0);
+        assert_eq!(Vector3::from_slice(&[1.0, 2.0]), None);
+
+        assert_eq!(Quaternion::default(), Quaternion::from_euler(0.0, 0.0, 0.0));
+
+        let q = Quaternion::from_euler(0.0, 0.0, std::f64::consts::PI);
+        assert!(q.z > 1.0 - 1E-9 && q.w.abs() < 1E-9, "{q:?}");
+
+        let pose = Pose::default();
+        assert_eq!(pose.orientation.w, 1.0);
+        assert_eq!(Twist::default().angular, Vector3::default());
+    }
+
+    #[test]
+    pub fn msgs_labels() {
+        let header = Header::new("arm", 3, 1_500_000);
+        assert_eq!(header.seconds(), 1.5);
+
+        let array =
+            Float64Array::labeled(header.clone(), vec!["kp", "ki"], vec![1.0, 0.1]).unwrap();
+        assert_eq!(array.get("ki"), Some(0.1));
+        assert_eq!(array.get("kd"), None);
+        assert!(Float64Array::labeled(header.clone(), vec!["kp"], vec![1.0, 0.1]).is_none());
+        assert_eq!(
+            Float64Array::new(header.clone(), vec![2.0]).labels,
+            vec!["0".to_string()]
+        );
+
+        let joints = JointState {
+            header,
+            name: vec!["hip".to_string(), "knee".to_string()],
+            position: vec![0.1, 0.2],
+            ..Default::default()
+        };
+        assert_eq!(joints.position_of("knee"), Some(0.2));
+        assert_eq!(joints.position_of("ankle"), None);
+    }
+
+    #[test]
+    pub fn msgs_lsm9ds1() {
+        let header = Header::new("lsm9ds1", 1, 0);
+        let data = [0.0, 0.0, 9.8, 0.2, 0.0, -0.4, 0.1, 0.2, 0.3];
+
+        let (imu, mag) = lsm9ds1_msgs(header, &data).unwrap();
+        assert_eq!(imu.linear_acceleration, Vector3::new(0.0, 0.0, 9.8));
+        assert_eq!(imu.angular_velocity, Vector3::new(0.1, 0.2, 0.3));
+        assert!(!imu.has_orientation());
+        assert_eq!(mag.magnetic_field, Vector3::new(0.2, 0.0, -0.4).scale(1E-4));
+        assert_eq!(mag.header.frame_id, "lsm9ds1");
+
+        assert!(lsm9ds1_msgs(Header::default(), &data[..6]).is_none());
+    }
+
+    #[test]
+    pub fn msgs_over_socks() {
+        let net = loopback::network(LoopbackConfig::ideal());
+
+        let mut source = Sock::loopback("lsm9ds1/imu", vec![], &net);
+        let mut sinc = Sock::loopback("sinc", vec!["lsm9ds1/imu"], &net);
+        let mut json = Sock::loopback("inspect", vec!["lsm9ds1/imu"], &net);
+
+        let header = Header::new("lsm9ds1", 7, 42);
+        let imu = Imu::raw(
+            header,
+            Vector3::new(0.1, 0.2, 0.3),
+            Vector3::new(0.0, 0.0, 9.8),
+        );
+        source.advertise_schema::<Imu>("lsm9ds1/imu");
+        source.tx_payload(imu.clone());
+
+        assert_eq!(loopback::drain(&mut sinc), vec![0]);
+        assert_eq!(sinc.decode_message::<Imu>(0), Ok(imu.clone()));
+
+        let value = sinc.decode(0).unwrap().unwrap();
+        let header = value.get("header").unwrap();
+        assert_eq!(header.get("seq"), Some(&Value::UInt(7)));
+        assert_eq!(
+            value.get("linear_acceleration").unwrap().get("z"),
+            Some(&Value::Float(9.8))
+        );
+
+        // same message readable by non-rust tools
+        source.set_codec(CodecId::Json);
+        source.tx_payload(imu.clone());
+        assert_eq!(loopback::drain(&mut json), vec![0, 0]);
+        assert_eq!(json.decode_message::<Imu>(0), Ok(imu));
+    }
+}
@@ -0,0 +1,283 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::{
+    rid::{
+        data_structures::HidControlFlags, interface::HidInterface, layer::HidConfig,
+        reader::HidReader, robot_firmware::RobotFirmware, task_graph::TaskGraph,
+        transport::TransportConfig, writer::HidWriter,
+    },
+    socks::{
+        names::{join_name, normalize_name, NameResolver},
+        sockapi,
+    },
+    utilities::loaders::{BuffYamlUtil, ByuParseError},
+};
+use log::info;
+use std::{
+    thread::{self, Builder},
+    time::Instant,
+};
+use yaml_rust::Yaml;
+
+/// nodes.yaml section listing the mcus when a robot has more than one
+pub const MCUS_ITEM: &str = "mcus";
+/// Task list (in the robot's directory) of a board that doesn't name one
+pub const DEFAULT_TASKS_YAML: &str = "firmware_tasks";
+
+/// One mcu of the robot, its link, its tasks and where its topics go.
+/// Every board shares the ids and timing at the top of the file ([`HidConfig`]).
+///
+/// ```yaml
+/// mcus:
+///   front:
+///     tasks: front_tasks
+///     mcu_transport: {type: hid, serial_number: 1234560}
+///   rear:
+///     mcu_transport: {type: serial, path: /dev/ttyACM1}
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct McuConfig {
+    /// topics of the board go under this name, empty for a lone mcu
+    pub name: String,
+    pub tasks: String,
+    pub hid: HidConfig,
+    pub transport: TransportConfig,
+}
+
+impl Default for McuConfig {
+    fn default() -> Self {
+        McuConfig {
+            name: String::new(),
+            tasks: DEFAULT_TASKS_YAML.to_string(),
+            hid: HidConfig::default(),
+            transport: TransportConfig::default(),
+        }
+    }
+}
+
+impl McuConfig {
+    /// Every board in [`MCUS_ITEM`], or the one mcu described at the top of the file
+    pub fn all_from_byu(byu: &BuffYamlUtil) -> Result<Vec<McuConfig>, ByuParseError> {
+        let hid = HidConfig::from_byu(byu)?;
+        let configs = match &byu.data()[MCUS_ITEM] {
+            Yaml::BadValue => vec![McuConfig {
+                hid,
+                transport: TransportConfig::from_byu(byu)?,
+                ..McuConfig::default()
+            }],
+            Yaml::Hash(boards) if !boards.is_empty() => boards
+                .iter()
+                .map(|(name, data)| McuConfig::parse(byu, &hid, name, data))
+                .collect::<Result<Vec<McuConfig>, ByuParseError>>()?,
+            _ => return Err(ByuParseError::item(MCUS_ITEM, &byu.yaml_path)),
+        };
+
+        McuConfig::validate(&configs, &byu.yaml_path)?;
+        Ok(configs)
+    }
+
+    fn parse(
+        byu: &BuffYamlUtil,
+        hid: &HidConfig,
+        name: &Yaml,
+        data: &Yaml,
+    ) -> Result<McuConfig, ByuParseError> {
+        let name = name
+            .as_str()
+            .map(normalize_name)
+            .filter(|name| !name.is_empty())
+            .ok_or(ByuParseError::string(MCUS_ITEM, &byu.yaml_path))?;
+
+        let tasks = match data["tasks"].is_badvalue() {
+            true => DEFAULT_TASKS_YAML.to_string(),
+            false => byu.parse_str("tasks", data).map_err(|_| {
+                ByuParseError::string(&format!("{MCUS_ITEM}/{name}/tasks"), &byu.yaml_path)
+            })?,
+        };
+
+        Ok(McuConfig {
+            tasks,
+            hid: hid.clone(),
+            transport: TransportConfig::parse(byu, data)?,
+            name,
+        })
+    }
+
+    /// Two boards can't share a name or a link, hid boards on the same ids
+    /// need a serial_number or path to tell them apart
+    fn validate(configs: &[McuConfig], yaml_path: &str) -> Result<(), ByuParseError> {
+        configs
+            .iter()
+            .enumerate()
+            .flat_map(|(i, a)| configs[i + 1..].iter().map(move |b| (a, b)))
+            .try_for_each(|(a, b)| {
+                match (a.name == b.name, a.transport == b.transport) {
+                    (true, _) => Err(format!("{MCUS_ITEM}/{}: unique names", a.name)),
+                    (_, true) => Err(format!(
+                        "{MCUS_ITEM}/{}, {}: different devices (serial_number or path)",
+                        a.name, b.name
+                    )),
+                    _ => Ok(()),
+                }
+                .map_err(|item| ByuParseError::new(item, yaml_path))
+            })
+    }
+
+    /// Boards of the robot from ROBOT_NAME (or self.txt)
+    pub fn all_from_env() -> Result<Vec<McuConfig>, ByuParseError> {
+        McuConfig::all_from_byu(&BuffYamlUtil::default("nodes"))
+    }
+
+    /// The process' names with the board's name added to the namespace
+    pub fn names(&self) -> NameResolver {
+        let mut names = NameResolver::from_env();
+        names.namespace = join_name(&names.namespace, &self.name);
+        names
+    }
+
+    /// The board's tasks, parameters are saved back to its own task list
+    pub fn robot_fw(&self) -> RobotFirmware {
+        let byu = BuffYamlUtil::default(&self.tasks);
+        let config_path = format!("{}/{}.yaml", byu.yaml_path, self.tasks);
+        let mut robot_fw = RobotFirmware::with_names(byu, self.names());
+        robot_fw.config_path = Some(config_path);
+        robot_fw
+    }
+
+    /// The board's task graph, feedback checked against the board's report rate
+    pub fn task_graph(&self) -> TaskGraph {
+        let mut graph = TaskGraph::from_byu(&BuffYamlUtil::default(&self.tasks));
+        graph.report_rate = 1E6 / self.hid.cycle_time_us;
+        graph
+    }
+}
+
+/// Several mcus driven from one process. Each board is a [`HidInterface`]
+/// with its own reader, writer and control threads, tasks and namespace, so
+/// one board (re)connecting or configuring doesn't hold up the others.
+pub struct MultiHidInterface {
+    pub names: Vec<String>,
+    pub boards: Vec<HidInterface>,
+}
+
+impl MultiHidInterface {
+    pub fn from_configs(
+        configs: &[McuConfig],
+    ) -> (MultiHidInterface, Vec<HidReader>, Vec<HidWriter>) {
+        MultiHidInterface::with_boards(
+            configs
+                .iter()
+                .map(|config| {
+                    (
+                        config.name.clone(),
+                        HidInterface::from_config(
+                            config.hid.clone(),
+                            &config.transport,
+                            config.robot_fw(),
+                        ),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    pub fn with_boards(
+        boards: Vec<(String, (HidInterface, HidReader, HidWriter))>,
+    ) -> (MultiHidInterface, Vec<HidReader>, Vec<HidWriter>) {
+        let mut multi = MultiHidInterface {
+            names: vec![],
+            boards: vec![],
+        };
+        let mut readers = vec![];
+        let mut writers = vec![];
+
+        boards
+            .into_iter()
+            .for_each(|(name, (board, reader, writer))| {
+                multi.names.push(name);
+                multi.boards.push(board);
+                readers.push(reader);
+                writers.push(writer);
+            });
+
+        (multi, readers, writers)
+    }
+
+    pub fn board(&self, name: &str) -> Option<&HidInterface> {
+        self.names
+            .iter()
+            .position(|n| n == name)
+            .map(|i| &self.boards[i])
+    }
+
+    /// One board shutting down stops all of them
+    pub fn is_shutdown(&self) -> bool {
+        self.boards
+            .iter()
+            .any(|board| board.layer.control_flags.is_shutdown())
+    }
+
+    pub fn shutdown(&self) {
+        self.boards
+            .iter()
+            .for_each(|board| board.layer.control_flags.shutdown());
+    }
+
+    /// Cycle every board on its own thread until one shuts down, each
+    /// connects on its own
+    pub fn pipeline(&mut self) {
+        if self.boards.is_empty() {
+            return;
+        }
+
+        info!("Live, {} mcus", self.boards.len());
+
+        let flags: Vec<HidControlFlags> = self
+            .boards
+            .iter()
+            .map(|board| board.layer.control_flags.clone())
+            .collect();
+
+        thread::scope(|scope| {
+            self.names
+                .iter()
+                .zip(self.boards.iter_mut())
+                .for_each(|(name, board)| {
+                    let flags = &flags;
+                    Builder::new()
+                        .name(format!("HID Control {name}"))
+                        .spawn_scoped(scope, move || {
+                            while !flags.iter().any(|flags| flags.is_shutdown()) {
+                                let loopt = Instant::now();
+                                board.spin_once();
+                                board.cycled(board.layer.delay(loopt));
+                            }
+
+                            flags.iter().for_each(|flags| flags.shutdown());
+                        })
+                        .unwrap();
+                });
+        });
+
+        sockapi::shutdown();
+        info!("shutdown");
+        self.names
+            .iter()
+            .zip(self.boards.iter())
+            .for_each(|(name, board)| {
+                info!("mcu {name}");
+                board.layer.print();
+            });
+    }
+}
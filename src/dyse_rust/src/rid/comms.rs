@@ -0,0 +1,101 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use dyse_rust::{
+    rid::{
+        boards::{McuConfig, MultiHidInterface},
+        interface::HidInterface,
+    },
+    socks::logging,
+};
+use log::{error, warn};
+use std::{env, thread::Builder};
+
+/// Run the hid pipeline
+/// ```text
+/// comms [sil|graph]
+/// ```
+/// Talks to every mcu in the robot's nodes.yaml (and stops if it doesn't check out),
+/// `sil` runs against the firmware emulator instead of a Teensy, `graph` checks every
+/// board's tasks and prints them as graphviz dot (issues are logged to stderr)
+fn main() {
+    let logger = logging::init_from_env();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let (mut interface, readers, writers) = match args
+        .iter()
+        .map(|s| s as &str)
+        .collect::<Vec<&str>>()
+        .as_slice()
+    {
+        [] => match McuConfig::all_from_env() {
+            Ok(configs) => MultiHidInterface::from_configs(&configs),
+            Err(e) => {
+                error!("{e}");
+                return;
+            }
+        },
+        ["sil"] => MultiHidInterface::with_boards(vec![(String::new(), HidInterface::sil())]),
+        ["graph"] => {
+            logger.print_to_stderr(true);
+            match McuConfig::all_from_env() {
+                Ok(configs) => configs.iter().for_each(|config| {
+                    let graph = config.task_graph();
+                    graph
+                        .validate()
+                        .iter()
+                        .for_each(|issue| match issue.is_error() {
+                            true => error!("{} {}: {issue}", config.tasks, config.name),
+                            false => warn!("{} {}: {issue}", config.tasks, config.name),
+                        });
+                    print!("{}", graph.to_dot());
+                }),
+                Err(e) => error!("{e}"),
+            }
+            return;
+        }
+        _ => {
+            error!("usage: comms [sil|graph]");
+            return;
+        }
+    };
+
+    let reader_handles: Vec<_> = readers
+        .into_iter()
+        .map(|mut reader| {
+            Builder::new()
+                .name("HID Reader".to_string())
+                .spawn(move || reader.pipeline())
+                .unwrap()
+        })
+        .collect();
+
+    let writer_handles: Vec<_> = writers
+        .into_iter()
+        .map(|mut writer| {
+            Builder::new()
+                .name("HID Writer".to_string())
+                .spawn(move || writer.pipeline())
+                .unwrap()
+        })
+        .collect();
+
+    interface.pipeline();
+
+    reader_handles
+        .into_iter()
+        .for_each(|handle| handle.join().expect("[HID-Reader]: failed"));
+    writer_handles
+        .into_iter()
+        .for_each(|handle| handle.join().expect("[HID-Writer]: failed"));
+}
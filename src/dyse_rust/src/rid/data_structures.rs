@@ -12,7 +12,10 @@
  ********************************************************************************/
 
 use chrono::{DateTime, Utc};
-use std::sync::{Arc, RwLock};
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
 
 pub const HID_PACKET_SIZE: usize = 64;
 
@@ -25,6 +28,17 @@ pub const HID_RUCT_INDEX: usize = 52;
 pub const HID_PCTS_INDEX: usize = 56;
 pub const HID_UCTS_INDEX: usize = 60;
 
+// task init packet, matches init_task_hid() in the firmware
+pub const HID_INIT_RATE_INDEX: usize = 3;
+pub const HID_INIT_KEY_INDEX: usize = 5;
+pub const HID_INIT_N_INPUTS_INDEX: usize = 10;
+pub const HID_INIT_INPUTS_INDEX: usize = 11;
+
+// report trailer (see rid::report), task data ends before it
+pub const HID_VERSION_INDEX: usize = 43;
+pub const HID_SEQ_INDEX: usize = 44;
+pub const HID_CRC_INDEX: usize = 46;
+
 pub type HidPacket = [u8; HID_PACKET_SIZE];
 
 #[derive(Clone)]
@@ -98,20 +112,45 @@ impl NetFlowStats {
     }
 }
 
+/// Where the link to the mcu is, each state includes the ones before it
+/// (an initialized mcu is also connected)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LinkState {
+    Disconnected,
+    Searching,
+    Connected,
+    Initialized,
+    Configured,
+}
+
+impl fmt::Display for LinkState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            LinkState::Disconnected => "disconnected",
+            LinkState::Searching => "searching",
+            LinkState::Connected => "connected",
+            LinkState::Initialized => "initialized",
+            LinkState::Configured => "configured",
+        };
+        write!(f, "{name}")
+    }
+}
+
 #[derive(Clone)]
 pub struct HidControlFlags {
     // Logic flags to cause events in other threads
     shutdown: Arc<RwLock<bool>>,
-    connected: Arc<RwLock<bool>>,
-    initialized: Arc<RwLock<bool>>,
+    state: Arc<RwLock<LinkState>>,
+    // counts the times the link came up, handles remember which one they joined
+    connection: Arc<RwLock<u64>>,
 }
 
 impl HidControlFlags {
     pub fn new() -> HidControlFlags {
         HidControlFlags {
             shutdown: Arc::new(RwLock::new(false)),
-            connected: Arc::new(RwLock::new(false)),
-            initialized: Arc::new(RwLock::new(false)),
+            state: Arc::new(RwLock::new(LinkState::Disconnected)),
+            connection: Arc::new(RwLock::new(0)),
         }
     }
 
@@ -127,32 +166,89 @@ impl HidControlFlags {
         *self.shutdown.write().unwrap() = false;
     }
 
-    pub fn is_connected(&self) -> bool {
-        *self.connected.read().unwrap()
+    pub fn state(&self) -> LinkState {
+        *self.state.read().unwrap()
     }
 
-    pub fn connect(&self) {
-        *self.connected.write().unwrap() = true;
+    /// Move to `to` if the link is in one of `from`, returns if it moved
+    fn transition(&self, from: &[LinkState], to: LinkState) -> bool {
+        let mut state = self.state.write().unwrap();
+        match from.contains(&state) {
+            true => {
+                *state = to;
+                true
+            }
+            false => false,
+        }
     }
 
-    pub fn disconnect(&self) {
-        *self.connected.write().unwrap() = false;
+    /// Another thread might have found the mcu already, only a lost link searches
+    pub fn search(&self) {
+        self.transition(&[LinkState::Disconnected], LinkState::Searching);
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state() >= LinkState::Connected
+    }
+
+    /// A handle opened, returns the connection it belongs to (a new one
+    /// unless another thread already brought the link up)
+    pub fn connect(&self) -> u64 {
+        let mut connection = self.connection.write().unwrap();
+        if self.transition(
+            &[LinkState::Disconnected, LinkState::Searching],
+            LinkState::Connected,
+        ) {
+            *connection += 1;
+        }
+        *connection
+    }
+
+    /// A handle from `connection` failed, the link only drops if that's the
+    /// current connection so stale handles can't take down a new one.
+    /// Returns if the link dropped.
+    pub fn disconnect(&self, connection: u64) -> bool {
+        let current = self.connection.read().unwrap();
+        *current == connection
+            && self.transition(
+                &[
+                    LinkState::Connected,
+                    LinkState::Initialized,
+                    LinkState::Configured,
+                ],
+                LinkState::Disconnected,
+            )
     }
 
     pub fn is_initialized(&self) -> bool {
-        *self.initialized.read().unwrap()
+        self.state() >= LinkState::Initialized
     }
 
+    /// Initializers were sent (or have to be sent again), only for a connected mcu
     pub fn initialize(&self, status: bool) {
-        *self.initialized.write().unwrap() = status;
+        match status {
+            true => self.transition(&[LinkState::Connected], LinkState::Initialized),
+            false => self.transition(
+                &[LinkState::Initialized, LinkState::Configured],
+                LinkState::Connected,
+            ),
+        };
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.state() >= LinkState::Configured
+    }
+
+    /// Every task took its parameters
+    pub fn configure(&self) {
+        self.transition(&[LinkState::Initialized], LinkState::Configured);
     }
 
     pub fn print(&self) {
         println!(
-            "\tShutdown: {}\n\tConnected: {}\n\tInitialized: {}",
+            "\tShutdown: {}\n\tLink: {}",
             self.is_shutdown(),
-            self.is_connected(),
-            self.is_initialized()
+            self.state()
         );
     }
 }
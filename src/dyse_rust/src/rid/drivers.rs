@@ -0,0 +1,219 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::rid::{
+    emulator::{COMPLIMENTARY_DRIVER, CONSTANT_DRIVER, LPF_DRIVER, PID_DRIVER, SIN_DRIVER},
+    robot_firmware::LSM9DS1_DRIVER,
+};
+use std::fmt;
+
+/// Driver keys only the firmware runs (firmware/src/task_manager/task_factory.h)
+pub const LSM6DSOX_DRIVER: &str = "LSM";
+pub const PWM_DRIVER: &str = "PWM";
+pub const TB6612FNG_DRIVER: &str = "FNG";
+
+/// A value a driver takes or gives, `unit` is empty when it has none
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DriverField {
+    pub name: &'static str,
+    pub unit: &'static str,
+}
+
+const fn field(name: &'static str, unit: &'static str) -> DriverField {
+    DriverField { name, unit }
+}
+
+impl fmt::Display for DriverField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.unit {
+            "" => write!(f, "{}", self.name),
+            unit => write!(f, "{} ({unit})", self.name),
+        }
+    }
+}
+
+/// What the pc knows about a firmware driver (firmware/src/tasks), see
+/// [`DRIVERS`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DriverSpec {
+    pub key: &'static str,
+    pub description: &'static str,
+    pub parameters: &'static [DriverField],
+    /// floats of input the driver runs on, None takes any
+    pub inputs: Option<usize>,
+    /// None when there's one output per input
+    pub outputs: Option<&'static [DriverField]>,
+}
+
+const IMU_OUTPUTS: [DriverField; 9] = [
+    field("accel x", "m/s^2"),
+    field("accel y", "m/s^2"),
+    field("accel z", "m/s^2"),
+    field("mag x", "gauss"),
+    field("mag y", "gauss"),
+    field("mag z", "gauss"),
+    field("gyro x", "rad/s"),
+    field("gyro y", "rad/s"),
+    field("gyro z", "rad/s"),
+];
+
+/// Every driver the firmware or the emulator runs. The firmware runs any
+/// other key as an empty task. LPF and PID only run in the emulator.
+pub const DRIVERS: &[DriverSpec] = &[
+    DriverSpec {
+        key: LSM6DSOX_DRIVER,
+        description: "LSM6DSOX + LIS3MDL imu",
+        parameters: &[],
+        inputs: Some(0),
+        outputs: Some(&[
+            field("accel x", "m/s^2"),
+            field("accel y", "m/s^2"),
+            field("accel z", "m/s^2"),
+            field("gyro x", "rad/s"),
+            field("gyro y", "rad/s"),
+            field("gyro z", "rad/s"),
+            field("mag x", "uT"),
+            field("mag y", "uT"),
+            field("mag z", "uT"),
+        ]),
+    },
+    DriverSpec {
+        key: LSM9DS1_DRIVER,
+        description: "LSM9DS1 imu",
+        parameters: &[],
+        inputs: Some(0),
+        outputs: Some(&IMU_OUTPUTS),
+    },
+    DriverSpec {
+        key: PWM_DRIVER,
+        description: "pwm output of its input in [0, 1]",
+        parameters: &[field("pin", "")],
+        inputs: Some(1),
+        outputs: Some(&[field("duty", "1/32768")]),
+    },
+    DriverSpec {
+        key: TB6612FNG_DRIVER,
+        description: "TB6612FNG dual motor driver",
+        parameters: &[
+            field("standby pin", ""),
+            field("a in 1 pin", ""),
+            field("a in 2 pin", ""),
+            field("b in 1 pin", ""),
+            field("b in 2 pin", ""),
+            field("a pwm pin", ""),
+            field("b pwm pin", ""),
+        ],
+        inputs: Some(1),
+        outputs: Some(&[
+            field("enabled", ""),
+            field("direction", ""),
+            field("a speed", "1/32768"),
+            field("b speed", "1/32768"),
+        ]),
+    },
+    DriverSpec {
+        key: COMPLIMENTARY_DRIVER,
+        description: "complimentary filter attitude",
+        parameters: &[field("gain", "")],
+        inputs: Some(12),
+        outputs: Some(&[
+            field("roll", "rad"),
+            field("pitch", "rad"),
+            field("yaw", "rad"),
+        ]),
+    },
+    DriverSpec {
+        key: CONSTANT_DRIVER,
+        description: "constant, truncated to an int",
+        parameters: &[field("value", "")],
+        inputs: Some(0),
+        outputs: Some(&[field("value", "")]),
+    },
+    DriverSpec {
+        key: SIN_DRIVER,
+        description: "sinusoid",
+        parameters: &[
+            field("frequency", "rad/s"),
+            field("amplitude", ""),
+            field("shift", ""),
+        ],
+        inputs: Some(0),
+        outputs: Some(&[field("signal", "")]),
+    },
+    DriverSpec {
+        key: LPF_DRIVER,
+        description: "low pass filter on every input, emulator only",
+        parameters: &[field("gain", "")],
+        inputs: None,
+        outputs: None,
+    },
+    DriverSpec {
+        key: PID_DRIVER,
+        description: "pd controller, emulator only",
+        parameters: &[
+            field("p", ""),
+            field("i (unused)", ""),
+            field("d", ""),
+            field("f (unused)", ""),
+        ],
+        inputs: Some(2),
+        outputs: Some(&[field("effort", "")]),
+    },
+];
+
+/// The driver with `key`, None if neither the firmware nor the emulator has it
+pub fn driver_spec(key: &str) -> Option<&'static DriverSpec> {
+    DRIVERS.iter().find(|spec| spec.key == key)
+}
+
+fn list(fields: &[DriverField]) -> String {
+    let names: Vec<String> = fields.iter().map(|field| field.to_string()).collect();
+    format!("[{}]", names.join(", "))
+}
+
+impl DriverSpec {
+    /// Parameters are all or nothing, the firmware won't set up a task
+    /// until it has every one
+    pub fn check_parameters(&self, parameters: &[f64]) -> Result<(), String> {
+        match parameters.len() == self.parameters.len() {
+            true => Ok(()),
+            false => Err(format!(
+                "{} takes {} parameters {}, got {}",
+                self.key,
+                self.parameters.len(),
+                list(self.parameters),
+                parameters.len()
+            )),
+        }
+    }
+
+    /// Number of outputs given `inputs` floats of input
+    pub fn n_outputs(&self, inputs: usize) -> usize {
+        self.outputs.map_or(inputs, |outputs| outputs.len())
+    }
+
+    /// Names for `n` outputs, the index when the driver doesn't name them
+    pub fn output_labels(&self, n: usize) -> Vec<String> {
+        (0..n)
+            .map(|i| match self.outputs.and_then(|outputs| outputs.get(i)) {
+                Some(field) => field.to_string(),
+                None => format!("{i}"),
+            })
+            .collect()
+    }
+
+    /// `parameters` with their names, like a firmware_tasks.yaml comment
+    pub fn describe_parameters(&self) -> String {
+        format!("{} parameters {}", self.key, list(self.parameters))
+    }
+}
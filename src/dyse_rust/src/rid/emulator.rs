@@ -0,0 +1,304 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::{
+    rid::{
+        mock::{MockDriver, MockEcho, MockFirmware, MockHid},
+        robot_firmware::LSM9DS1_DRIVER,
+    },
+    socks::clocks::Clock,
+};
+use std::f64::consts::PI;
+
+/// Driver keys the emulator runs (firmware/src/task_manager/task_factory.h).
+/// LPF and PID are emulator only, they run the firmware's filters (lp_filter.h,
+/// pid_filter.h) but the firmware has no tasks for them and runs them empty.
+pub const SIN_DRIVER: &str = "SIN";
+pub const CONSTANT_DRIVER: &str = "VAL";
+pub const LPF_DRIVER: &str = "LPF";
+pub const PID_DRIVER: &str = "PID";
+pub const COMPLIMENTARY_DRIVER: &str = "CMF";
+
+/// firmware/src/tasks/complimentary_filter.h DEFAULT_GAIN
+pub const CMF_DEFAULT_GAIN: f64 = 0.4;
+/// firmware/src/tasks/lp_filter.h default gain
+pub const LPF_DEFAULT_GAIN: f64 = 0.4;
+
+/// `amplitude * sin(frequency * t) + shift`, t wraps at 2pi like the firmware
+/// - parameters: [frequency, amplitude, shift]
+/// - outputs: [signal]
+#[derive(Default)]
+pub struct SinTask {
+    pub frequency: f64,
+    pub amplitude: f64,
+    pub shift: f64,
+    pub counter: f64,
+}
+
+impl MockDriver for SinTask {
+    fn is_configured(&self, parameters: &[f64]) -> bool {
+        parameters.len() == 3
+    }
+
+    fn setup(&mut self, parameters: &[f64]) {
+        self.frequency = parameters[0];
+        self.amplitude = parameters[1];
+        self.shift = parameters[2];
+    }
+
+    fn n_inputs(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    fn n_outputs(&self) -> usize {
+        1
+    }
+
+    fn run(&mut self, _inputs: &[f64], dt: f64) -> Vec<f64> {
+        self.counter = (self.counter + dt) % (2.0 * PI);
+        vec![self.amplitude * (self.frequency * self.counter).sin() + self.shift]
+    }
+}
+
+/// Outputs its parameter, truncated to an int like the firmware's ConstTask
+/// - parameters: [value]
+/// - outputs: [value]
+#[derive(Default)]
+pub struct ConstTask {
+    pub value: f64,
+}
+
+impl MockDriver for ConstTask {
+    fn is_configured(&self, parameters: &[f64]) -> bool {
+        parameters.len() == 1
+    }
+
+    fn setup(&mut self, parameters: &[f64]) {
+        self.value = parameters[0].trunc();
+    }
+
+    fn n_inputs(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    fn n_outputs(&self) -> usize {
+        1
+    }
+
+    fn run(&mut self, _inputs: &[f64], _dt: f64) -> Vec<f64> {
+        vec![self.value]
+    }
+}
+
+/// Low pass filter on every input, `y = k * y + (1 - k) * x`
+/// - parameters: [k]
+/// - outputs: one per input
+pub struct LpfTask {
+    pub gain: f64,
+    pub output: Vec<f64>,
+}
+
+impl Default for LpfTask {
+    fn default() -> Self {
+        LpfTask {
+            gain: LPF_DEFAULT_GAIN,
+            output: vec![],
+        }
+    }
+}
+
+impl MockDriver for LpfTask {
+    fn is_configured(&self, parameters: &[f64]) -> bool {
+        parameters.len() == 1
+    }
+
+    fn setup(&mut self, parameters: &[f64]) {
+        self.gain = parameters[0];
+        self.output.clear();
+    }
+
+    fn run(&mut self, inputs: &[f64], _dt: f64) -> Vec<f64> {
+        self.output.resize(inputs.len(), 0.0);
+        self.output
+            .iter_mut()
+            .zip(inputs)
+            .for_each(|(y, &x)| *y = (self.gain * *y) + ((1.0 - self.gain) * x));
+        self.output.clone()
+    }
+}
+
+/// The firmware's PIDFilter on `setpoint - measurement`, clamped to [-1, 1].
+/// Like PIDFilter only P and D are applied, I and F are ignored.
+/// - inputs: [setpoint, measurement]
+/// - parameters: [p, i, d, f]
+/// - outputs: [effort]
+#[derive(Default)]
+pub struct PidTask {
+    pub gains: [f64; 4],
+    pub prev_error: f64,
+}
+
+impl MockDriver for PidTask {
+    fn is_configured(&self, parameters: &[f64]) -> bool {
+        parameters.len() == 4
+    }
+
+    fn setup(&mut self, parameters: &[f64]) {
+        self.gains.copy_from_slice(parameters);
+        self.prev_error = 0.0;
+    }
+
+    fn n_inputs(&self) -> Option<usize> {
+        Some(2)
+    }
+
+    fn n_outputs(&self) -> usize {
+        1
+    }
+
+    fn run(&mut self, inputs: &[f64], dt: f64) -> Vec<f64> {
+        let error = inputs[0] - inputs[1];
+        let derivative = match dt > 0.0 {
+            true => (error - self.prev_error) / dt,
+            false => 0.0,
+        };
+        self.prev_error = error;
+
+        let [p, _, d, _] = self.gains;
+        let output = (p * error) + (d * derivative);
+        vec![output.clamp(-1.0, 1.0)]
+    }
+}
+
+/// Attitude from an accelerometer, gyro and magnetometer (ComplimentaryFilter)
+/// - inputs: [estimate (3), accel (3), gyro (3), mag (3)]
+/// - parameters: [k], weight of the accel/mag attitude
+/// - outputs: [roll, pitch, yaw]
+pub struct ComplimentaryTask {
+    pub gain: f64,
+}
+
+impl Default for ComplimentaryTask {
+    fn default() -> Self {
+        ComplimentaryTask {
+            gain: CMF_DEFAULT_GAIN,
+        }
+    }
+}
+
+impl ComplimentaryTask {
+    pub fn filter(
+        &self,
+        estimate: &[f64],
+        accel: &[f64],
+        gyro: &[f64],
+        mag: &[f64],
+        dt: f64,
+    ) -> Vec<f64> {
+        let axz_norm = accel[0].hypot(accel[2]);
+        let ayz_norm = accel[1].hypot(accel[2]);
+        let mag_norm = (mag[0].powi(2) + mag[1].powi(2) + mag[2].powi(2)).sqrt();
+
+        let roll = accel[1].atan2(axz_norm);
+        let pitch = (-accel[0]).atan2(ayz_norm);
+        let opposite = ((mag[2] * roll.sin()) - (mag[1] * roll.cos())) / mag_norm;
+        let adjacent = ((mag[0] * pitch.cos())
+            + (pitch.sin() * (mag[1] * roll.cos()) + (mag[2] * roll.sin())))
+            / mag_norm;
+        let yaw = match opposite.atan2(adjacent) {
+            yaw if yaw.is_nan() => 0.0,
+            yaw => yaw,
+        };
+
+        [roll, pitch, yaw]
+            .iter()
+            .zip(estimate.iter().zip(gyro))
+            .map(|(q_accel, (q, w))| (self.gain * q_accel) + ((1.0 - self.gain) * (q + w * dt)))
+            .collect()
+    }
+}
+
+impl MockDriver for ComplimentaryTask {
+    fn is_configured(&self, parameters: &[f64]) -> bool {
+        parameters.len() == 1
+    }
+
+    fn setup(&mut self, parameters: &[f64]) {
+        self.gain = parameters[0];
+    }
+
+    fn n_inputs(&self) -> Option<usize> {
+        Some(12)
+    }
+
+    fn n_outputs(&self) -> usize {
+        3
+    }
+
+    fn run(&mut self, inputs: &[f64], dt: f64) -> Vec<f64> {
+        self.filter(
+            &inputs[0..3],
+            &inputs[3..6],
+            &inputs[6..9],
+            &inputs[9..12],
+            dt,
+        )
+    }
+}
+
+/// A still, level LSM9DS1: gravity on z, no rotation and a field along x
+/// - outputs: [accel (3), mag (3), gyro (3)], same as the firmware
+#[derive(Default)]
+pub struct StillImu;
+
+impl MockDriver for StillImu {
+    fn is_configured(&self, parameters: &[f64]) -> bool {
+        parameters.is_empty()
+    }
+
+    fn n_inputs(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    fn n_outputs(&self) -> usize {
+        9
+    }
+
+    fn run(&mut self, _inputs: &[f64], _dt: f64) -> Vec<f64> {
+        vec![0.0, 0.0, 9.81, 0.2, 0.0, -0.4, 0.0, 0.0, 0.0]
+    }
+}
+
+/// [`MockDriverFactory`](crate::rid::mock::MockDriverFactory) for the
+/// emulated drivers, anything else is a [`MockEcho`]
+pub fn sil_driver(key: &str) -> Box<dyn MockDriver> {
+    match key {
+        SIN_DRIVER => Box::new(SinTask::default()),
+        CONSTANT_DRIVER => Box::new(ConstTask::default()),
+        LPF_DRIVER => Box::new(LpfTask::default()),
+        PID_DRIVER => Box::new(PidTask::default()),
+        COMPLIMENTARY_DRIVER => Box::new(ComplimentaryTask::default()),
+        LSM9DS1_DRIVER => Box::new(StillImu),
+        _ => Box::new(MockEcho::default()),
+    }
+}
+
+/// Firmware that runs the emulated drivers on `clock`
+pub fn sil_firmware(clock: Clock) -> MockFirmware {
+    MockFirmware::with_factory(clock, sil_driver)
+}
+
+/// Software in the loop mcu, a [`MockHid`] running [`sil_firmware`]
+pub fn sil_hid() -> MockHid {
+    MockHid::with_firmware(sil_firmware(Clock::real()))
+}
@@ -12,8 +12,20 @@
  ********************************************************************************/
 
 use crate::{
-    rid::{data_structures::*, layer::*, reader::*, robot_firmware::*, writer::*},
-    socks::sockapi,
+    msgs::diagnostics::*,
+    rid::{
+        data_structures::*,
+        emulator::sil_hid,
+        layer::*,
+        mock::MockHid,
+        reader::*,
+        report::{check_report, HidReportType, SequenceTracker},
+        robot_firmware::*,
+        transport::{HidTransport, TransportConfig},
+        writer::*,
+    },
+    socks::{diagnostics::DIAGNOSTICS_PERIOD_US, logging, names::join_name, sockapi},
+    utilities::loaders::{BuffYamlUtil, ByuParseError},
 };
 use std::{
     sync::mpsc::{channel, Receiver, Sender},
@@ -21,6 +33,7 @@ use std::{
 };
 
 use chrono::{DateTime, Utc};
+use log::{debug, info, log_enabled, Level};
 
 pub static MCU_NO_COMMS_TIMEOUT_S: u64 = 10;
 pub static MCU_NO_COMMS_RESET_MS: u128 = 10;
@@ -34,8 +47,59 @@ pub static TEENSY_CYCLE_TIME_ER: f64 = TEENSY_CYCLE_TIME_US + 50.0; // err thres
 pub static TEENSY_DEFAULT_VID: u16 = 0x16C0;
 pub static TEENSY_DEFAULT_PID: u16 = 0x0486;
 
+/// Link state changes are published here (a [`DiagnosticStatus`])
+pub const HID_STATUS_TOPIC: &str = "hid/status";
+
+/// Problems with the hid link, counted between diagnostics reports
+#[derive(Default)]
+pub struct HidHealth {
+    pub over_cycles: u64,
+    pub max_cycle_us: f64,
+    pub time_drifts: u64,
+    pub max_drift_s: f64,
+    pub unknown_reports: u64,
+    /// reports from the mcu that failed their crc or version check
+    pub corrupt_reports: u64,
+    /// reports from the mcu that never arrived (sequence gaps)
+    pub dropped_reports: u64,
+    /// the mcu's totals for our reports, from its last status report
+    pub mcu_dropped_reports: f64,
+    pub mcu_corrupt_reports: f64,
+}
+
+impl HidHealth {
+    pub fn status(&self, connected: bool) -> DiagnosticStatus {
+        let status = match (
+            connected,
+            self.time_drifts + self.unknown_reports + self.corrupt_reports > 0,
+            self.dropped_reports > 0,
+            self.over_cycles > 0,
+        ) {
+            (false, _, _, _) => DiagnosticStatus::error("hid", "mcu disconnected"),
+            (true, true, _, _) => DiagnosticStatus::warn("hid", "bad reports"),
+            (true, false, true, _) => DiagnosticStatus::warn("hid", "dropped reports"),
+            (true, false, false, true) => DiagnosticStatus::warn("hid", "over cycled"),
+            (true, false, false, false) => DiagnosticStatus::ok("hid", "connected"),
+        };
+
+        status
+            .with("over cycles", self.over_cycles)
+            .with("max cycle (s)", format!("{:.6}", 1E-6 * self.max_cycle_us))
+            .with("time drifts", self.time_drifts)
+            .with("max drift (s)", format!("{:.6}", self.max_drift_s))
+            .with("unknown reports", self.unknown_reports)
+            .with("corrupt reports", self.corrupt_reports)
+            .with("dropped reports", self.dropped_reports)
+            .with("dropped by mcu", self.mcu_dropped_reports)
+            .with("corrupt at mcu", self.mcu_corrupt_reports)
+    }
+}
+
 pub struct HidInterface {
     pub layer: HidLayer,
+    pub health: HidHealth,
+    // Sequence numbers of the mcu's reports
+    pub sequence: SequenceTracker,
 
     // For sending reports to the writer
     pub reader_rx: Receiver<(HidPacket, DateTime<Utc>)>,
@@ -43,28 +107,88 @@ pub struct HidInterface {
 
     // For storing reply data
     pub robot_fw: RobotFirmware,
+
+    // Last link state published and how many times the link was lost
+    pub link_state: LinkState,
+    pub disconnects: u64,
+
+    print_timer: Instant,
+    diagnostics_timer: Instant,
 }
 
 impl HidInterface {
-    pub fn new() -> (HidInterface, HidReader, HidWriter) {
-        let layer = HidLayer::new(TEENSY_DEFAULT_VID, TEENSY_DEFAULT_PID, TEENSY_CYCLE_TIME_US);
+    /// Talk to the mcu the robot's nodes.yaml asks for (hid if it doesn't say),
+    /// errors if the config doesn't check out
+    pub fn new() -> Result<(HidInterface, HidReader, HidWriter), ByuParseError> {
+        let byu = BuffYamlUtil::default("nodes");
+        let config = HidConfig::from_byu(&byu)?;
+        let transport = TransportConfig::from_byu(&byu)?;
+        Ok(HidInterface::from_config(
+            config,
+            &transport,
+            RobotFirmware::default(),
+        ))
+    }
+
+    pub fn from_config(
+        config: HidConfig,
+        transport: &TransportConfig,
+        robot_fw: RobotFirmware,
+    ) -> (HidInterface, HidReader, HidWriter) {
+        let layer = HidLayer::with_transport(config, transport.transport());
+        HidInterface::with_layer(layer, robot_fw)
+    }
+
+    /// Interface, reader and writer that talk to the mcu through `transport`
+    pub fn with_transport(
+        transport: Box<dyn HidTransport>,
+        robot_fw: RobotFirmware,
+    ) -> (HidInterface, HidReader, HidWriter) {
+        let layer = HidLayer::with_transport(HidConfig::default(), transport);
+        HidInterface::with_layer(layer, robot_fw)
+    }
+
+    pub fn with_layer(
+        layer: HidLayer,
+        mut robot_fw: RobotFirmware,
+    ) -> (HidInterface, HidReader, HidWriter) {
         let (writer_tx, writer_rx) = channel::<HidPacket>();
         let (reader_tx, reader_rx) = channel::<(HidPacket, DateTime<Utc>)>();
+        if logging::logger().is_some() {
+            robot_fw.sock.forward_logs();
+        }
+        let status_topic = robot_fw.sock.resolve(HID_STATUS_TOPIC);
+        robot_fw
+            .sock
+            .advertise_schema::<DiagnosticStatus>(&status_topic);
 
         (
             HidInterface {
                 layer: layer.clone(),
+                health: HidHealth::default(),
+                sequence: SequenceTracker::default(),
                 reader_rx: reader_rx,
                 writer_tx: writer_tx,
-                robot_fw: RobotFirmware::default(),
+                robot_fw,
+                link_state: LinkState::Disconnected,
+                disconnects: 0,
+                print_timer: Instant::now(),
+                diagnostics_timer: Instant::now(),
             },
             HidReader::new(layer.clone(), reader_tx),
             HidWriter::new(layer, writer_rx),
         )
     }
 
+    /// Interface to the emulated firmware, runs the robot's tasks on the pc
+    pub fn sil() -> (HidInterface, HidReader, HidWriter) {
+        HidInterface::with_transport(Box::new(sil_hid()), RobotFirmware::default())
+    }
+
+    /// Interface to mock firmware, no device needed
     pub fn sim() -> HidInterface {
-        let (hidui, _, _) = HidInterface::new();
+        let (hidui, _, _) =
+            HidInterface::with_transport(Box::new(MockHid::new()), RobotFirmware::default());
         hidui
     }
 
@@ -75,72 +199,92 @@ impl HidInterface {
         };
     }
 
+    /// Parse every report the reader passed along since the last call, so a
+    /// slow cycle doesn't leave a backlog of old reports. Reports that fail
+    /// their check are dropped, gaps in their sequence are counted.
     pub fn check_feedback(&mut self) {
-        match self.reader_rx.try_recv() {
-            Ok((buffer, datetime)) => {
-                let run_time = f32::from_le_bytes(
-                    buffer[HID_RUNT_INDEX..HID_RUNT_INDEX + 4]
-                        .try_into()
-                        .unwrap(),
-                ) as f64;
-
-                let replied_mcu_time = f32::from_le_bytes(
-                    buffer[HID_RUCT_INDEX..HID_RUCT_INDEX + 4]
-                        .try_into()
-                        .unwrap(),
-                ) as f64;
-
-                let replied_pc_time = f32::from_le_bytes(
-                    buffer[HID_PCTS_INDEX..HID_PCTS_INDEX + 4]
-                        .try_into()
-                        .unwrap(),
-                ) as f64;
-
-                let mcu_time = self
-                    .layer
-                    .mcu_stats
-                    .from_bytes(&buffer[HID_UCTS_INDEX..HID_UCTS_INDEX + 4]);
-                let pc_time = self.layer.pc_stats.from_utcs(datetime, self.layer.datetime) as f64;
-
-                match (buffer[0], pc_time - replied_pc_time < 0.05) {
-                    (255, true) => {
-                        let packets_tx = f32::from_le_bytes(
-                            buffer[HID_TASK_INDEX..HID_TASK_INDEX + 4]
-                                .try_into()
-                                .unwrap(),
-                        ) as f64;
-                        let packets_rx = f32::from_le_bytes(
-                            buffer[HID_TASK_INDEX + 4..HID_TASK_INDEX + 8]
-                                .try_into()
-                                .unwrap(),
-                        ) as f64;
-
-                        // println!("[HID-Control]: Packet Sync\t{}\t{}", packets_tx - self.layer.mcu_stats.n_tx(), packets_rx - self.layer.mcu_stats.n_rx());
-
-                        self.layer.mcu_stats.set_tx(packets_tx);
-                        self.layer.mcu_stats.set_rx(packets_rx);
-                    }
-                    (1, true) => {
-                        self.robot_fw.parse_hid(
-                            pc_time,
-                            mcu_time,
-                            run_time,
-                            buffer[HID_TASK_INDEX] as usize,
-                            buffer,
-                        );
-
-                        self.layer.mcu_stats.update_tx(1.0); // only works if we don't miss packets
-                        self.layer.mcu_stats.update_rx(1.0);
-                    }
-                    (_, true) => println!("[HID-Control]: Unknown Report Mode\t{buffer:?}"),
-                    (_, false) => println!(
-                        "[HID-Control]: Large Time drift\t{:.6} {:.6}",
-                        pc_time - replied_pc_time,
-                        mcu_time - replied_mcu_time,
-                    ),
-                };
-            }
-            _ => {}
+        while let Ok((buffer, datetime)) = self.reader_rx.try_recv() {
+            let missing = match check_report(&buffer) {
+                Ok(seq) => self.sequence.update(seq),
+                Err(e) => {
+                    debug!("{e}");
+                    self.health.corrupt_reports += 1;
+                    continue;
+                }
+            };
+            self.health.dropped_reports += missing;
+
+            let run_time = f32::from_le_bytes(
+                buffer[HID_RUNT_INDEX..HID_RUNT_INDEX + 4]
+                    .try_into()
+                    .unwrap(),
+            ) as f64;
+
+            let replied_mcu_time = f32::from_le_bytes(
+                buffer[HID_RUCT_INDEX..HID_RUCT_INDEX + 4]
+                    .try_into()
+                    .unwrap(),
+            ) as f64;
+
+            let replied_pc_time = f32::from_le_bytes(
+                buffer[HID_PCTS_INDEX..HID_PCTS_INDEX + 4]
+                    .try_into()
+                    .unwrap(),
+            ) as f64;
+
+            let mcu_time = self
+                .layer
+                .mcu_stats
+                .from_bytes(&buffer[HID_UCTS_INDEX..HID_UCTS_INDEX + 4]);
+            let pc_time = self.layer.pc_stats.from_utcs(datetime, self.layer.datetime) as f64;
+
+            match (
+                HidReportType::of(&buffer),
+                pc_time - replied_pc_time < self.layer.config.max_drift_s,
+            ) {
+                (Some(HidReportType::Status), true) => {
+                    let packets_tx = f32::from_le_bytes(
+                        buffer[HID_TASK_INDEX..HID_TASK_INDEX + 4]
+                            .try_into()
+                            .unwrap(),
+                    ) as f64;
+                    let packets_rx = f32::from_le_bytes(
+                        buffer[HID_TASK_INDEX + 4..HID_TASK_INDEX + 8]
+                            .try_into()
+                            .unwrap(),
+                    ) as f64;
+
+                    let lost =
+                        |i: usize| f32::from_le_bytes(buffer[i..i + 4].try_into().unwrap()) as f64;
+
+                    self.layer.mcu_stats.set_tx(packets_tx);
+                    self.layer.mcu_stats.set_rx(packets_rx);
+                    self.health.mcu_dropped_reports = lost(HID_TASK_INDEX + 8);
+                    self.health.mcu_corrupt_reports = lost(HID_TASK_INDEX + 12);
+                }
+                (Some(HidReportType::Feedback), true) => {
+                    self.robot_fw.parse_hid(
+                        pc_time,
+                        mcu_time,
+                        run_time,
+                        buffer[HID_TASK_INDEX] as usize,
+                        buffer,
+                    );
+
+                    // the mcu answers every report, lost ones were still sent
+                    self.layer.mcu_stats.update_tx(1.0 + missing as f64);
+                    self.layer.mcu_stats.update_rx(1.0 + missing as f64);
+                }
+                (_, true) => self.health.unknown_reports += 1,
+                (_, false) => {
+                    self.health.time_drifts += 1;
+                    self.health.max_drift_s = self
+                        .health
+                        .max_drift_s
+                        .max((pc_time - replied_pc_time).abs())
+                        .max((mcu_time - replied_mcu_time).abs());
+                }
+            };
         }
     }
 
@@ -162,50 +306,120 @@ impl HidInterface {
     }
 
     pub fn pipeline(&mut self, _unused_flag: bool) {
-        while !self.layer.control_flags.is_connected() {}
-
-        let mut t = Instant::now();
-
-        println!("[HID-Control]: Live");
+        info!("Live");
 
         while !self.layer.control_flags.is_shutdown() {
             let loopt = Instant::now();
+            self.spin_once();
+            self.cycled(self.layer.delay(loopt));
+        }
 
-            if !self.layer.control_flags.is_connected()
-                || !self.layer.control_flags.is_initialized()
-            {
+        sockapi::shutdown();
+        self.layer.control_flags.shutdown();
+        info!("shutdown");
+        self.layer.print();
+    }
+
+    /// One cycle of the pipeline: set up the mcu when it (re)connects,
+    /// otherwise finish its config and pass commands and feedback along.
+    /// Commands that come in while the mcu is gone are dropped.
+    pub fn spin_once(&mut self) {
+        match self.layer.control_flags.state() {
+            LinkState::Disconnected | LinkState::Searching => {
+                self.robot_fw.parse_sock();
+            }
+            LinkState::Connected => {
+                // a new link, the mcu may have restarted its count and
+                // reports from the old link don't say anything about it
+                while self.reader_rx.try_recv().is_ok() {}
+                self.sequence.reset();
                 self.robot_fw.configured = vec![false; self.robot_fw.tasks.len()];
                 self.layer.control_flags.initialize(true);
                 self.send_initializers();
-            } else {
+            }
+            LinkState::Initialized | LinkState::Configured => {
                 self.try_config();
-
-                match self.robot_fw.parse_sock() {
-                    Some(packet) => self.writer_tx(packet),
-                    _ => {}
+                if self
+                    .robot_fw
+                    .configured
+                    .iter()
+                    .all(|&configured| configured)
+                {
+                    self.layer.control_flags.configure();
                 }
 
+                self.robot_fw
+                    .parse_sock()
+                    .into_iter()
+                    .for_each(|packet| self.writer_tx(packet));
+
                 self.check_feedback();
 
-                if t.elapsed().as_secs() >= 20 {
+                if self.print_timer.elapsed().as_secs() >= 20 && log_enabled!(Level::Debug) {
                     self.print();
-                    t = Instant::now();
+                    self.print_timer = Instant::now();
                 }
             }
+        }
+
+        self.publish_link();
 
-            // self.layer.delay(loopt);
-            if self.layer.delay(loopt) > TEENSY_CYCLE_TIME_US {
-                println!(
-                    "[HID-Control]: over cycled {:.6}s",
-                    1E-6 * (t.elapsed().as_micros() as f64)
-                );
+        if self.diagnostics_timer.elapsed().as_micros() as i64 >= DIAGNOSTICS_PERIOD_US {
+            self.diagnose();
+            self.diagnostics_timer = Instant::now();
+        }
+    }
+
+    /// Count a cycle that took `cycle` micros if it ran over
+    pub fn cycled(&mut self, cycle: f64) {
+        if cycle > self.layer.config.cycle_time_us {
+            self.health.over_cycles += 1;
+            self.health.max_cycle_us = self.health.max_cycle_us.max(cycle);
+        }
+    }
+
+    /// The link's state, how healthy it is and how often it was lost
+    pub fn link_status(&self) -> DiagnosticStatus {
+        let name = join_name(&self.robot_fw.sock.name, "link");
+        let message = self.link_state.to_string();
+        let status = match self.link_state {
+            LinkState::Configured => DiagnosticStatus::ok(&name, &message),
+            LinkState::Connected | LinkState::Initialized => {
+                DiagnosticStatus::warn(&name, &message)
+            }
+            LinkState::Disconnected | LinkState::Searching => {
+                DiagnosticStatus::error(&name, &message)
             }
+        };
+
+        status.with("disconnects", self.disconnects)
+    }
+
+    /// Publish [`HidInterface::link_status`] on [`HID_STATUS_TOPIC`] when the state changes
+    pub fn publish_link(&mut self) {
+        let state = self.layer.control_flags.state();
+        if state == self.link_state {
+            return;
         }
 
-        sockapi::shutdown();
-        self.layer.control_flags.shutdown();
-        println!("[HID-Control]: shutdown");
-        self.layer.print();
+        info!("link {} -> {state}", self.link_state);
+        // the link only goes back when it's lost, even if this missed the search
+        if self.link_state >= LinkState::Connected && state < self.link_state {
+            self.disconnects += 1;
+        }
+        self.link_state = state;
+
+        let topic = self.robot_fw.sock.resolve(HID_STATUS_TOPIC);
+        let status = self.link_status();
+        self.robot_fw.sock.tx_any_payload(&topic, &status, 0);
+    }
+
+    /// Report the link and the tasks, then start counting problems again
+    pub fn diagnose(&mut self) {
+        let status = self.health.status(self.layer.control_flags.is_connected());
+        self.robot_fw.sock.diagnose(status);
+        self.robot_fw.diagnose();
+        self.health = HidHealth::default();
     }
 
     pub fn print(&self) {
@@ -11,20 +11,141 @@
  *
  ********************************************************************************/
 
-extern crate hidapi;
-
-use crate::rid::data_structures::{HidControlFlags, NetFlowStats};
+use crate::{
+    rid::{
+        data_structures::{HidControlFlags, NetFlowStats},
+        interface::{TEENSY_CYCLE_TIME_US, TEENSY_DEFAULT_PID, TEENSY_DEFAULT_VID},
+        transport::{HidTransport, HidapiTransport},
+    },
+    utilities::loaders::{BuffYamlUtil, ByuParseError},
+};
 use chrono::{DateTime, Utc};
-use hidapi::{HidApi, HidDevice};
-use std::time::Instant;
+use log::{info, warn};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+use yaml_rust::Yaml;
 
-pub struct HidLayer {
-    // Device info for initializing connection
+/// nodes.yaml section with the timing of the hid link
+pub const HID_CONFIG_ITEM: &str = "hid";
+
+/// Device ids and timing of the link to the mcu, from nodes.yaml:
+/// ```yaml
+/// teensy_vid: 0x16C0
+/// teensy_pid: 0x0486
+/// hid:                        # optional, every item has a default
+///   cycle_time_us: 1000       # one packet each way per cycle
+///   no_comms_timeout_ms: 1000 # silence before the link is reopened
+///   search_period_ms: 5.0     # time between the first tries while searching,
+///   max_search_period_ms: 1000 # doubled after each one up to this
+///   max_drift_s: 0.05         # replies older than this count as time drifts
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct HidConfig {
     pub vid: u16,
     pub pid: u16,
-    pub sample_time: f64,
+    pub cycle_time_us: f64,
+    pub no_comms_timeout_ms: f64,
+    pub search_period_ms: f64,
+    pub max_search_period_ms: f64,
+    pub max_drift_s: f64,
+}
+
+impl Default for HidConfig {
+    fn default() -> Self {
+        HidConfig {
+            vid: TEENSY_DEFAULT_VID,
+            pid: TEENSY_DEFAULT_PID,
+            cycle_time_us: TEENSY_CYCLE_TIME_US,
+            no_comms_timeout_ms: 1000.0,
+            search_period_ms: 5.0,
+            max_search_period_ms: 1000.0,
+            max_drift_s: 0.05,
+        }
+    }
+}
 
-    pub hidapi: HidApi,
+impl HidConfig {
+    /// Read and check the config, items that are left out keep their defaults
+    pub fn from_byu(byu: &BuffYamlUtil) -> Result<HidConfig, ByuParseError> {
+        let defaults = HidConfig::default();
+        let section = &byu.data()[HID_CONFIG_ITEM];
+        match section {
+            Yaml::Hash(_) | Yaml::BadValue => {}
+            _ => return Err(ByuParseError::item(HID_CONFIG_ITEM, &byu.yaml_path)),
+        }
+
+        let id = |name: &str, default: u16| match &byu.data()[name] {
+            Yaml::BadValue => Ok(default),
+            Yaml::Integer(id) => {
+                u16::try_from(*id)
+                    .ok()
+                    .filter(|&id| id != 0)
+                    .ok_or(ByuParseError::new(
+                        format!("{name}: usb id (1 to 0xFFFF)"),
+                        &byu.yaml_path,
+                    ))
+            }
+            _ => Err(ByuParseError::int(name, &byu.yaml_path)),
+        };
+        let float = |name: &str, default: f64| match section[name].is_badvalue() {
+            true => Ok(default),
+            false => byu.parse_float(name, section).map_err(|_| {
+                ByuParseError::float(&format!("{HID_CONFIG_ITEM}/{name}"), &byu.yaml_path)
+            }),
+        };
+
+        let config = HidConfig {
+            vid: id("teensy_vid", defaults.vid)?,
+            pid: id("teensy_pid", defaults.pid)?,
+            cycle_time_us: float("cycle_time_us", defaults.cycle_time_us)?,
+            no_comms_timeout_ms: float("no_comms_timeout_ms", defaults.no_comms_timeout_ms)?,
+            search_period_ms: float("search_period_ms", defaults.search_period_ms)?,
+            max_search_period_ms: float("max_search_period_ms", defaults.max_search_period_ms)?,
+            max_drift_s: float("max_drift_s", defaults.max_drift_s)?,
+        };
+
+        config.validate().map_err(|item| {
+            ByuParseError::new(format!("{HID_CONFIG_ITEM}/{item}"), &byu.yaml_path)
+        })?;
+        Ok(config)
+    }
+
+    /// From the nodes.yaml of ROBOT_NAME or self.txt
+    pub fn from_env() -> Result<HidConfig, ByuParseError> {
+        HidConfig::from_byu(&BuffYamlUtil::default("nodes"))
+    }
+
+    /// The first item that's out of range and what it should be
+    pub fn validate(&self) -> Result<(), String> {
+        let cycle_time_ms = self.cycle_time_us * 1E-3;
+        if !(100.0..=100_000.0).contains(&self.cycle_time_us) {
+            Err("cycle_time_us: 100 to 100000".to_string())
+        } else if self.no_comms_timeout_ms <= cycle_time_ms {
+            Err(format!(
+                "no_comms_timeout_ms: longer than a cycle ({cycle_time_ms}ms)"
+            ))
+        } else if self.search_period_ms <= 0.0 {
+            Err("search_period_ms: > 0".to_string())
+        } else if self.max_search_period_ms < self.search_period_ms {
+            Err(format!(
+                "max_search_period_ms: at least search_period_ms ({}ms)",
+                self.search_period_ms
+            ))
+        } else if self.max_drift_s <= 0.0 {
+            Err("max_drift_s: > 0".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub struct HidLayer {
+    // Device info and timing for the connection
+    pub config: HidConfig,
+
+    pub transport: Box<dyn HidTransport>,
 
     pub datetime: DateTime<Utc>,
 
@@ -36,13 +157,15 @@ pub struct HidLayer {
 }
 
 impl HidLayer {
-    pub fn new(vid: u16, pid: u16, sample_time: f64) -> HidLayer {
+    pub fn new(config: HidConfig) -> HidLayer {
+        HidLayer::with_transport(config, Box::new(HidapiTransport::new()))
+    }
+
+    pub fn with_transport(config: HidConfig, transport: Box<dyn HidTransport>) -> HidLayer {
         HidLayer {
-            vid: vid,
-            pid: pid,
-            sample_time: sample_time,
+            config,
 
-            hidapi: HidApi::new().expect("Failed to create API instance"),
+            transport,
 
             datetime: Utc::now(),
             pc_stats: NetFlowStats::new(),
@@ -53,11 +176,9 @@ impl HidLayer {
 
     pub fn clone(&self) -> HidLayer {
         HidLayer {
-            vid: self.vid,
-            pid: self.pid,
-            sample_time: self.sample_time,
+            config: self.config.clone(),
 
-            hidapi: HidApi::new().expect("Failed to create API instance"),
+            transport: self.transport.handle(),
 
             datetime: Utc::now(),
             pc_stats: self.pc_stats.clone(),
@@ -66,67 +187,74 @@ impl HidLayer {
         }
     }
 
-    pub fn device(&self) -> Option<HidDevice> {
-        match self.hidapi.open(self.vid, self.pid) {
-            Ok(dev) => {
-                println!("New Device");
-                self.control_flags.connect();
-                dev.set_blocking_mode(false).unwrap();
-                Some(dev)
-            }
-            Err(_) => {
-                if self.control_flags.is_shutdown() {
-                    panic!("[HID-Layer]: Shutdown while searching for MCU");
-                }
-                None
+    /// Open a handle to the mcu, the link is connected once one opens.
+    /// Also returns the connection the handle belongs to.
+    pub fn device(&self) -> Option<(Box<dyn HidTransport>, u64)> {
+        let mut dev = self.transport.handle();
+        match dev.open(self.config.vid, self.config.pid) {
+            Ok(_) => {
+                info!("New Device");
+                Some((dev, self.control_flags.connect()))
             }
+            Err(_) => None,
         }
     }
 
-    pub fn wait_for_device(&self) -> HidDevice {
-        let mut lap_millis = 0;
-        let mut lap_secs = 0;
+    /// Look for the mcu until it shows up, the wait between tries doubles
+    /// from `search_period_ms` up to `max_search_period_ms`. None when shut
+    /// down before it was found.
+    pub fn search(&self) -> Option<(Box<dyn HidTransport>, u64)> {
+        self.control_flags.search();
         let t = Instant::now();
+        let mut period_ms = self.config.search_period_ms;
+        let mut lap_secs = 0;
 
-        while t.elapsed().as_secs() < 5 {
-            match self.device() {
-                Some(dev) => {
-                    return dev;
-                }
-                None => {
-                    let elapsed = t.elapsed();
-                    if elapsed.as_secs() - lap_secs >= 1 {
-                        println!(
-                            "[HID-Layer]: Hasn't heard from MCU for {}s",
-                            (elapsed.as_millis() as f64) * 1E-3
-                        );
-                        lap_secs = elapsed.as_secs();
-                    }
-                }
+        while !self.control_flags.is_shutdown() {
+            if let Some(dev) = self.device() {
+                return Some(dev);
             }
 
-            while ((t.elapsed().as_millis() - lap_millis) as f64) < 5.0 * self.sample_time {}
-            lap_millis = t.elapsed().as_millis()
+            let elapsed = t.elapsed();
+            if elapsed.as_secs() > lap_secs {
+                warn!(
+                    "Hasn't found MCU for {}s",
+                    (elapsed.as_millis() as f64) * 1E-3
+                );
+                lap_secs = elapsed.as_secs();
+            }
+
+            // sleep a cycle at a time so shutdown isn't held up
+            let lap = Instant::now();
+            while (lap.elapsed().as_micros() as f64) < period_ms * 1E3
+                && !self.control_flags.is_shutdown()
+            {
+                thread::sleep(Duration::from_micros(self.config.cycle_time_us as u64));
+            }
+            period_ms = (2.0 * period_ms).min(self.config.max_search_period_ms);
         }
 
-        self.control_flags.shutdown();
-        panic!("[HID-Layer]: Could not find MCU, shutting down");
+        None
     }
 
     pub fn delay(&self, time: Instant) -> f64 {
         let mut t = time.elapsed().as_micros() as f64;
-        while t < self.sample_time {
+        while t < self.config.cycle_time_us {
             t = time.elapsed().as_micros() as f64;
         }
         t
     }
 
     pub fn print(&self) {
-        println!("[HID-Layer]: {} {}", self.vid, self.pid);
-        self.control_flags.print();
-        println!("[PC]");
-        self.pc_stats.print();
-        println!("[MCU]");
-        self.mcu_stats.print();
+        info!(
+            "{} {} link: {}, shutdown: {}, pc tx/rx: {}/{}, mcu tx/rx: {}/{}",
+            self.config.vid,
+            self.config.pid,
+            self.control_flags.state(),
+            self.control_flags.is_shutdown(),
+            self.pc_stats.n_tx(),
+            self.pc_stats.n_rx(),
+            self.mcu_stats.n_tx(),
+            self.mcu_stats.n_rx(),
+        );
     }
 }
@@ -0,0 +1,534 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::{
+    rid::{
+        data_structures::*,
+        report::{check_report, seal_report, SequenceTracker},
+        robot_firmware::*,
+        transport::HidTransport,
+    },
+    socks::clocks::Clock,
+};
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+/// Replies the mock holds for the reader, older ones are dropped
+pub const MOCK_REPLY_QUEUE: usize = 16;
+/// Unconfigured tasks report an empty output this often (micros), like
+/// the firmware's status indicator
+pub const MOCK_UNCONFIGURED_PERIOD_US: u64 = 250_000;
+
+/// What a mock firmware task does (firmware/src/tasks), made by a
+/// [`MockDriverFactory`] from the driver key
+pub trait MockDriver: Send {
+    /// If setup can be called with these parameters (all chunks arrived)
+    fn is_configured(&self, parameters: &[f64]) -> bool;
+
+    fn setup(&mut self, _parameters: &[f64]) {}
+
+    /// Size of the concatenated inputs the task runs on, the firmware
+    /// won't run a task until it's linked to this many (None takes any)
+    fn n_inputs(&self) -> Option<usize> {
+        None
+    }
+
+    /// Outputs start as this many zeros once the task is configured
+    fn n_outputs(&self) -> usize {
+        0
+    }
+
+    /// Output for the concatenated outputs of the task's inputs,
+    /// `dt` is seconds since the last run
+    fn run(&mut self, inputs: &[f64], dt: f64) -> Vec<f64>;
+}
+
+pub type MockDriverFactory = fn(&str) -> Box<dyn MockDriver>;
+
+/// Stands in for any driver, outputs how many times it ran then its
+/// inputs and parameters (up to [`MAX_TASK_DATA`]). It's configured by
+/// whatever parameters arrive.
+#[derive(Default)]
+pub struct MockEcho {
+    pub runs: u32,
+    pub parameters: Vec<f64>,
+}
+
+impl MockDriver for MockEcho {
+    fn is_configured(&self, _parameters: &[f64]) -> bool {
+        true
+    }
+
+    fn setup(&mut self, parameters: &[f64]) {
+        self.parameters = parameters.to_vec();
+    }
+
+    fn run(&mut self, inputs: &[f64], _dt: f64) -> Vec<f64> {
+        self.runs += 1;
+        [self.runs as f64]
+            .into_iter()
+            .chain(inputs.iter().copied())
+            .chain(self.parameters.iter().copied())
+            .take(MAX_TASK_DATA)
+            .collect()
+    }
+}
+
+pub fn mock_echo_factory(_driver: &str) -> Box<dyn MockDriver> {
+    Box::new(MockEcho::default())
+}
+
+/// A task on the mock mcu (firmware TaskNode)
+pub struct MockTask {
+    /// id the pc gave the task
+    pub id: u8,
+    pub driver_key: String,
+    pub driver: Box<dyn MockDriver>,
+    pub period_us: u64,
+    pub inputs: Vec<u8>,
+    pub parameters: Vec<f64>,
+    pub configured: bool,
+    pub latch: u8,
+    pub input: Vec<f64>,
+    pub output: Vec<f64>,
+    /// has feedback to send
+    pub update: bool,
+    /// output being sent and its next chunk, a new output waits until
+    /// the last one is all out
+    pub sending: Vec<f64>,
+    pub chunk: usize,
+    /// collects the chunks of latches
+    pub chunks: TaskDataAssembler,
+    pub run_time: f64,
+    last_run: Option<u64>,
+}
+
+/// In-memory firmware that answers hid packets like
+/// firmware/src/hid_comms and task_manager do: init, config and latch
+/// packets set up tasks, every packet gets one reply, either a task's
+/// feedback or the packet sync status (mode 255). Packets that fail their
+/// report check only get the status.
+pub struct MockFirmware {
+    pub clock: Clock,
+    pub factory: MockDriverFactory,
+    pub tasks: Vec<MockTask>,
+    /// packets received/sent since the last reset (reported in status replies)
+    pub reads: f64,
+    pub writes: f64,
+    /// pc reports lost or failing their check (reported in status replies)
+    pub dropped: f64,
+    pub corrupt: f64,
+    pub kills: u32,
+    pub replies: VecDeque<HidPacket>,
+    /// sequence numbers of the pc's reports
+    pub sequence: SequenceTracker,
+    origin: u64,
+    task_num: usize,
+    seq: u16,
+}
+
+impl MockFirmware {
+    pub fn new(clock: Clock) -> MockFirmware {
+        MockFirmware::with_factory(clock, mock_echo_factory)
+    }
+
+    pub fn with_factory(clock: Clock, factory: MockDriverFactory) -> MockFirmware {
+        MockFirmware {
+            origin: clock.micros(),
+            clock,
+            factory,
+            tasks: vec![],
+            reads: 0.0,
+            writes: 0.0,
+            dropped: 0.0,
+            corrupt: 0.0,
+            kills: 0,
+            replies: VecDeque::new(),
+            sequence: SequenceTracker::default(),
+            task_num: 0,
+            seq: 0,
+        }
+    }
+
+    pub fn task(&self, id: u8) -> Option<&MockTask> {
+        self.tasks.iter().find(|task| task.id == id)
+    }
+
+    fn task_index(&self, id: u8) -> Option<usize> {
+        self.tasks.iter().position(|task| task.id == id)
+    }
+
+    fn floats(packet: &HidPacket, start: usize, n: usize) -> Vec<f64> {
+        (0..n)
+            .filter(|i| start + 4 * i + 4 <= HID_VERSION_INDEX)
+            .map(|i| {
+                f32::from_le_bytes(packet[start + 4 * i..start + 4 * i + 4].try_into().unwrap())
+                    as f64
+            })
+            .collect()
+    }
+
+    /// Handle a packet from the pc and queue the reply
+    pub fn receive(&mut self, packet: &HidPacket) {
+        self.reads += 1.0;
+
+        match check_report(packet) {
+            Ok(seq) => self.dropped += self.sequence.update(seq) as f64,
+            Err(_) => {
+                self.corrupt += 1.0;
+                let status = self.status(packet);
+                self.push_reply(status);
+                return;
+            }
+        }
+
+        match (packet[HID_MODE_INDEX], packet[HID_TOGL_INDEX]) {
+            (255, 1) => self.init_task(packet),
+            (255, 2) => self.config_task(packet),
+            (1, _) => self.overwrite_task(packet),
+            (13, _) => {
+                let status = self.status(packet);
+                self.push_reply(status);
+                self.reset();
+                self.kills += 1;
+                return;
+            }
+            _ => {}
+        }
+
+        self.spin();
+        let reply = self.feedback(packet).unwrap_or(self.status(packet));
+        self.push_reply(reply);
+    }
+
+    /// Reset packet from [`get_task_reset_packet`]
+    fn init_task(&mut self, packet: &HidPacket) {
+        let id = packet[HID_TASK_INDEX];
+        let period =
+            u16::from_le_bytes([packet[HID_INIT_RATE_INDEX], packet[HID_INIT_RATE_INDEX + 1]]);
+        let key = String::from_utf8_lossy(&packet[HID_INIT_KEY_INDEX..HID_INIT_KEY_INDEX + 3])
+            .to_string();
+        let n_inputs = packet[HID_INIT_N_INPUTS_INDEX] as usize;
+        let inputs = packet[HID_INIT_INPUTS_INDEX..HID_INIT_INPUTS_INDEX + n_inputs].to_vec();
+        let mut driver = (self.factory)(&key);
+        let configured = driver.is_configured(&[]);
+        let output = match configured {
+            true => {
+                driver.setup(&[]);
+                vec![0.0; driver.n_outputs()]
+            }
+            false => vec![],
+        };
+
+        let task = MockTask {
+            id,
+            configured,
+            driver_key: key,
+            driver,
+            period_us: period as u64,
+            inputs,
+            parameters: vec![],
+            latch: 0,
+            input: vec![],
+            output,
+            update: false,
+            sending: vec![],
+            chunk: 0,
+            chunks: TaskDataAssembler::default(),
+            run_time: 0.0,
+            last_run: None,
+        };
+
+        match self.task_index(id) {
+            Some(i) => self.tasks[i] = task,
+            None => self.tasks.push(task),
+        }
+    }
+
+    /// Parameter chunks from [`get_task_parameter_packets`]
+    fn config_task(&mut self, packet: &HidPacket) {
+        let Some(i) = self.task_index(packet[HID_TASK_INDEX]) else {
+            return;
+        };
+        let chunk = packet[HID_DATA_INDEX] as usize;
+        let size = packet[HID_DATA_INDEX + 1] as usize;
+        let data = MockFirmware::floats(packet, HID_DATA_INDEX + 2, size);
+        let task = &mut self.tasks[i];
+
+        let start = chunk * MAX_HID_FLOAT_DATA;
+        if start <= task.parameters.len() {
+            task.parameters.truncate(start);
+            task.parameters.extend(data);
+        }
+
+        task.configured = task.driver.is_configured(&task.parameters);
+        if task.configured {
+            task.driver.setup(&task.parameters);
+            if task.output.is_empty() {
+                task.output = vec![0.0; task.driver.n_outputs()];
+            }
+        }
+    }
+
+    /// Latch packets: 1 overwrites the output, 2 the input and 0 unlatches,
+    /// once every chunk of the data arrived
+    fn overwrite_task(&mut self, packet: &HidPacket) {
+        let (Some(i), Some((chunk, total, data))) = (
+            self.task_index(packet[HID_TASK_INDEX]),
+            parse_task_data(packet),
+        ) else {
+            return;
+        };
+        let latch = packet[HID_TOGL_INDEX];
+        let task = &mut self.tasks[i];
+
+        let Some(data) = task.chunks.push(chunk, total, data) else {
+            return;
+        };
+        if !task.configured {
+            return;
+        }
+
+        match latch {
+            1 if task.output.len() == data.len() => {
+                task.output = data;
+                task.latch = 1;
+            }
+            2 if task.input.len() == data.len() => {
+                task.input = data;
+                task.latch = 2;
+            }
+            0 => task.latch = 0,
+            _ => {}
+        }
+    }
+
+    /// Run the tasks that are due
+    pub fn spin(&mut self) {
+        let now = self.clock.micros();
+
+        for i in 0..self.tasks.len() {
+            let due = |period: u64| {
+                self.tasks[i]
+                    .last_run
+                    .is_none_or(|last| now.saturating_sub(last) >= period)
+            };
+
+            match self.tasks[i].configured {
+                true if due(self.tasks[i].period_us) => {
+                    let input: Vec<f64> = match self.tasks[i].latch {
+                        2 => self.tasks[i].input.clone(),
+                        _ => self.tasks[i]
+                            .inputs
+                            .iter()
+                            .filter_map(|&id| self.task(id))
+                            .flat_map(|input| input.output.clone())
+                            .collect(),
+                    };
+                    // not linked yet
+                    if self.tasks[i]
+                        .driver
+                        .n_inputs()
+                        .is_some_and(|n| n != input.len())
+                    {
+                        continue;
+                    }
+
+                    let dt = self.tasks[i]
+                        .last_run
+                        .map_or(0.0, |last| (now - last) as f64 * 1E-6);
+
+                    let task = &mut self.tasks[i];
+                    let t = Instant::now();
+                    if task.latch != 1 {
+                        task.output = task.driver.run(&input, dt);
+                    }
+                    task.input = input;
+                    task.run_time = t.elapsed().as_micros() as f64 * 1E-3;
+                    task.last_run = Some(now);
+                    task.update = true;
+                }
+                false if due(MOCK_UNCONFIGURED_PERIOD_US) => {
+                    let task = &mut self.tasks[i];
+                    task.output.clear();
+                    task.last_run = Some(now);
+                    task.update = true;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Next chunk of the next task with an update (round robin), firmware
+    /// send_hid_feedback
+    fn feedback(&mut self, received: &HidPacket) -> Option<HidPacket> {
+        let n = self.tasks.len();
+        let i = (0..n)
+            .map(|k| (self.task_num + k) % n)
+            .find(|&i| self.tasks[i].update || self.tasks[i].chunk > 0)?;
+        self.task_num = (i + 1) % n;
+
+        let task = &mut self.tasks[i];
+        if task.chunk == 0 {
+            task.update = false;
+            task.sending = task.output.iter().take(MAX_TASK_DATA).copied().collect();
+        }
+
+        let mut buffer = get_task_data_packet(i as u8, task.latch, &task.sending, task.chunk);
+        task.chunk = (task.chunk + 1) % task_data_chunks(task.sending.len());
+        buffer[HID_RUNT_INDEX..HID_RUNT_INDEX + 4]
+            .copy_from_slice(&(task.run_time as f32).to_le_bytes());
+
+        Some(self.stamp(buffer, received))
+    }
+
+    /// Packet sync, firmware send_hid_status
+    fn status(&mut self, received: &HidPacket) -> HidPacket {
+        let mut buffer = [0; HID_PACKET_SIZE];
+        buffer[HID_MODE_INDEX] = 255;
+        buffer[HID_TOGL_INDEX] = 255;
+        buffer[HID_TASK_INDEX..HID_TASK_INDEX + 4]
+            .copy_from_slice(&(self.writes as f32).to_le_bytes());
+        buffer[HID_TASK_INDEX + 4..HID_TASK_INDEX + 8]
+            .copy_from_slice(&(self.reads as f32).to_le_bytes());
+        buffer[HID_TASK_INDEX + 8..HID_TASK_INDEX + 12]
+            .copy_from_slice(&(self.dropped as f32).to_le_bytes());
+        buffer[HID_TASK_INDEX + 12..HID_TASK_INDEX + 16]
+            .copy_from_slice(&(self.corrupt as f32).to_le_bytes());
+        self.stamp(buffer, received)
+    }
+
+    /// Echo the pc's times back, add ours and seal it (send_hid_with_timestamp)
+    fn stamp(&mut self, mut buffer: HidPacket, received: &HidPacket) -> HidPacket {
+        buffer[HID_RUCT_INDEX..HID_RUCT_INDEX + 8]
+            .copy_from_slice(&received[HID_RUCT_INDEX..HID_RUCT_INDEX + 8]);
+        let t = self.clock.elapsed(self.origin) as f64 * 1E-6;
+        buffer[HID_UCTS_INDEX..HID_UCTS_INDEX + 4].copy_from_slice(&(t as f32).to_le_bytes());
+        self.writes += 1.0;
+        seal_report(&mut buffer, self.seq);
+        self.seq = self.seq.wrapping_add(1);
+        buffer
+    }
+
+    fn push_reply(&mut self, reply: HidPacket) {
+        if self.replies.len() >= MOCK_REPLY_QUEUE {
+            self.replies.pop_front();
+        }
+        self.replies.push_back(reply);
+    }
+
+    /// Kill packet, drop every task and start over
+    pub fn reset(&mut self) {
+        self.tasks.clear();
+        self.reads = 0.0;
+        self.writes = 0.0;
+        self.dropped = 0.0;
+        self.corrupt = 0.0;
+        self.sequence.reset();
+        self.task_num = 0;
+        self.origin = self.clock.micros();
+    }
+}
+
+/// [`HidTransport`] to a [`MockFirmware`], every handle talks to the same one
+#[derive(Clone)]
+pub struct MockHid {
+    pub firmware: Arc<Mutex<MockFirmware>>,
+    plugged: Arc<AtomicBool>,
+    open: bool,
+}
+
+impl MockHid {
+    pub fn new() -> MockHid {
+        MockHid::with_firmware(MockFirmware::new(Clock::real()))
+    }
+
+    pub fn with_firmware(firmware: MockFirmware) -> MockHid {
+        MockHid {
+            firmware: Arc::new(Mutex::new(firmware)),
+            plugged: Arc::new(AtomicBool::new(true)),
+            open: false,
+        }
+    }
+
+    /// Disconnect every handle until [`MockHid::plug`]
+    pub fn unplug(&self) {
+        self.plugged.store(false, Ordering::Relaxed);
+    }
+
+    pub fn plug(&self) {
+        self.plugged.store(true, Ordering::Relaxed);
+    }
+
+    fn check(&self) -> io::Result<()> {
+        match (self.plugged.load(Ordering::Relaxed), self.open) {
+            (false, _) => Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "mock unplugged",
+            )),
+            (true, false) => Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "mock isn't open",
+            )),
+            (true, true) => Ok(()),
+        }
+    }
+}
+
+impl Default for MockHid {
+    fn default() -> Self {
+        MockHid::new()
+    }
+}
+
+impl HidTransport for MockHid {
+    fn open(&mut self, _vid: u16, _pid: u16) -> io::Result<()> {
+        self.open = true;
+        self.check().inspect_err(|_| self.open = false)
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.check()?;
+        match self.firmware.lock().unwrap().replies.pop_front() {
+            Some(reply) => {
+                let n = reply.len().min(buffer.len());
+                buffer[..n].copy_from_slice(&reply[..n]);
+                Ok(n)
+            }
+            None => Ok(0),
+        }
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.check()?;
+        let mut packet = [0; HID_PACKET_SIZE];
+        let n = buffer.len().min(HID_PACKET_SIZE);
+        packet[..n].copy_from_slice(&buffer[..n]);
+        self.firmware.lock().unwrap().receive(&packet);
+        Ok(n)
+    }
+
+    fn handle(&self) -> Box<dyn HidTransport> {
+        Box::new(MockHid {
+            open: false,
+            ..self.clone()
+        })
+    }
+}
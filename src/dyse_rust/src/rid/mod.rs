@@ -1,8 +1,16 @@
 pub mod test;
 
+pub mod boards;
 pub mod data_structures;
+pub mod drivers;
+pub mod emulator;
 pub mod interface;
 pub mod layer;
+pub mod mock;
 pub mod reader;
+pub mod report;
 pub mod robot_firmware;
+pub mod serial;
+pub mod task_graph;
+pub mod transport;
 pub mod writer;
@@ -11,47 +11,48 @@
  *
  ********************************************************************************/
 
-extern crate hidapi;
-use hidapi::HidDevice;
-
 use crate::rid::{
     data_structures::{HidPacket, HID_PACKET_SIZE},
     layer::*,
+    transport::HidTransport,
 };
 
 use chrono::{DateTime, Utc};
+use log::{info, warn};
 use std::{sync::mpsc::Sender, time::Instant};
 
 /// Reads from an Hid Device and send the packets through a channel
 pub struct HidReader {
     parser_tx: Sender<(HidPacket, DateTime<Utc>)>,
-    teensy: HidDevice,
+    teensy: Option<Box<dyn HidTransport>>,
+    // connection the teensy handle was opened in
+    connection: u64,
     layer: HidLayer,
     timestamp: Instant,
 }
 
 impl HidReader {
+    /// The device is opened by [`HidReader::spin`], so this doesn't wait for it
     pub fn new(layer: HidLayer, parser_tx: Sender<(HidPacket, DateTime<Utc>)>) -> HidReader {
         HidReader {
             parser_tx: parser_tx,
-            teensy: layer.wait_for_device(),
+            teensy: None,
+            connection: 0,
             layer: layer,
             timestamp: Instant::now(),
         }
     }
 
+    /// Drop the device, the next cycle searches for it again
     pub fn reconnect(&mut self) {
-        // check reconnect after 1000 cycles
-        if self.timestamp.elapsed().as_millis() as f64 > self.layer.sample_time {
-            if self.layer.control_flags.is_connected() {
-                println!(
-                    "[HID-Reader]: hasn't written for {}s",
-                    (self.timestamp.elapsed().as_millis() as f64) * 1E-3
-                );
-            }
-
-            self.teensy = self.layer.wait_for_device();
+        if self.layer.control_flags.disconnect(self.connection) {
+            warn!(
+                "disconnected, hasn't read for {}s",
+                (self.timestamp.elapsed().as_millis() as f64) * 1E-3
+            );
         }
+
+        self.teensy = None;
     }
 
     /// Read data into the input buffer and return how many bytes were read
@@ -68,22 +69,31 @@ impl HidReader {
     /// ```
     pub fn read(&mut self) -> usize {
         let mut buffer = [0; HID_PACKET_SIZE];
-        match &self.teensy.read(&mut buffer) {
+        let read = match &mut self.teensy {
+            Some(teensy) => teensy.read(&mut buffer),
+            None => return 0,
+        };
+
+        match read {
+            Ok(HID_PACKET_SIZE) => {
+                match self.parser_tx.send((buffer, Utc::now())) {
+                    Ok(_) => {}
+                    _ => self.layer.control_flags.shutdown(),
+                };
+
+                self.layer.pc_stats.update_rx(1.0);
+                self.timestamp = Instant::now();
+                HID_PACKET_SIZE
+            }
             Ok(value) => {
-                if *value == HID_PACKET_SIZE {
-                    match self.parser_tx.send((buffer, Utc::now())) {
-                        Ok(_) => {}
-                        _ => self.layer.control_flags.shutdown(),
-                    };
-
-                    self.layer.pc_stats.update_rx(1.0);
-                    self.timestamp = Instant::now();
+                if self.timestamp.elapsed().as_millis() as f64
+                    > self.layer.config.no_comms_timeout_ms
+                {
+                    self.reconnect();
                 }
-
-                *value
+                value
             }
-            _ => {
-                self.layer.control_flags.initialize(false);
+            Err(_) => {
                 self.reconnect();
                 0
             }
@@ -100,7 +110,18 @@ impl HidReader {
         while !self.layer.control_flags.is_shutdown() {
             let t = Instant::now();
 
-            self.read();
+            match self.teensy.is_some() {
+                true => {
+                    self.read();
+                }
+                false => {
+                    if let Some((teensy, connection)) = self.layer.search() {
+                        self.teensy = Some(teensy);
+                        self.connection = connection;
+                    }
+                    self.timestamp = Instant::now();
+                }
+            }
 
             self.layer.delay(t);
             // if self.layer.delay(t) > 1000.0 {
@@ -110,10 +131,10 @@ impl HidReader {
     }
 
     pub fn pipeline(&mut self) {
-        println!("[HID-reader]: Live");
+        info!("Live");
 
         self.spin();
 
-        println!("[HID-reader]: Shutdown");
+        info!("Shutdown");
     }
 }
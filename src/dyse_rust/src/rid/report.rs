@@ -0,0 +1,161 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::rid::{
+    data_structures::{
+        HidPacket, HID_CRC_INDEX, HID_MODE_INDEX, HID_SEQ_INDEX, HID_TOGL_INDEX, HID_VERSION_INDEX,
+    },
+    serial::crc16,
+};
+use std::fmt;
+
+/// Layout of the reports both ends agree on, bumped when it changes
+pub const HID_REPORT_VERSION: u8 = 1;
+
+/// Sequence numbers further apart than this mean the other end restarted
+pub const HID_SEQ_RESTART: u16 = 0x8000;
+
+/// What a report is, from its first two bytes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HidReportType {
+    /// pc -> mcu: set up a task
+    Init,
+    /// pc -> mcu: a chunk of a task's parameters
+    Config,
+    /// pc -> mcu: overwrite a task's inputs or outputs
+    Overwrite,
+    /// pc -> mcu: stop every task
+    Kill,
+    /// mcu -> pc: packet counts, nothing to report
+    Status,
+    /// mcu -> pc: a task's output
+    Feedback,
+}
+
+impl HidReportType {
+    /// The type of a report from the pc or the mcu, None when it's neither
+    pub fn of(packet: &HidPacket) -> Option<HidReportType> {
+        match (packet[HID_MODE_INDEX], packet[HID_TOGL_INDEX]) {
+            (255, 1) => Some(HidReportType::Init),
+            (255, 2) => Some(HidReportType::Config),
+            (255, 255) => Some(HidReportType::Status),
+            (1, _) => Some(HidReportType::Feedback),
+            (13, _) => Some(HidReportType::Kill),
+            _ => None,
+        }
+    }
+
+    /// Feedback and overwrites share a mode, the direction tells them apart
+    pub fn from_pc(packet: &HidPacket) -> Option<HidReportType> {
+        match HidReportType::of(packet) {
+            Some(HidReportType::Feedback) => Some(HidReportType::Overwrite),
+            Some(HidReportType::Status) => None,
+            kind => kind,
+        }
+    }
+}
+
+/// Why a report was thrown away
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HidReportError {
+    Version(u8),
+    Crc { expected: u16, found: u16 },
+}
+
+impl fmt::Display for HidReportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HidReportError::Version(version) => {
+                write!(f, "report version {version}, expected {HID_REPORT_VERSION}")
+            }
+            HidReportError::Crc { expected, found } => {
+                write!(f, "report crc {found:#06X}, expected {expected:#06X}")
+            }
+        }
+    }
+}
+
+fn report_crc(packet: &HidPacket) -> u16 {
+    let mut zeroed = *packet;
+    zeroed[HID_CRC_INDEX..HID_CRC_INDEX + 2].fill(0);
+    crc16(&zeroed)
+}
+
+/// Stamp the version, `seq` and a crc of the whole report (crc bytes zeroed),
+/// do it last, any change after this breaks the crc
+pub fn seal_report(packet: &mut HidPacket, seq: u16) {
+    packet[HID_VERSION_INDEX] = HID_REPORT_VERSION;
+    packet[HID_SEQ_INDEX..HID_SEQ_INDEX + 2].copy_from_slice(&seq.to_le_bytes());
+    let crc = report_crc(packet);
+    packet[HID_CRC_INDEX..HID_CRC_INDEX + 2].copy_from_slice(&crc.to_le_bytes());
+}
+
+/// The report's sequence number if it's intact and in this version
+pub fn check_report(packet: &HidPacket) -> Result<u16, HidReportError> {
+    match packet[HID_VERSION_INDEX] {
+        HID_REPORT_VERSION => {}
+        version => return Err(HidReportError::Version(version)),
+    }
+
+    let found = u16::from_le_bytes([packet[HID_CRC_INDEX], packet[HID_CRC_INDEX + 1]]);
+    let expected = report_crc(packet);
+    match found == expected {
+        true => Ok(u16::from_le_bytes([
+            packet[HID_SEQ_INDEX],
+            packet[HID_SEQ_INDEX + 1],
+        ])),
+        false => Err(HidReportError::Crc { expected, found }),
+    }
+}
+
+/// Counts the reports missing between the sequence numbers that arrived
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SequenceTracker {
+    pub last: Option<u16>,
+    pub received: u64,
+    pub dropped: u64,
+    pub restarts: u64,
+}
+
+impl SequenceTracker {
+    /// Take the next sequence number, returns how many went missing before it.
+    /// A big jump (or a repeat) is the other end starting over, not a loss.
+    pub fn update(&mut self, seq: u16) -> u64 {
+        self.received += 1;
+        let missing = match self.last {
+            Some(last) => match seq.wrapping_sub(last) {
+                0 => None,
+                step if step < HID_SEQ_RESTART => Some(step as u64 - 1),
+                _ => None,
+            },
+            None => Some(0),
+        };
+        self.last = Some(seq);
+
+        match missing {
+            Some(missing) => {
+                self.dropped += missing;
+                missing
+            }
+            None => {
+                self.restarts += 1;
+                0
+            }
+        }
+    }
+
+    /// Forget the last number, the next one starts a new count
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
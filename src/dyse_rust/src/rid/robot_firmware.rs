@@ -12,11 +12,26 @@
  ********************************************************************************/
 
 use crate::{
-    rid::data_structures::*,
-    socks::{message::UDP_PACKET_SIZE, socks::*},
+    msgs::{diagnostics::*, firmware::*, geometry::*, sensors::*, standard::*},
+    rid::{
+        data_structures::*,
+        drivers::{driver_spec, DriverSpec},
+        task_graph::{TaskGraph, TaskGraphIssue, MCU_MAX_TASKS},
+    },
+    schema,
+    socks::{
+        message::UDP_PACKET_SIZE,
+        names::{join_name, NameResolver},
+        params::*,
+        socks::*,
+        throttle::Throttle,
+    },
     utilities::loaders::*,
 };
+use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
+use std::io;
+use yaml_rust::Yaml;
 
 /// helpful constants to use
 pub static P: u8 = 0x50;
@@ -27,9 +42,15 @@ pub static I: u8 = 0x4C;
 pub static C: u8 = 0x53;
 pub static DELIM: u8 = 0x3A;
 
-/// HID laws
-pub static MAX_HID_FLOAT_DATA: usize = 10;
+/// Tasks running under this fraction of their rate are reported as slow
+pub const TASK_SLOW_RATIO: f64 = 0.9;
+
+/// HID laws, task data has to end before the report trailer (HID_VERSION_INDEX)
+pub static MAX_HID_FLOAT_DATA: usize = 9;
 pub static MAX_TASK_PARAMETERS: usize = 100;
+/// Task inputs/outputs go out in chunks of [`MAX_HID_FLOAT_DATA`], the
+/// total length has to fit in a byte
+pub static MAX_TASK_DATA: usize = 255;
 
 /// first HID identifier
 /// determines which report handler to use
@@ -70,14 +91,17 @@ pub fn get_task_reset_packet(
     buffer[HID_TOGL_INDEX] = INIT_NODE_MODE;
     buffer[HID_TASK_INDEX] = id;
 
-    ((1E6 / rate) as u16)
-        .to_le_bytes()
+    buffer[HID_INIT_RATE_INDEX..HID_INIT_RATE_INDEX + 2]
+        .copy_from_slice(&((1E6 / rate) as u16).to_le_bytes());
+    driver
+        .iter()
+        .enumerate()
+        .for_each(|(i, &b)| buffer[HID_INIT_KEY_INDEX + i] = b);
+    buffer[HID_INIT_N_INPUTS_INDEX] = input_ids.len() as u8;
+    input_ids
         .iter()
-        .chain(driver.iter())
-        .chain([input_ids.len() as u8].iter())
-        .chain(input_ids.iter())
         .enumerate()
-        .for_each(|(i, &b)| buffer[HID_DATA_INDEX + i] = b);
+        .for_each(|(i, &b)| buffer[HID_INIT_INPUTS_INDEX + i] = b);
     buffer
 }
 
@@ -117,70 +141,175 @@ pub fn get_task_initializers(
         .collect()
 }
 
-pub fn get_latch_packet(i: u8, latch: u8, data: &Vec<f64>) -> HidPacket {
+/// Number of chunks task data of length `total` takes, empty data still
+/// takes one
+pub fn task_data_chunks(total: usize) -> usize {
+    total.div_ceil(MAX_HID_FLOAT_DATA).max(1)
+}
+
+/// One chunk of a task's inputs or outputs (latches from the pc, feedback
+/// from the mcu). Laid out like the parameter chunks, but with the total
+/// length after the chunk id so the other end knows when it has them all.
+pub fn get_task_data_packet(id: u8, latch: u8, data: &[f64], chunk: usize) -> HidPacket {
     let mut buffer = [0; HID_PACKET_SIZE];
     buffer[HID_MODE_INDEX] = TASK_CONTROL_ID;
     buffer[HID_TOGL_INDEX] = latch;
-    buffer[HID_TASK_INDEX] = i;
-    buffer[HID_DATA_INDEX] = data.len() as u8;
-    data.iter().enumerate().for_each(|(i, &x)| {
-        (x as f32)
-            .to_le_bytes()
-            .iter()
-            .enumerate()
-            .for_each(|(j, &b)| buffer[(4 * i) + j] = b)
-    });
+    buffer[HID_TASK_INDEX] = id;
+    buffer[HID_DATA_INDEX] = chunk as u8;
+    buffer[HID_DATA_INDEX + 1] = data.len() as u8;
+    data.iter()
+        .skip(chunk * MAX_HID_FLOAT_DATA)
+        .take(MAX_HID_FLOAT_DATA)
+        .flat_map(|&x| (x as f32).to_le_bytes())
+        .enumerate()
+        .for_each(|(i, b)| buffer[HID_DATA_INDEX + 2 + i] = b);
     buffer
 }
 
-pub fn disable_latch(i: u8) -> HidPacket {
-    get_latch_packet(i, 0, &vec![])
+/// Chunk id, total length and data of a [`get_task_data_packet`], None when
+/// the chunk isn't part of data that long
+pub fn parse_task_data(buffer: &HidPacket) -> Option<(usize, usize, Vec<f64>)> {
+    let chunk = buffer[HID_DATA_INDEX] as usize;
+    let total = buffer[HID_DATA_INDEX + 1] as usize;
+    if chunk >= task_data_chunks(total) {
+        return None;
+    }
+
+    let size = (total - chunk * MAX_HID_FLOAT_DATA).min(MAX_HID_FLOAT_DATA);
+    let start = HID_DATA_INDEX + 2;
+    let data = buffer[start..start + 4 * size]
+        .chunks(4)
+        .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()) as f64)
+        .collect();
+    Some((chunk, total, data))
 }
 
-pub fn output_latch(i: u8, data: &Vec<f64>) -> HidPacket {
-    get_latch_packet(i, 1, data)
+/// Every chunk of `data` (up to [`MAX_TASK_DATA`]), in order
+pub fn get_latch_packets(i: u8, latch: u8, data: &Vec<f64>) -> Vec<HidPacket> {
+    let data = &data[..data.len().min(MAX_TASK_DATA)];
+    (0..task_data_chunks(data.len()))
+        .map(|chunk| get_task_data_packet(i, latch, data, chunk))
+        .collect()
 }
 
-pub fn input_latch(i: u8, data: &Vec<f64>) -> HidPacket {
-    get_latch_packet(i, 2, data)
+pub fn disable_latch(i: u8) -> HidPacket {
+    get_task_data_packet(i, 0, &[], 0)
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
-pub enum TaskMarshallType {
-    Input,
-    Output,
-    Parameter,
+pub fn output_latch(i: u8, data: &Vec<f64>) -> Vec<HidPacket> {
+    get_latch_packets(i, 1, data)
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
-pub struct TaskMarshall {
-    pub name: String,
-    pub data: Vec<f64>,
-    pub mode: TaskMarshallType,
+pub fn input_latch(i: u8, data: &Vec<f64>) -> Vec<HidPacket> {
+    get_latch_packets(i, 2, data)
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
-pub struct TaskCommunication {
-    pub name: String,
-    pub latch: u8,
-    pub rate: f64,
-    pub pc_time: f64,
-    pub mcu_time: f64,
-    pub run_time: f64,
+/// Puts task data back together from its chunks. They have to arrive in
+/// order, anything else drops what was collected so far.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaskDataAssembler {
+    pub total: usize,
     pub data: Vec<f64>,
+    /// data that was started but never finished
+    pub dropped: u64,
+    next: usize,
+}
+
+impl TaskDataAssembler {
+    /// Add a chunk from [`parse_task_data`], returns the data once it's whole
+    pub fn push(&mut self, chunk: usize, total: usize, data: Vec<f64>) -> Option<Vec<f64>> {
+        // a chunk that doesn't follow the last one abandons the data
+        let follows = chunk == self.next && total == self.total;
+        if self.next > 0 && !follows {
+            self.dropped += 1;
+        }
+
+        match (chunk, follows) {
+            (0, _) => {
+                self.total = total;
+                self.data = data;
+            }
+            (_, true) => self.data.extend(data),
+            (_, false) => {
+                self.reset();
+                return None;
+            }
+        }
+
+        self.next = chunk + 1;
+        match self.data.len() >= self.total {
+            true => {
+                self.next = 0;
+                Some(std::mem::take(&mut self.data))
+            }
+            false => None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.next = 0;
+        self.data.clear();
+    }
+}
+
+/// driver key of the LSM9DS1, outputs accel (m/s^2), mag (gauss, Adafruit_LSM9DS1
+/// doesn't convert it to uT) and gyro (rad/s)
+pub const LSM9DS1_DRIVER: &str = "DS1";
+
+/// Split an LSM9DS1 output into standard messages
+pub fn lsm9ds1_msgs(header: Header, data: &[f64]) -> Option<(Imu, MagneticField)> {
+    match data.len() {
+        9 => Some((
+            Imu::raw(
+                header.clone(),
+                Vector3::from_slice(&data[6..])?,
+                Vector3::from_slice(&data[..3])?,
+            ),
+            MagneticField {
+                header,
+                magnetic_field: Vector3::from_slice(&data[3..])?.scale(1E-4),
+                magnetic_field_covariance: [0.0; 9],
+            },
+        )),
+        _ => None,
+    }
+}
+
+schema! {
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct TaskCommunication {
+        pub name: String,
+        pub latch: u8,
+        pub rate: f64,
+        pub pc_time: f64,
+        pub mcu_time: f64,
+        pub run_time: f64,
+        pub data: Vec<f64>,
+    }
 }
 
 pub struct EmbeddedTask {
     pub name: String,
     pub driver: String,
+    /// what the pc knows about the driver, None if it's not in [`crate::rid::drivers::DRIVERS`]
+    pub spec: Option<&'static DriverSpec>,
 
     pub input_names: Vec<String>,
+    /// limit on the task topic
+    pub throttle: Option<Throttle>,
+    /// throttled copies of the task topic, published as `name/<suffix>`
+    pub republish: Vec<(String, Throttle)>,
 
     pub output: Vec<f64>,
     pub parameters: Vec<f64>,
 
     pub latch: u8,
+    pub seq: u32,
+    /// collects the chunks of the task's output
+    pub chunks: TaskDataAssembler,
     pub rate: f64,
+    /// rate the mcu says the task ran at in its last report
+    pub measured_rate: f64,
     pub pc_time: f64,
     pub mcu_time: f64,
     pub run_time: f64,
@@ -196,34 +325,70 @@ impl EmbeddedTask {
     ) -> EmbeddedTask {
         EmbeddedTask {
             name: name.clone(),
+            spec: driver_spec(&driver),
             driver: driver,
 
             output: vec![],
             parameters: params,
             input_names: input_names,
+            throttle: None,
+            republish: vec![],
 
             latch: 0,
+            seq: 0,
+            chunks: TaskDataAssembler::default(),
             rate: rate,
+            measured_rate: 0.0,
             pc_time: 0.0,
             mcu_time: 0.0,
             run_time: 0.0,
         }
     }
 
-    pub fn set_params(&mut self, params: Vec<f64>) {
+    /// Parameters are checked against the driver when it's in the registry
+    pub fn set_params(&mut self, params: Vec<f64>) -> Result<(), String> {
         if params.len() > MAX_TASK_PARAMETERS {
-            panic!(
-                "{:?} configuration exceeds maximum parameters ({MAX_TASK_PARAMETERS})",
-                self.name
-            );
+            return Err(format!(
+                "{} takes at most {MAX_TASK_PARAMETERS} parameters, got {}",
+                self.name,
+                params.len()
+            ));
         }
+        if let Some(spec) = self.spec {
+            spec.check_parameters(&params)
+                .map_err(|e| format!("{}: {e}", self.name))?;
+        }
+
         self.parameters = params;
+        Ok(())
     }
 
     pub fn driver(&self) -> Vec<u8> {
         self.driver.as_bytes().to_vec()
     }
 
+    /// What's wrong with the task's driver and parameters, for drivers
+    /// in the registry
+    pub fn check_driver(&self) -> Result<(), String> {
+        match self.spec {
+            Some(spec) => spec
+                .check_parameters(&self.parameters)
+                .map_err(|e| format!("{}: {e}", self.name)),
+            None => Err(format!(
+                "{}: unknown driver {:?}, the firmware runs it as an empty task",
+                self.name, self.driver
+            )),
+        }
+    }
+
+    /// Names of the values in the task's output
+    pub fn output_labels(&self) -> Vec<String> {
+        match self.spec {
+            Some(spec) => spec.output_labels(self.output.len()),
+            None => (0..self.output.len()).map(|i| format!("{i}")).collect(),
+        }
+    }
+
     pub fn params(&self) -> Vec<u8> {
         self.parameters
             .iter()
@@ -253,6 +418,8 @@ impl EmbeddedTask {
         // }
 
         self.latch = packet.latch;
+        self.seq = self.seq.wrapping_add(1);
+        self.measured_rate = packet.rate;
         self.output = packet.data;
         self.pc_time = packet.pc_time;
         self.mcu_time = packet.mcu_time;
@@ -273,53 +440,199 @@ pub struct RobotFirmware {
     pub sock: Sock,
     pub configured: Vec<bool>,
     pub tasks: Vec<EmbeddedTask>,
+    /// reports that couldn't be parsed, since the last [`RobotFirmware::diagnose`]
+    pub garbage_reports: u64,
+    /// firmware_tasks.yaml the tasks came from, where parameters get saved
+    pub config_path: Option<String>,
 }
 
 impl RobotFirmware {
     pub fn from_byu(byu: BuffYamlUtil) -> RobotFirmware {
-        let tasks: Vec<EmbeddedTask> = byu
-            .data()
+        RobotFirmware::with_names(byu, NameResolver::from_env())
+    }
+
+    /// The tasks in a firmware_tasks.yaml, missing items get placeholders
+    /// that [`TaskGraph::validate`] reports
+    pub fn parse_tasks(byu: &BuffYamlUtil) -> Vec<EmbeddedTask> {
+        byu.data()
             .as_hash()
             .unwrap()
             .iter()
             .map(|(key, data)| {
-                EmbeddedTask::named(
+                let mut task = EmbeddedTask::named(
                     key.as_str().unwrap().to_string(),
                     byu.parse_str("driver", data).unwrap_or("NUL".to_string()),
                     byu.parse_float("rate", data).unwrap_or(-1.0),
                     byu.parse_strs("inputs", data).unwrap_or(vec![]),
                     byu.parse_floats("parameters", data).unwrap_or(vec![]),
-                )
+                );
+                task.throttle = RobotFirmware::parse_throttle(byu, "throttle", data);
+                task.republish = match data["republish"].as_hash() {
+                    Some(copies) => copies
+                        .keys()
+                        .filter_map(|suffix| suffix.as_str())
+                        .filter_map(|suffix| {
+                            RobotFirmware::parse_throttle(byu, suffix, &data["republish"])
+                                .map(|throttle| (suffix.to_string(), throttle))
+                        })
+                        .collect(),
+                    None => vec![],
+                };
+                task
             })
-            .collect();
+            .collect()
+    }
+
+    /// `tasks` without the ones [`TaskGraph::validate`] has errors for, or
+    /// that take input from them, so they never get initializers. Past
+    /// [`MCU_MAX_TASKS`] the last tasks are left out. Everything is logged.
+    pub fn runnable_tasks(mut tasks: Vec<EmbeddedTask>) -> Vec<EmbeddedTask> {
+        loop {
+            let issues = TaskGraph::from_tasks(&tasks).validate();
+            let mut rejected: Vec<String> = vec![];
+            issues
+                .iter()
+                .filter(|issue| issue.is_error())
+                .for_each(|issue| match issue {
+                    TaskGraphIssue::TooManyTasks { .. } => {}
+                    _ => {
+                        error!("{issue}, not loading {}", issue.tasks().join(", "));
+                        rejected.extend(issue.tasks().into_iter().map(String::from));
+                    }
+                });
+
+            if rejected.is_empty() {
+                if tasks.len() > MCU_MAX_TASKS {
+                    let dropped: Vec<String> =
+                        tasks.drain(MCU_MAX_TASKS..).map(|task| task.name).collect();
+                    error!(
+                        "the mcu runs {MCU_MAX_TASKS} tasks, not loading {}",
+                        dropped.join(", ")
+                    );
+                    continue;
+                }
+
+                issues.iter().for_each(|issue| warn!("{issue}"));
+                return tasks;
+            }
 
-        let target_names: Vec<String> = (0..tasks.len())
-            .map(|i| tasks[i].name.clone() + "/ctrl")
+            tasks.retain(|task| !rejected.contains(&task.name));
+        }
+    }
+
+    /// Tasks from `byu` with topics resolved by `names` (one namespace per mcu),
+    /// the ones the firmware can't run are left out (see [`RobotFirmware::runnable_tasks`])
+    pub fn with_names(byu: BuffYamlUtil, names: NameResolver) -> RobotFirmware {
+        let tasks = RobotFirmware::runnable_tasks(RobotFirmware::parse_tasks(&byu));
+
+        let mut target_names: Vec<String> = (0..tasks.len())
+            .map(|i| join_name(&tasks[i].name, "ctrl"))
             .collect();
+        // after the tasks so target indices still line up with them
+        target_names.push(FIRMWARE_CTRL_NAME.to_string());
+
+        let mut sock = Sock::with_names(
+            "robot_fw",
+            target_names.iter().map(|name| name.as_str()).collect(),
+            vec![],
+            SockSocket::multicast(),
+            names,
+        );
+
+        tasks.iter().for_each(|task| {
+            let topic = sock.resolve(&task.name);
+            sock.advertise_schema::<TaskCommunication>(&topic);
+            if let Some(throttle) = task.throttle {
+                sock.set_throttle(&topic, throttle);
+            }
+
+            task.republish.iter().for_each(|(suffix, throttle)| {
+                let copy = sock.resolve(&join_name(&task.name, suffix));
+                sock.advertise_schema::<TaskCommunication>(&copy);
+                sock.set_throttle(&copy, *throttle);
+            });
+
+            if task.driver == LSM9DS1_DRIVER {
+                let imu = sock.resolve(&join_name(&task.name, "imu"));
+                let mag = sock.resolve(&join_name(&task.name, "mag"));
+                sock.advertise_schema::<Imu>(&imu);
+                sock.advertise_schema::<MagneticField>(&mag);
+            }
+
+            let param = Param::new(
+                &join_name(&task.name, "parameters"),
+                ParamValue::Floats(task.parameters.clone()),
+            )
+            .describe(&match task.spec {
+                Some(spec) => spec.describe_parameters(),
+                None => format!("{} parameters", task.driver),
+            });
+            let param = match task.spec {
+                Some(spec) => param.sized(spec.parameters.len(), spec.parameters.len()),
+                None => param.sized(0, MAX_TASK_PARAMETERS),
+            };
+            if let Err(e) = sock.declare_param(param) {
+                warn!("{e}, {} parameters can't be changed", task.name);
+            }
+
+            // names of the data in the task's reports, for drivers that name them
+            if let Some(spec) = task.spec.filter(|spec| spec.outputs.is_some()) {
+                let labels = Param::new(
+                    &join_name(&task.name, "outputs"),
+                    ParamValue::String(spec.output_labels(spec.n_outputs(0)).join(", ")),
+                )
+                .read_only()
+                .describe(&format!("names of the data in {}'s reports", task.name));
+                if let Err(e) = sock.declare_param(labels) {
+                    warn!("{e}, {} outputs aren't labeled", task.name);
+                }
+            }
+        });
 
         RobotFirmware {
-            sock: Sock::sinc(
-                "robot_fw",
-                target_names.iter().map(|name| name.as_str()).collect(),
-            ),
+            sock,
             configured: vec![false; tasks.len()],
             tasks: tasks,
+            garbage_reports: 0,
+            config_path: None,
+        }
+    }
+
+    /// Keep track of the robot's firmware_tasks.yaml so tuned parameters can be saved
+    pub fn from_robot_byu(byu: BuffYamlUtil) -> RobotFirmware {
+        let config_path = format!("{}/firmware_tasks.yaml", byu.yaml_path);
+        let mut rs = RobotFirmware::from_byu(byu);
+        rs.config_path = Some(config_path);
+        rs
+    }
+
+    /// Optional throttle `item` of a task, bad ones are reported and ignored
+    pub fn parse_throttle(byu: &BuffYamlUtil, item: &str, data: &Yaml) -> Option<Throttle> {
+        match data[item].is_badvalue() {
+            true => None,
+            false => match Throttle::from_yaml(byu, item, data) {
+                Ok(throttle) => Some(throttle),
+                Err(e) => {
+                    warn!("ignoring throttle {e}");
+                    None
+                }
+            },
         }
     }
 
     pub fn default() -> RobotFirmware {
         let byu = BuffYamlUtil::default("firmware_tasks");
-        RobotFirmware::from_byu(byu)
+        RobotFirmware::from_robot_byu(byu)
     }
 
     pub fn new(robot_name: &str) -> RobotFirmware {
         let byu = BuffYamlUtil::robot(robot_name, "firmware_tasks");
-        RobotFirmware::from_byu(byu)
+        RobotFirmware::from_robot_byu(byu)
     }
 
     pub fn from_self() -> RobotFirmware {
         let byu = BuffYamlUtil::from_self("firmware_tasks");
-        RobotFirmware::from_byu(byu)
+        RobotFirmware::from_robot_byu(byu)
     }
 
     pub fn get_task_names(&self) -> Vec<&String> {
@@ -330,6 +643,8 @@ impl RobotFirmware {
         self.tasks.iter().position(|task| name == task.name)
     }
 
+    /// Ids of the tasks named, unknown names are left out ([`TaskGraph::validate`]
+    /// reports them when the tasks load)
     pub fn input_task_ids(&self, names: &Vec<String>) -> Vec<u8> {
         names
             .iter()
@@ -369,29 +684,111 @@ impl RobotFirmware {
             .collect()
     }
 
-    pub fn parse_sock(&mut self) -> Option<HidPacket> {
+    pub fn parse_sock(&mut self) -> Vec<HidPacket> {
         let mut buffer = [0u8; UDP_PACKET_SIZE];
-        match self.sock.try_rx(&mut buffer) {
-            Some(i) => {
-                let packet: TaskMarshall =
-                    bincode::deserialize(&self.sock.messages[i].to_payload()).unwrap();
-                match self.sock.is_target(&packet.name) {
-                    Some(i) => match packet.mode {
-                        TaskMarshallType::Input => Some(input_latch(i as u8, &packet.data)),
-                        TaskMarshallType::Output => Some(output_latch(i as u8, &packet.data)),
-                        TaskMarshallType::Parameter => {
-                            self.configured[i] = false;
-                            self.tasks[i].set_params(packet.data);
-                            None
-                        }
-                    },
-                    _ => None,
+        let latch = match self.sock.try_rx(&mut buffer) {
+            Some(i) => self.parse_marshall(i),
+            _ => vec![],
+        };
+
+        self.apply_param_changes();
+        self.sock.publish_logs();
+        latch
+    }
+
+    /// Packets for a marshall from the network, latches can take several
+    pub fn parse_marshall(&mut self, idx: usize) -> Vec<HidPacket> {
+        let packet: TaskMarshall = match self.sock.decode_message(idx) {
+            Ok(packet) => packet,
+            Err(e) => {
+                warn!("bad task marshall {e}");
+                return vec![];
+            }
+        };
+        if packet.mode == TaskMarshallType::Save {
+            match self.save_parameters() {
+                Ok(backup) => {
+                    info!("saved parameters, old config in {backup}")
                 }
+                Err(e) => error!("failed to save parameters {e}"),
             }
-            _ => None,
+            return vec![];
+        }
+
+        match self.sock.is_target(&self.sock.resolve(&packet.name)) {
+            Some(i) if i < self.tasks.len() => match packet.mode {
+                TaskMarshallType::Input | TaskMarshallType::Output
+                    if packet.data.len() > MAX_TASK_DATA =>
+                {
+                    warn!(
+                        "{} latch is too long ({} > {MAX_TASK_DATA})",
+                        self.tasks[i].name,
+                        packet.data.len()
+                    );
+                    vec![]
+                }
+                TaskMarshallType::Input => input_latch(i as u8, &packet.data),
+                TaskMarshallType::Output => output_latch(i as u8, &packet.data),
+                TaskMarshallType::Parameter => {
+                    // goes through the parameter service so everyone hears about it
+                    let name = join_name(&self.tasks[i].name, "parameters");
+                    if let Err(e) = self.sock.set_param(&name, ParamValue::Floats(packet.data)) {
+                        warn!("dropped parameters for {}, {e}", self.tasks[i].name);
+                    }
+                    vec![]
+                }
+                TaskMarshallType::Save => vec![],
+            },
+            _ => vec![],
         }
     }
 
+    /// Write every task's current parameters into the firmware_tasks.yaml at `path`,
+    /// everything else in the file is left alone. The old file is kept as `path.bak`,
+    /// which is returned.
+    pub fn save_parameters_to(&self, path: &str) -> io::Result<String> {
+        let mut editor = YamlEditor::read(path)?;
+        for task in self.tasks.iter().filter(|task| !task.parameters.is_empty()) {
+            editor
+                .set_floats(&task.name, "parameters", &task.parameters)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        }
+
+        let backup = backup_file(path)?;
+        editor.save(path)?;
+        Ok(backup)
+    }
+
+    /// [`RobotFirmware::save_parameters_to`] the yaml the tasks were loaded from
+    pub fn save_parameters(&self) -> io::Result<String> {
+        match &self.config_path {
+            Some(path) => self.save_parameters_to(path),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "tasks weren't loaded from a robot's firmware_tasks.yaml",
+            )),
+        }
+    }
+
+    /// Give tasks the parameters set through the parameter service, they
+    /// are sent to the mcu with the other unconfigured parameters
+    pub fn apply_param_changes(&mut self) {
+        self.sock.param_changes().into_iter().for_each(|name| {
+            let task = self
+                .tasks
+                .iter()
+                .position(|task| join_name(&task.name, "parameters") == name);
+
+            if let (Some(i), Some(params)) = (task, self.sock.param::<Vec<f64>>(&name)) {
+                match self.tasks[i].set_params(params) {
+                    Ok(_) => self.configured[i] = false,
+                    Err(e) => warn!("{e}"),
+                }
+            }
+        });
+    }
+
+    /// Publish a task's output once every chunk of it arrived
     pub fn parse_hid(
         &mut self,
         pc_time: f64,
@@ -400,43 +797,126 @@ impl RobotFirmware {
         task_idx: usize,
         buffer: HidPacket,
     ) {
-        if self.tasks.len() > task_idx {
-            let data_length = buffer[HID_DATA_INDEX] as usize;
-
-            if data_length == 0 {
-                // tasks send 0 length data when unconfigured
+        if self.tasks.len() <= task_idx {
+            self.garbage_reports += 1;
+            return;
+        }
 
+        let data = match parse_task_data(&buffer) {
+            // tasks send 0 length data when unconfigured
+            Some((_, 0, _)) => {
+                self.tasks[task_idx].chunks.reset();
                 self.configured[task_idx] = false;
-            } else if data_length < MAX_HID_FLOAT_DATA {
-                // garbage shield
-
-                let comm_packet = TaskCommunication {
-                    name: format!("{}", self.tasks[task_idx].name),
-                    latch: buffer[HID_TOGL_INDEX],
-                    rate: 1.0 / (mcu_time - self.tasks[task_idx].mcu_time),
-                    pc_time: pc_time,
-                    mcu_time: mcu_time,
-                    run_time: run_time,
-                    data: buffer[4..4 + (4 * data_length)]
-                        .to_vec()
-                        .chunks(4)
-                        .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()) as f64)
-                        .collect(),
-                };
+                return;
+            }
+            Some((chunk, total, data)) => {
+                match self.tasks[task_idx].chunks.push(chunk, total, data) {
+                    Some(data) => data,
+                    // the rest is in the next reports
+                    None => return,
+                }
+            }
+            None => {
+                self.garbage_reports += 1;
+                return;
+            }
+        };
+
+        let comm_packet = TaskCommunication {
+            name: format!("{}", self.tasks[task_idx].name),
+            latch: buffer[HID_TOGL_INDEX],
+            rate: 1.0 / (mcu_time - self.tasks[task_idx].mcu_time),
+            pc_time: pc_time,
+            mcu_time: mcu_time,
+            run_time: run_time,
+            data,
+        };
+
+        // println!("{} {}", self.tasks[task_idx].name, (comm_packet.pc_time - self.tasks[task_idx].pc_time + comm_packet.mcu_time - self.tasks[task_idx].mcu_time) / 2.0);
+
+        let micros = (1E6 / self.tasks[task_idx].rate) as u64;
+        let topic = self.sock.resolve(&comm_packet.name);
+        self.sock.tx_any_payload(&topic, &comm_packet, micros);
+
+        (0..self.tasks[task_idx].republish.len()).for_each(|i| {
+            let (suffix, throttle) = self.tasks[task_idx].republish[i].clone();
+            let copy = self.sock.resolve(&join_name(&comm_packet.name, &suffix));
+            self.sock
+                .tx_any_payload(&copy, &comm_packet, throttle.period(micros));
+        });
 
-                // println!("{} {}", self.tasks[task_idx].name, (comm_packet.pc_time - self.tasks[task_idx].pc_time + comm_packet.mcu_time - self.tasks[task_idx].mcu_time) / 2.0);
+        self.tasks[task_idx].update(comm_packet);
+        self.publish_msgs(task_idx);
 
-                self.sock.tx_any_payload(
-                    &comm_packet.name,
-                    &comm_packet,
-                    (1E6 / self.tasks[task_idx].rate) as u64,
-                );
+        self.configured[task_idx] = true;
+    }
 
-                self.tasks[task_idx].update(comm_packet);
+    /// How a task is doing, it's slow if it runs under [`TASK_SLOW_RATIO`] of its rate
+    pub fn task_status(&self, task_idx: usize) -> DiagnosticStatus {
+        let task = &self.tasks[task_idx];
+        let status = match (
+            self.configured[task_idx],
+            task.measured_rate < TASK_SLOW_RATIO * task.rate,
+        ) {
+            (false, _) => DiagnosticStatus::warn(&task.name, "not configured"),
+            (true, true) => DiagnosticStatus::warn(&task.name, "running slow"),
+            (true, false) => DiagnosticStatus::ok(&task.name, "running"),
+        };
+
+        let status = status
+            .hardware(&task.driver)
+            .with("rate", format!("{:.1}", task.rate))
+            .with("measured rate", format!("{:.1}", task.measured_rate))
+            .with("run time", format!("{:.6}", task.run_time))
+            .with("reports", task.seq)
+            .with("incomplete reports", task.chunks.dropped);
+
+        // the last output, named when the driver's known
+        match task.spec {
+            Some(_) => task
+                .output_labels()
+                .into_iter()
+                .zip(task.output.iter())
+                .fold(status, |status, (label, x)| {
+                    status.with(&label, format!("{x:.6}"))
+                }),
+            None => status,
+        }
+    }
+
+    /// Report every task and the garbage count (see [`crate::socks::diagnostics`])
+    pub fn diagnose(&mut self) {
+        (0..self.tasks.len()).for_each(|i| {
+            let status = self.task_status(i);
+            self.sock.diagnose(status);
+        });
 
-                self.configured[task_idx] = true;
-            } else {
-                println!("[Robot-Firmware]: garbage report {buffer:?}");
+        let reports = match self.garbage_reports {
+            0 => DiagnosticStatus::ok("reports", "all parsed"),
+            _ => DiagnosticStatus::warn("reports", "garbage reports"),
+        };
+        self.sock
+            .diagnose(reports.with("garbage", self.garbage_reports));
+        self.garbage_reports = 0;
+    }
+
+    /// Publish standard messages (see [`crate::msgs`]) for the drivers that
+    /// have them, next to the raw task topic
+    pub fn publish_msgs(&mut self, task_idx: usize) {
+        let task = &self.tasks[task_idx];
+        let name = task.name.clone();
+        let header = Header::new(&name, task.seq, self.sock.now());
+        let micros = (1E6 / task.rate) as u64;
+
+        if task.driver == LSM9DS1_DRIVER {
+            match lsm9ds1_msgs(header, &task.output) {
+                Some((imu, mag)) => {
+                    let imu_topic = self.sock.resolve(&join_name(&name, "imu"));
+                    let mag_topic = self.sock.resolve(&join_name(&name, "mag"));
+                    self.sock.tx_any_payload(&imu_topic, &imu, micros);
+                    self.sock.tx_any_payload(&mag_topic, &mag, micros);
+                }
+                _ => warn!("{name} isn't a {LSM9DS1_DRIVER} output"),
             }
         }
     }
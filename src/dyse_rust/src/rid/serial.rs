@@ -0,0 +1,364 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+extern crate libc;
+
+use crate::rid::transport::HidTransport;
+use std::{
+    collections::VecDeque,
+    ffi::CString,
+    fs::File,
+    io::{self, Read, Write},
+    os::fd::{AsRawFd, FromRawFd},
+    time::{Duration, Instant},
+};
+
+/// Biggest frame the decoder keeps (COBS overhead and crc included),
+/// anything longer is junk from a bad link
+pub const SERIAL_MAX_FRAME: usize = 1024;
+/// Frames end with a zero, COBS keeps zeros out of the frame
+pub const SERIAL_FRAME_END: u8 = 0;
+/// How long a write can wait on a full tty buffer
+pub const SERIAL_WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+pub const SERIAL_DEFAULT_BAUD: u32 = 115200;
+
+/// CRC-16/CCITT-FALSE (poly 0x1021, init 0xFFFF)
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, &b| {
+        (0..8).fold(crc ^ ((b as u16) << 8), |crc, _| match crc & 0x8000 {
+            0 => crc << 1,
+            _ => (crc << 1) ^ 0x1021,
+        })
+    })
+}
+
+/// Consistent overhead byte stuffing, the output has no zeros
+pub fn cobs_encode(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / 254 + 2);
+    let mut code_index = 0;
+    out.push(0);
+
+    data.iter().for_each(|&b| {
+        if b != 0 {
+            out.push(b);
+        }
+
+        if b == 0 || out.len() - code_index == 255 {
+            out[code_index] = (out.len() - code_index) as u8;
+            code_index = out.len();
+            out.push(0);
+        }
+    });
+
+    out[code_index] = (out.len() - code_index) as u8;
+    out
+}
+
+/// Undo [`cobs_encode`], None if the data wasn't encoded
+pub fn cobs_decode(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+
+    while i < data.len() {
+        let code = data[i] as usize;
+        if code == 0 || i + code > data.len() {
+            return None;
+        }
+
+        out.extend_from_slice(&data[i + 1..i + code]);
+        i += code;
+
+        if code < 255 && i < data.len() {
+            out.push(0);
+        }
+    }
+
+    Some(out)
+}
+
+/// Payload, crc (little endian), stuffed and ended with [`SERIAL_FRAME_END`]
+pub fn encode_frame(payload: &[u8]) -> Vec<u8> {
+    let mut data = payload.to_vec();
+    data.extend_from_slice(&crc16(payload).to_le_bytes());
+    let mut frame = cobs_encode(&data);
+    frame.push(SERIAL_FRAME_END);
+    frame
+}
+
+/// Payload of one frame (without the end byte), None if it's corrupt
+pub fn decode_frame(frame: &[u8]) -> Option<Vec<u8>> {
+    let mut data = cobs_decode(frame)?;
+    if data.len() < 2 {
+        return None;
+    }
+
+    let crc = data.split_off(data.len() - 2);
+    match crc16(&data).to_le_bytes() == crc[..] {
+        true => Some(data),
+        false => None,
+    }
+}
+
+/// Splits a byte stream into payloads, corrupt frames are counted and dropped
+#[derive(Default)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+    pub payloads: VecDeque<Vec<u8>>,
+    pub bad_frames: u64,
+}
+
+impl FrameDecoder {
+    pub fn push(&mut self, bytes: &[u8]) {
+        bytes.iter().for_each(|&b| match b {
+            SERIAL_FRAME_END => {
+                if !self.buffer.is_empty() {
+                    match decode_frame(&self.buffer) {
+                        Some(payload) => self.payloads.push_back(payload),
+                        None => self.bad_frames += 1,
+                    }
+                    self.buffer.clear();
+                }
+            }
+            _ => match self.buffer.len() < SERIAL_MAX_FRAME {
+                true => self.buffer.push(b),
+                false => {
+                    // lost an end byte, wait for the next one
+                    self.bad_frames += 1;
+                    self.buffer.clear();
+                }
+            },
+        });
+    }
+
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        self.payloads.pop_front()
+    }
+}
+
+fn os_result(ret: libc::c_int) -> io::Result<libc::c_int> {
+    match ret {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(ret),
+    }
+}
+
+/// termios speed for a baud rate, None if the tty can't do it
+pub fn baud_speed(baud: u32) -> Option<libc::speed_t> {
+    match baud {
+        9600 => Some(libc::B9600),
+        19200 => Some(libc::B19200),
+        38400 => Some(libc::B38400),
+        57600 => Some(libc::B57600),
+        115200 => Some(libc::B115200),
+        230400 => Some(libc::B230400),
+        460800 => Some(libc::B460800),
+        921600 => Some(libc::B921600),
+        1000000 => Some(libc::B1000000),
+        2000000 => Some(libc::B2000000),
+        4000000 => Some(libc::B4000000),
+        _ => None,
+    }
+}
+
+/// Raw (no echo, no line editing) and non blocking at `baud`
+pub fn configure_tty(file: &File, baud: u32) -> io::Result<()> {
+    let speed = baud_speed(baud).ok_or(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("unsupported baud {baud}"),
+    ))?;
+
+    unsafe {
+        let mut termios: libc::termios = std::mem::zeroed();
+        os_result(libc::tcgetattr(file.as_raw_fd(), &mut termios))?;
+        libc::cfmakeraw(&mut termios);
+        termios.c_cflag |= libc::CLOCAL | libc::CREAD;
+        termios.c_cc[libc::VMIN] = 0;
+        termios.c_cc[libc::VTIME] = 0;
+        os_result(libc::cfsetspeed(&mut termios, speed))?;
+        os_result(libc::tcsetattr(file.as_raw_fd(), libc::TCSANOW, &termios))?;
+    }
+
+    Ok(())
+}
+
+/// Open a tty without making it our controlling terminal
+pub fn open_tty(path: &str) -> io::Result<File> {
+    let c_path = CString::new(path)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "bad tty path"))?;
+    let fd = os_result(unsafe {
+        libc::open(
+            c_path.as_ptr(),
+            libc::O_RDWR | libc::O_NOCTTY | libc::O_NONBLOCK | libc::O_CLOEXEC,
+        )
+    })?;
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// Framed packets over a USB CDC or UART tty, same semantics as hid reports:
+/// one payload per read/write and reads don't block. The firmware speaks it
+/// when built with `make COMMS=serial` (hid_comms/serial_comms), frames from
+/// it start with an end byte too so anything it printed is dropped as a bad frame.
+pub struct SerialTransport {
+    pub path: String,
+    pub baud: u32,
+    port: Option<File>,
+    pub decoder: FrameDecoder,
+}
+
+impl SerialTransport {
+    pub fn new(path: &str, baud: u32) -> SerialTransport {
+        SerialTransport {
+            path: path.to_string(),
+            baud,
+            port: None,
+            decoder: FrameDecoder::default(),
+        }
+    }
+
+    fn port(&mut self) -> io::Result<&mut File> {
+        self.port.as_mut().ok_or(io::Error::new(
+            io::ErrorKind::NotConnected,
+            "port isn't open",
+        ))
+    }
+
+    /// Read whatever is waiting into the decoder. Raw ttys read 0 bytes
+    /// when there's nothing, so hang ups come from poll.
+    fn fill(&mut self) -> io::Result<()> {
+        let mut poll = libc::pollfd {
+            fd: self.port()?.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        os_result(unsafe { libc::poll(&mut poll, 1, 0) })?;
+
+        if poll.revents & (libc::POLLHUP | libc::POLLERR | libc::POLLNVAL) != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                format!("{} hung up", self.path),
+            ));
+        }
+
+        if poll.revents & libc::POLLIN == 0 {
+            return Ok(());
+        }
+
+        let mut bytes = [0; 256];
+        loop {
+            match self.port()?.read(&mut bytes) {
+                Ok(0) => return Ok(()),
+                Ok(n) => self.decoder.push(&bytes[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl HidTransport for SerialTransport {
+    /// Serial ports are found by path, `vid` and `pid` aren't used
+    fn open(&mut self, _vid: u16, _pid: u16) -> io::Result<()> {
+        let port = open_tty(&self.path)?;
+        configure_tty(&port, self.baud)?;
+        self.port = Some(port);
+        self.decoder = FrameDecoder::default();
+        Ok(())
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if self.decoder.payloads.is_empty() {
+            self.fill()?;
+        }
+
+        match self.decoder.pop() {
+            Some(payload) => {
+                let n = payload.len().min(buffer.len());
+                buffer[..n].copy_from_slice(&payload[..n]);
+                Ok(n)
+            }
+            None => Ok(0),
+        }
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let frame = encode_frame(buffer);
+        let port = self.port()?;
+        let t = Instant::now();
+        let mut sent = 0;
+
+        while sent < frame.len() {
+            match port.write(&frame[sent..]) {
+                Ok(n) => sent += n,
+                Err(e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        && t.elapsed() < SERIAL_WRITE_TIMEOUT =>
+                {
+                    // sleep until the tty drains instead of spinning on it
+                    let mut poll = libc::pollfd {
+                        fd: port.as_raw_fd(),
+                        events: libc::POLLOUT,
+                        revents: 0,
+                    };
+                    let wait = SERIAL_WRITE_TIMEOUT.saturating_sub(t.elapsed());
+                    match os_result(unsafe {
+                        libc::poll(&mut poll, 1, wait.as_millis().max(1) as libc::c_int)
+                    }) {
+                        Err(e) if e.kind() != io::ErrorKind::Interrupted => return Err(e),
+                        _ => {}
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(buffer.len())
+    }
+
+    fn handle(&self) -> Box<dyn HidTransport> {
+        Box::new(SerialTransport::new(&self.path, self.baud))
+    }
+}
+
+/// Pseudo terminal pair, the master end plays the mcu and
+/// [`SerialTransport`] opens `path` (the slave end)
+pub struct Pty {
+    pub master: File,
+    pub path: String,
+}
+
+impl Pty {
+    pub fn open() -> io::Result<Pty> {
+        let fd = os_result(unsafe {
+            libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY | libc::O_NONBLOCK | libc::O_CLOEXEC)
+        })?;
+        let master = unsafe { File::from_raw_fd(fd) };
+
+        let mut name = [0 as libc::c_char; 128];
+        unsafe {
+            os_result(libc::grantpt(fd))?;
+            os_result(libc::unlockpt(fd))?;
+            match libc::ptsname_r(fd, name.as_mut_ptr(), name.len()) {
+                0 => {}
+                e => return Err(io::Error::from_raw_os_error(e)),
+            }
+        }
+
+        let path = unsafe { std::ffi::CStr::from_ptr(name.as_ptr()) }
+            .to_string_lossy()
+            .to_string();
+
+        Ok(Pty { master, path })
+    }
+}
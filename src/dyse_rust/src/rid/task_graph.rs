@@ -0,0 +1,462 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::{
+    rid::{
+        data_structures::{HID_INIT_INPUTS_INDEX, HID_VERSION_INDEX},
+        drivers::DriverSpec,
+        layer::HidConfig,
+        robot_firmware::{task_data_chunks, EmbeddedTask, RobotFirmware, MAX_TASK_PARAMETERS},
+    },
+    utilities::loaders::BuffYamlUtil,
+};
+use std::fmt;
+
+/// The firmware keeps its tasks in Vectors that can't grow past this
+pub const MCU_MAX_TASKS: usize = 128;
+/// Input ids have to fit in the init report, before the report trailer
+pub const MCU_MAX_INPUTS: usize = HID_VERSION_INDEX - HID_INIT_INPUTS_INDEX;
+/// Task periods go to the mcu as u16 micros
+pub const MCU_MIN_RATE: f64 = 1E6 / u16::MAX as f64;
+pub const MCU_MAX_RATE: f64 = 1E6;
+
+/// A task and the tasks it takes input from
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskGraphNode {
+    pub name: String,
+    pub driver: String,
+    pub spec: Option<&'static DriverSpec>,
+    pub rate: f64,
+    pub parameters: Vec<f64>,
+    /// each input's name and task index, None when no task has that name
+    pub inputs: Vec<(String, Option<usize>)>,
+}
+
+/// Something in a task graph the mcu won't run (errors) or won't run the
+/// way it reads (warnings)
+#[derive(Clone, Debug, PartialEq)]
+pub enum TaskGraphIssue {
+    UnknownInput {
+        task: String,
+        input: String,
+    },
+    UnknownDriver {
+        task: String,
+        driver: String,
+    },
+    SelfLoop {
+        task: String,
+    },
+    Cycle {
+        tasks: Vec<String>,
+    },
+    Rate {
+        task: String,
+        rate: f64,
+    },
+    SlowInput {
+        task: String,
+        input: String,
+        rate: f64,
+        input_rate: f64,
+    },
+    Parameters {
+        task: String,
+        error: String,
+    },
+    TooManyParameters {
+        task: String,
+        n: usize,
+    },
+    InputSize {
+        task: String,
+        expected: usize,
+        found: usize,
+    },
+    TooManyInputs {
+        task: String,
+        n: usize,
+    },
+    TooManyTasks {
+        n: usize,
+    },
+    Bandwidth {
+        reports: f64,
+        report_rate: f64,
+    },
+}
+
+impl TaskGraphIssue {
+    /// Errors keep a task (or all of them) from running on the mcu
+    pub fn is_error(&self) -> bool {
+        !matches!(
+            self,
+            TaskGraphIssue::UnknownDriver { .. }
+                | TaskGraphIssue::SelfLoop { .. }
+                | TaskGraphIssue::Cycle { .. }
+                | TaskGraphIssue::SlowInput { .. }
+                | TaskGraphIssue::Bandwidth { .. }
+        )
+    }
+
+    /// The tasks it's about, none when it's about the whole graph
+    pub fn tasks(&self) -> Vec<&str> {
+        match self {
+            TaskGraphIssue::UnknownInput { task, .. }
+            | TaskGraphIssue::UnknownDriver { task, .. }
+            | TaskGraphIssue::SelfLoop { task }
+            | TaskGraphIssue::Rate { task, .. }
+            | TaskGraphIssue::SlowInput { task, .. }
+            | TaskGraphIssue::Parameters { task, .. }
+            | TaskGraphIssue::TooManyParameters { task, .. }
+            | TaskGraphIssue::InputSize { task, .. }
+            | TaskGraphIssue::TooManyInputs { task, .. } => vec![task],
+            TaskGraphIssue::Cycle { tasks } => tasks.iter().map(|task| task.as_str()).collect(),
+            TaskGraphIssue::TooManyTasks { .. } | TaskGraphIssue::Bandwidth { .. } => vec![],
+        }
+    }
+}
+
+impl fmt::Display for TaskGraphIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TaskGraphIssue::UnknownInput { task, input } => {
+                write!(f, "{task}: no task named {input:?} to take input from")
+            }
+            TaskGraphIssue::UnknownDriver { task, driver } => write!(
+                f,
+                "{task}: unknown driver {driver:?}, the firmware runs it as an empty task"
+            ),
+            TaskGraphIssue::SelfLoop { task } => {
+                write!(f, "{task}: takes its own output, from its last run")
+            }
+            TaskGraphIssue::Cycle { tasks } => write!(
+                f,
+                "feedback loop through {}, they run on each other's last outputs",
+                tasks.join(", ")
+            ),
+            TaskGraphIssue::Rate { task, rate } => write!(
+                f,
+                "{task}: rate {rate} Hz, the mcu takes {MCU_MIN_RATE:.2} to {MCU_MAX_RATE} Hz"
+            ),
+            TaskGraphIssue::SlowInput {
+                task,
+                input,
+                rate,
+                input_rate,
+            } => write!(
+                f,
+                "{task}: runs at {rate} Hz on {input} at {input_rate} Hz, it sees repeats"
+            ),
+            TaskGraphIssue::Parameters { task, error } => write!(f, "{task}: {error}"),
+            TaskGraphIssue::TooManyParameters { task, n } => {
+                write!(f, "{task}: {n} parameters, at most {MAX_TASK_PARAMETERS}")
+            }
+            TaskGraphIssue::InputSize {
+                task,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{task}: takes {expected} inputs, its input tasks give {found}"
+            ),
+            TaskGraphIssue::TooManyInputs { task, n } => {
+                write!(f, "{task}: {n} input tasks, at most {MCU_MAX_INPUTS}")
+            }
+            TaskGraphIssue::TooManyTasks { n } => {
+                write!(f, "{n} tasks, the mcu holds {MCU_MAX_TASKS}")
+            }
+            TaskGraphIssue::Bandwidth {
+                reports,
+                report_rate,
+            } => write!(
+                f,
+                "feedback needs {reports:.0} reports/s, the link carries {report_rate:.0}, some are skipped"
+            ),
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", escape(s))
+}
+
+/// Which tasks feed which, built from a firmware_tasks.yaml before it goes
+/// to the mcu
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskGraph {
+    pub nodes: Vec<TaskGraphNode>,
+    /// reports a second the link carries, every chunk of feedback takes one
+    pub report_rate: f64,
+}
+
+impl TaskGraph {
+    pub fn from_tasks(tasks: &[EmbeddedTask]) -> TaskGraph {
+        let nodes = tasks
+            .iter()
+            .map(|task| TaskGraphNode {
+                name: task.name.clone(),
+                driver: task.driver.clone(),
+                spec: task.spec,
+                rate: task.rate,
+                parameters: task.parameters.clone(),
+                inputs: task
+                    .input_names
+                    .iter()
+                    .map(|input| {
+                        let id = tasks.iter().position(|task| &task.name == input);
+                        (input.clone(), id)
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        TaskGraph {
+            nodes,
+            report_rate: 1E6 / HidConfig::default().cycle_time_us,
+        }
+    }
+
+    pub fn from_byu(byu: &BuffYamlUtil) -> TaskGraph {
+        TaskGraph::from_tasks(&RobotFirmware::parse_tasks(byu))
+    }
+
+    /// Indices of the tasks `i` takes input from (known names only)
+    fn input_ids(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        self.nodes[i].inputs.iter().filter_map(|(_, id)| *id)
+    }
+
+    /// How many floats each task outputs, None when it depends on a driver
+    /// the registry doesn't know (or on a loop)
+    pub fn output_sizes(&self) -> Vec<Option<usize>> {
+        let mut sizes: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        // each pass settles at least one more task unless the rest are stuck
+        (0..self.nodes.len()).for_each(|_| {
+            (0..self.nodes.len()).for_each(|i| {
+                sizes[i] = match self.nodes[i].spec {
+                    Some(spec) if spec.outputs.is_some() => Some(spec.n_outputs(0)),
+                    Some(spec) => self.input_size(i, &sizes).map(|n| spec.n_outputs(n)),
+                    None => None,
+                };
+            });
+        });
+        sizes
+    }
+
+    /// Floats of input task `i` gets, None when an input's size isn't known
+    fn input_size(&self, i: usize, sizes: &[Option<usize>]) -> Option<usize> {
+        self.nodes[i]
+            .inputs
+            .iter()
+            .map(|(_, id)| id.and_then(|id| sizes[id]))
+            .sum()
+    }
+
+    /// Tasks `i` can reach by following its outputs
+    fn reachable(&self, i: usize) -> Vec<bool> {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack = vec![i];
+        while let Some(j) = stack.pop() {
+            (0..self.nodes.len())
+                .filter(|&k| !seen[k] && self.input_ids(k).any(|id| id == j))
+                .collect::<Vec<usize>>()
+                .into_iter()
+                .for_each(|k| {
+                    seen[k] = true;
+                    stack.push(k);
+                });
+        }
+        seen
+    }
+
+    /// Loops through more than one task, each reported once
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        let reach: Vec<Vec<bool>> = (0..self.nodes.len()).map(|i| self.reachable(i)).collect();
+        let mut cycles: Vec<Vec<usize>> = vec![];
+        (0..self.nodes.len()).for_each(|i| {
+            let cycle: Vec<usize> = (0..self.nodes.len())
+                .filter(|&j| reach[i][j] && reach[j][i])
+                .collect();
+            if cycle.len() > 1 && cycle[0] == i {
+                cycles.push(cycle);
+            }
+        });
+        cycles
+    }
+
+    /// Everything that's wrong with the graph, errors first
+    pub fn validate(&self) -> Vec<TaskGraphIssue> {
+        let mut issues = vec![];
+        let sizes = self.output_sizes();
+
+        if self.nodes.len() > MCU_MAX_TASKS {
+            issues.push(TaskGraphIssue::TooManyTasks {
+                n: self.nodes.len(),
+            });
+        }
+
+        self.nodes.iter().enumerate().for_each(|(i, node)| {
+            let task = node.name.clone();
+
+            if !(MCU_MIN_RATE..=MCU_MAX_RATE).contains(&node.rate) {
+                issues.push(TaskGraphIssue::Rate {
+                    task: task.clone(),
+                    rate: node.rate,
+                });
+            }
+
+            if node.parameters.len() > MAX_TASK_PARAMETERS {
+                issues.push(TaskGraphIssue::TooManyParameters {
+                    task: task.clone(),
+                    n: node.parameters.len(),
+                });
+            }
+            match node.spec {
+                Some(spec) => {
+                    if let Err(error) = spec.check_parameters(&node.parameters) {
+                        issues.push(TaskGraphIssue::Parameters {
+                            task: task.clone(),
+                            error,
+                        });
+                    }
+                }
+                None => issues.push(TaskGraphIssue::UnknownDriver {
+                    task: task.clone(),
+                    driver: node.driver.clone(),
+                }),
+            }
+
+            if node.inputs.len() > MCU_MAX_INPUTS {
+                issues.push(TaskGraphIssue::TooManyInputs {
+                    task: task.clone(),
+                    n: node.inputs.len(),
+                });
+            }
+
+            node.inputs.iter().for_each(|(input, id)| match id {
+                None => issues.push(TaskGraphIssue::UnknownInput {
+                    task: task.clone(),
+                    input: input.clone(),
+                }),
+                Some(id) if *id == i => {
+                    issues.push(TaskGraphIssue::SelfLoop { task: task.clone() })
+                }
+                Some(id) if self.nodes[*id].rate < node.rate => {
+                    issues.push(TaskGraphIssue::SlowInput {
+                        task: task.clone(),
+                        input: input.clone(),
+                        rate: node.rate,
+                        input_rate: self.nodes[*id].rate,
+                    })
+                }
+                Some(_) => {}
+            });
+
+            // the firmware won't link a task to inputs of the wrong size
+            match (
+                node.spec.and_then(|spec| spec.inputs),
+                self.input_size(i, &sizes),
+            ) {
+                (Some(expected), Some(found)) if expected != found => {
+                    issues.push(TaskGraphIssue::InputSize {
+                        task,
+                        expected,
+                        found,
+                    })
+                }
+                _ => {}
+            }
+        });
+
+        self.cycles().into_iter().for_each(|cycle| {
+            issues.push(TaskGraphIssue::Cycle {
+                tasks: cycle.iter().map(|&i| self.nodes[i].name.clone()).collect(),
+            })
+        });
+
+        let reports: f64 = self
+            .nodes
+            .iter()
+            .zip(sizes.iter())
+            .map(|(node, size)| node.rate.max(0.0) * task_data_chunks(size.unwrap_or(0)) as f64)
+            .sum();
+        if reports > self.report_rate {
+            issues.push(TaskGraphIssue::Bandwidth {
+                reports,
+                report_rate: self.report_rate,
+            });
+        }
+
+        issues.sort_by_key(|issue| !issue.is_error());
+        issues
+    }
+
+    /// The graph in graphviz dot, tasks with errors are red and tasks with
+    /// warnings orange, inputs no task has are dashed
+    pub fn to_dot(&self) -> String {
+        let issues = self.validate();
+        let sizes = self.output_sizes();
+        let color = |name: &str| {
+            let mut about = issues.iter().filter(|issue| issue.tasks().contains(&name));
+            match about.next() {
+                Some(issue) if issue.is_error() => ", color=red",
+                Some(_) => ", color=orange",
+                None => "",
+            }
+        };
+
+        let mut lines = vec![
+            "digraph firmware_tasks {".to_string(),
+            "    rankdir=LR;".to_string(),
+        ];
+        self.nodes.iter().for_each(|node| {
+            lines.push(format!(
+                "    {} [label=\"{}\\n{} {} Hz\"{}];",
+                quote(&node.name),
+                escape(&node.name),
+                escape(&node.driver),
+                node.rate,
+                color(&node.name),
+            ))
+        });
+
+        self.nodes.iter().for_each(|node| {
+            node.inputs.iter().for_each(|(input, id)| match id {
+                Some(id) => lines.push(format!(
+                    "    {} -> {}{};",
+                    quote(input),
+                    quote(&node.name),
+                    sizes[*id].map_or(String::new(), |n| format!(" [label=\"{n}\"]")),
+                )),
+                None => {
+                    lines.push(format!(
+                        "    {} [shape=plaintext, fontcolor=red];",
+                        quote(input)
+                    ));
+                    lines.push(format!(
+                        "    {} -> {} [style=dashed, color=red];",
+                        quote(input),
+                        quote(&node.name)
+                    ));
+                }
+            })
+        });
+
+        lines.push("}".to_string());
+        lines.join("\n") + "\n"
+    }
+}
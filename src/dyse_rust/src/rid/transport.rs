@@ -0,0 +1,232 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+extern crate hidapi;
+use hidapi::{HidApi, HidDevice};
+
+use crate::{
+    rid::serial::{baud_speed, SerialTransport, SERIAL_DEFAULT_BAUD},
+    utilities::loaders::{BuffYamlUtil, ByuParseError},
+};
+use std::{ffi::CString, io};
+use yaml_rust::Yaml;
+
+/// nodes.yaml section that picks the link to the mcu
+pub const TRANSPORT_ITEM: &str = "mcu_transport";
+
+/// How the hid threads talk to the mcu, each thread gets its own handle
+/// (see [`HidTransport::handle`]) and opens it before reading or writing.
+/// Reads don't block, they return 0 when there's nothing to read and errors
+/// mean the device is gone.
+pub trait HidTransport: Send {
+    fn open(&mut self, vid: u16, pid: u16) -> io::Result<()>;
+
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize>;
+
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize>;
+
+    /// A new (unopened) handle to the same backend
+    fn handle(&self) -> Box<dyn HidTransport>;
+}
+
+fn hid_error(e: hidapi::HidError) -> io::Error {
+    io::Error::new(io::ErrorKind::NotConnected, e.to_string())
+}
+
+/// Which of the devices matching the vid/pid to open, boards that share
+/// ids are told apart by serial number or path
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum HidSelector {
+    #[default]
+    First,
+    Serial(String),
+    Path(String),
+}
+
+/// A real device through hidapi
+pub struct HidapiTransport {
+    device: Option<HidDevice>,
+    pub selector: HidSelector,
+}
+
+impl HidapiTransport {
+    pub fn new() -> HidapiTransport {
+        HidapiTransport::with_selector(HidSelector::First)
+    }
+
+    pub fn with_selector(selector: HidSelector) -> HidapiTransport {
+        HidapiTransport {
+            device: None,
+            selector,
+        }
+    }
+
+    fn device(&self) -> io::Result<&HidDevice> {
+        self.device.as_ref().ok_or(io::Error::new(
+            io::ErrorKind::NotConnected,
+            "device isn't open",
+        ))
+    }
+}
+
+impl Default for HidapiTransport {
+    fn default() -> Self {
+        HidapiTransport::new()
+    }
+}
+
+impl HidTransport for HidapiTransport {
+    fn open(&mut self, vid: u16, pid: u16) -> io::Result<()> {
+        let hidapi = HidApi::new().map_err(hid_error)?;
+        let device = match &self.selector {
+            HidSelector::First => hidapi.open(vid, pid),
+            HidSelector::Serial(serial) => hidapi.open_serial(vid, pid, serial),
+            HidSelector::Path(path) => {
+                let path = CString::new(path.as_str())
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "bad hid path"))?;
+                hidapi.open_path(&path)
+            }
+        }
+        .map_err(hid_error)?;
+        device.set_blocking_mode(false).map_err(hid_error)?;
+        self.device = Some(device);
+        Ok(())
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.device()?.read(buffer).map_err(hid_error)
+    }
+
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.device()?.write(buffer).map_err(hid_error)
+    }
+
+    fn handle(&self) -> Box<dyn HidTransport> {
+        Box::new(HidapiTransport::with_selector(self.selector.clone()))
+    }
+}
+
+/// Which link comms uses to reach the mcu, from nodes.yaml:
+/// ```yaml
+/// mcu_transport:     # optional, hid when it's left out
+///   type: serial     # hid or serial
+///   path: /dev/ttyACM0
+///   baud: 115200     # optional
+/// ```
+/// hid links can pick a device with `serial_number` or `path` (hidraw or usb path),
+/// the usb ids come from [`HidConfig`](crate::rid::layer::HidConfig)
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransportConfig {
+    Hid { selector: HidSelector },
+    Serial { path: String, baud: u32 },
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        TransportConfig::Hid {
+            selector: HidSelector::First,
+        }
+    }
+}
+
+impl TransportConfig {
+    pub fn from_byu(byu: &BuffYamlUtil) -> Result<TransportConfig, ByuParseError> {
+        TransportConfig::parse(byu, byu.data())
+    }
+
+    /// Read [`TRANSPORT_ITEM`] from `data`
+    pub fn parse(byu: &BuffYamlUtil, data: &Yaml) -> Result<TransportConfig, ByuParseError> {
+        let section = &data[TRANSPORT_ITEM];
+        let item = |name: &str| format!("{TRANSPORT_ITEM}/{name}");
+
+        let kind = match section.is_badvalue() {
+            true => "hid".to_string(),
+            false => byu
+                .parse_str("type", section)
+                .map_err(|_| ByuParseError::string(&item("type"), &byu.yaml_path))?,
+        };
+
+        match kind.as_str() {
+            "hid" => Ok(TransportConfig::Hid {
+                selector: match (
+                    TransportConfig::parse_id(byu, "serial_number", section)?,
+                    TransportConfig::parse_id(byu, "path", section)?,
+                ) {
+                    (None, None) => HidSelector::First,
+                    (Some(serial), None) => HidSelector::Serial(serial),
+                    (None, Some(path)) => HidSelector::Path(path),
+                    (Some(_), Some(_)) => {
+                        return Err(ByuParseError::new(
+                            format!("{}: serial_number or path, not both", item("hid")),
+                            &byu.yaml_path,
+                        ))
+                    }
+                },
+            }),
+            "serial" => {
+                let path = byu
+                    .parse_str("path", section)
+                    .map_err(|_| ByuParseError::string(&item("path"), &byu.yaml_path))?;
+                let baud = match section["baud"].is_badvalue() {
+                    true => SERIAL_DEFAULT_BAUD,
+                    false => byu
+                        .parse_int("baud", section)
+                        .ok()
+                        .and_then(|baud| u32::try_from(baud).ok())
+                        .filter(|&baud| baud_speed(baud).is_some())
+                        .ok_or(ByuParseError::new(
+                            format!("{}: supported baud rate", item("baud")),
+                            &byu.yaml_path,
+                        ))?,
+                };
+
+                Ok(TransportConfig::Serial { path, baud })
+            }
+            _ => Err(ByuParseError::new(
+                format!("{}: hid or serial", item("type")),
+                &byu.yaml_path,
+            )),
+        }
+    }
+
+    /// Optional string (or number, serial numbers often look like one) of a section
+    fn parse_id(
+        byu: &BuffYamlUtil,
+        name: &str,
+        section: &Yaml,
+    ) -> Result<Option<String>, ByuParseError> {
+        match &section[name] {
+            Yaml::BadValue => Ok(None),
+            Yaml::String(id) => Ok(Some(id.clone())),
+            Yaml::Integer(id) => Ok(Some(id.to_string())),
+            _ => Err(ByuParseError::string(
+                &format!("{TRANSPORT_ITEM}/{name}"),
+                &byu.yaml_path,
+            )),
+        }
+    }
+
+    /// From the nodes.yaml of ROBOT_NAME or self.txt
+    pub fn from_env() -> Result<TransportConfig, ByuParseError> {
+        TransportConfig::from_byu(&BuffYamlUtil::default("nodes"))
+    }
+
+    pub fn transport(&self) -> Box<dyn HidTransport> {
+        match self {
+            TransportConfig::Hid { selector } => {
+                Box::new(HidapiTransport::with_selector(selector.clone()))
+            }
+            TransportConfig::Serial { path, baud } => Box::new(SerialTransport::new(path, *baud)),
+        }
+    }
+}
@@ -11,31 +11,38 @@
  *
  ********************************************************************************/
 
-extern crate hidapi;
-use hidapi::HidDevice;
-
 use crate::rid::{
     data_structures::{HidPacket, HID_PACKET_SIZE, HID_PCTS_INDEX, HID_UCTS_INDEX},
     layer::*,
+    report::seal_report,
+    transport::HidTransport,
 };
 
 use chrono::Utc;
+use log::{info, warn};
 use std::{sync::mpsc::Receiver, time::Instant};
 
 pub struct HidWriter {
     writer_rx: Receiver<HidPacket>,
-    teensy: HidDevice,
+    teensy: Option<Box<dyn HidTransport>>,
+    // connection the teensy handle was opened in
+    connection: u64,
     layer: HidLayer,
     timestamp: Instant,
+    // sequence number of the next report
+    seq: u16,
 }
 
 impl HidWriter {
+    /// The device is opened by [`HidWriter::pipeline`], so this doesn't wait for it
     pub fn new(layer: HidLayer, writer_rx: Receiver<HidPacket>) -> HidWriter {
         HidWriter {
             writer_rx: writer_rx,
-            teensy: layer.wait_for_device(),
+            teensy: None,
+            connection: 0,
             layer: layer,
             timestamp: Instant::now(),
+            seq: 0,
         }
     }
 
@@ -59,25 +66,19 @@ impl HidWriter {
         buffer
     }
 
+    /// Drop the device, the next cycle searches for it again
     pub fn reconnect(&mut self) {
-        // check reconnect after 1000 cycles
-        if !self.layer.control_flags.is_shutdown()
-            && self.timestamp.elapsed().as_millis() as f64 > self.layer.sample_time
-        {
-            if self.layer.control_flags.is_connected() {
-                println!(
-                    "[HID-Writer]: disconnecting, hasn't written for {}s",
-                    (self.timestamp.elapsed().as_millis() as f64) * 1E-3
-                );
-
-                self.layer.control_flags.disconnect();
-            }
-
-            self.teensy = self.layer.wait_for_device();
+        if self.layer.control_flags.disconnect(self.connection) {
+            warn!(
+                "disconnected, hasn't written for {}s",
+                (self.timestamp.elapsed().as_millis() as f64) * 1E-3
+            );
         }
+
+        self.teensy = None;
     }
 
-    /// Write the bytes from the buffer to the teensy.
+    /// Stamp and seal the report, then write it to the teensy.
     /// Reconnect if the write fails.
     /// # Usage
     /// ```
@@ -98,17 +99,27 @@ impl HidWriter {
             .enumerate()
             .for_each(|(i, &b)| buffer[HID_UCTS_INDEX + i] = b);
 
-        match self.teensy.write(buffer) {
-            Ok(value) => {
-                if value == HID_PACKET_SIZE {
-                    self.timestamp = Instant::now();
-                    self.layer.pc_stats.update_tx(1.0);
-                }
+        seal_report(buffer, self.seq);
+
+        let written = match &mut self.teensy {
+            Some(teensy) => teensy.write(buffer),
+            None => return,
+        };
+
+        match written {
+            Ok(HID_PACKET_SIZE) => {
+                self.timestamp = Instant::now();
+                self.seq = self.seq.wrapping_add(1);
+                self.layer.pc_stats.update_tx(1.0);
             }
-            _ => {
-                self.layer.control_flags.initialize(false);
-                self.reconnect();
+            Ok(_) => {
+                if self.timestamp.elapsed().as_millis() as f64
+                    > self.layer.config.no_comms_timeout_ms
+                {
+                    self.reconnect();
+                }
             }
+            Err(_) => self.reconnect(),
         }
     }
 
@@ -118,17 +129,28 @@ impl HidWriter {
     /// # Example
     /// See [`HidLayer::pipeline()`] source
     pub fn pipeline(&mut self) {
-        println!("[HID-writer]: Live");
+        info!("Live");
 
         while !self.layer.control_flags.is_shutdown() {
             let t = Instant::now();
 
-            let mut buffer = self
-                .writer_rx
-                .try_recv()
-                .unwrap_or(self.silent_channel_default());
+            match self.teensy.is_some() {
+                true => {
+                    let mut buffer = self
+                        .writer_rx
+                        .try_recv()
+                        .unwrap_or(self.silent_channel_default());
 
-            self.write(&mut buffer);
+                    self.write(&mut buffer);
+                }
+                false => {
+                    if let Some((teensy, connection)) = self.layer.search() {
+                        self.teensy = Some(teensy);
+                        self.connection = connection;
+                    }
+                    self.timestamp = Instant::now();
+                }
+            }
 
             self.layer.delay(t);
             // if self.layer.delay(t) > 1000.0 {
@@ -139,6 +161,6 @@ impl HidWriter {
         let mut buffer = [13; HID_PACKET_SIZE];
         self.write(&mut buffer);
 
-        println!("[HID-writer]: Shutdown");
+        info!("Shutdown");
     }
 }
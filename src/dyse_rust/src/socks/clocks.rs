@@ -0,0 +1,96 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use std::{
+    env,
+    sync::{Arc, RwLock},
+    time::Instant,
+};
+
+/// System topic that sim clocks follow
+pub const CLOCK_TOPIC: &str = "/clock";
+
+/// Set this (to anything) to make new socks run on simulated time
+pub const SIM_TIME_ENV: &str = "USE_SIM_TIME";
+
+/// Source of time for socks and tasks.
+///
+/// A real clock counts micros from when it was created, a sim clock
+/// only moves when it is advanced (by hand or from the [`CLOCK_TOPIC`]).
+/// Clones share the same time, so one sim clock can drive a whole graph.
+#[derive(Clone, Debug)]
+pub struct Clock {
+    origin: Instant,
+    sim: Option<Arc<RwLock<u64>>>,
+}
+
+impl Clock {
+    pub fn real() -> Clock {
+        Clock {
+            origin: Instant::now(),
+            sim: None,
+        }
+    }
+
+    pub fn sim() -> Clock {
+        Clock {
+            origin: Instant::now(),
+            sim: Some(Arc::new(RwLock::new(0))),
+        }
+    }
+
+    /// Sim clock if [`SIM_TIME_ENV`] is set, real otherwise
+    pub fn from_env() -> Clock {
+        match env::var(SIM_TIME_ENV) {
+            Ok(_) => Clock::sim(),
+            _ => Clock::real(),
+        }
+    }
+
+    pub fn is_sim(&self) -> bool {
+        self.sim.is_some()
+    }
+
+    /// Current time in micros
+    pub fn micros(&self) -> u64 {
+        match &self.sim {
+            Some(t) => *t.read().unwrap(),
+            None => self.origin.elapsed().as_micros() as u64,
+        }
+    }
+
+    /// Current time in seconds
+    pub fn secs(&self) -> f64 {
+        self.micros() as f64 * 1E-6
+    }
+
+    /// Micros since `micros` (a previous reading of this clock)
+    pub fn elapsed(&self, micros: u64) -> u64 {
+        self.micros().saturating_sub(micros)
+    }
+
+    /// Move a sim clock forward, does nothing to a real clock
+    pub fn advance(&self, micros: u64) {
+        if let Some(t) = &self.sim {
+            *t.write().unwrap() += micros;
+        }
+    }
+
+    /// Jump a sim clock to `micros`, sim time never goes backwards
+    pub fn set(&self, micros: u64) {
+        if let Some(t) = &self.sim {
+            let mut t = t.write().unwrap();
+            *t = (*t).max(micros);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::socks::message::MessageStamp;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt;
+
+/// Something went wrong encoding or decoding a payload
+#[derive(Clone, Debug, PartialEq)]
+pub struct CodecError {
+    pub codec: CodecId,
+    pub reason: String,
+}
+
+impl CodecError {
+    pub fn new(codec: CodecId, reason: impl fmt::Display) -> CodecError {
+        CodecError {
+            codec,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} codec: {}", self.codec, self.reason)
+    }
+}
+
+/// A wire format for payloads
+pub trait Codec {
+    const ID: CodecId;
+
+    fn encode<T: Serialize>(data: &T) -> Result<Vec<u8>, CodecError>;
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CodecError>;
+}
+
+/// Compact and fast, only rust can read it (the default)
+pub struct Bincode;
+/// Readable by anything, biggest on the wire
+pub struct Json;
+/// Compact and readable by most languages, structs are sent as maps
+pub struct MessagePack;
+
+impl Codec for Bincode {
+    const ID: CodecId = CodecId::Bincode;
+
+    fn encode<T: Serialize>(data: &T) -> Result<Vec<u8>, CodecError> {
+        bincode::serialize(data).map_err(|e| CodecError::new(Self::ID, e))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CodecError> {
+        bincode::deserialize(bytes).map_err(|e| CodecError::new(Self::ID, e))
+    }
+}
+
+impl Codec for Json {
+    const ID: CodecId = CodecId::Json;
+
+    fn encode<T: Serialize>(data: &T) -> Result<Vec<u8>, CodecError> {
+        serde_json::to_vec(data).map_err(|e| CodecError::new(Self::ID, e))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CodecError> {
+        serde_json::from_slice(bytes).map_err(|e| CodecError::new(Self::ID, e))
+    }
+}
+
+impl Codec for MessagePack {
+    const ID: CodecId = CodecId::MessagePack;
+
+    fn encode<T: Serialize>(data: &T) -> Result<Vec<u8>, CodecError> {
+        rmp_serde::to_vec_named(data).map_err(|e| CodecError::new(Self::ID, e))
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CodecError> {
+        rmp_serde::from_slice(bytes).map_err(|e| CodecError::new(Self::ID, e))
+    }
+}
+
+/// Which [`Codec`] a payload uses, sent in every sock header
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CodecId {
+    #[default]
+    Bincode,
+    Json,
+    MessagePack,
+}
+
+impl CodecId {
+    pub fn byte(&self) -> u8 {
+        match self {
+            CodecId::Bincode => 0,
+            CodecId::Json => 1,
+            CodecId::MessagePack => 2,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Option<CodecId> {
+        match byte {
+            0 => Some(CodecId::Bincode),
+            1 => Some(CodecId::Json),
+            2 => Some(CodecId::MessagePack),
+            _ => None,
+        }
+    }
+
+    /// Parse a codec name from yaml or the command line
+    pub fn from_name(name: &str) -> Option<CodecId> {
+        match name.to_ascii_lowercase().as_str() {
+            "bincode" => Some(CodecId::Bincode),
+            "json" => Some(CodecId::Json),
+            "msgpack" | "messagepack" => Some(CodecId::MessagePack),
+            _ => None,
+        }
+    }
+
+    /// True for codecs that can be read without knowing the type
+    pub fn is_self_describing(&self) -> bool {
+        *self != CodecId::Bincode
+    }
+
+    pub fn encode<T: Serialize>(&self, data: &T) -> Result<Vec<u8>, CodecError> {
+        match self {
+            CodecId::Bincode => Bincode::encode(data),
+            CodecId::Json => Json::encode(data),
+            CodecId::MessagePack => MessagePack::encode(data),
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        match self {
+            CodecId::Bincode => Bincode::decode(bytes),
+            CodecId::Json => Json::decode(bytes),
+            CodecId::MessagePack => MessagePack::decode(bytes),
+        }
+    }
+
+    /// Codec of task input `i`, inputs without a stamp are bincode
+    pub fn of_input(stamps: &[MessageStamp], i: usize) -> CodecId {
+        stamps.get(i).map_or(CodecId::Bincode, |stamp| stamp.codec)
+    }
+}
+
+impl fmt::Display for CodecId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodecId::Bincode => write!(f, "bincode"),
+            CodecId::Json => write!(f, "json"),
+            CodecId::MessagePack => write!(f, "msgpack"),
+        }
+    }
+}
@@ -0,0 +1,249 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::{
+    msgs::{diagnostics::*, standard::Header},
+    socks::{message::UDP_PACKET_SIZE, socks::Sock},
+};
+use log::warn;
+use std::collections::BTreeMap;
+
+/// Nodes send their statuses here, the aggregator publishes its summary on
+/// [`DIAGNOSTICS_AGG_TOPIC`]
+pub const DIAGNOSTICS_TOPIC: &str = "diagnostics";
+pub const DIAGNOSTICS_AGG_TOPIC: &str = "diagnostics_agg";
+/// Statuses are resent at least this often (micros) so the aggregator knows the node is alive
+pub const DIAGNOSTICS_PERIOD_US: i64 = 1_000_000;
+/// Statuses that haven't been heard from in this long (micros) are stale
+pub const DIAGNOSTICS_STALE_US: i64 = 5_000_000;
+
+/// A node's statuses, they get sent when one changes level or
+/// [`DIAGNOSTICS_PERIOD_US`] passes
+#[derive(Default)]
+pub struct Diagnostics {
+    pub statuses: Vec<DiagnosticStatus>,
+    pub seq: u32,
+    sent: Option<i64>,
+    changed: bool,
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics::default()
+    }
+
+    /// Replace the status with the same name
+    pub fn update(&mut self, status: DiagnosticStatus) {
+        match self.statuses.iter_mut().find(|s| s.name == status.name) {
+            Some(old) => {
+                self.changed |= old.level != status.level;
+                *old = status;
+            }
+            None => {
+                self.changed = true;
+                self.statuses.push(status);
+            }
+        }
+    }
+
+    pub fn status(&self, name: &str) -> Option<&DiagnosticStatus> {
+        self.statuses.iter().find(|status| status.name == name)
+    }
+
+    /// The statuses to send at `now`, None if nothing needs sending
+    pub fn due(&mut self, frame_id: &str, now: i64) -> Option<DiagnosticArray> {
+        let period = self
+            .sent
+            .is_none_or(|sent| now - sent >= DIAGNOSTICS_PERIOD_US);
+
+        match !self.statuses.is_empty() && (self.changed || period) {
+            true => {
+                self.sent = Some(now);
+                self.changed = false;
+                self.seq += 1;
+                Some(DiagnosticArray {
+                    header: Header::new(frame_id, self.seq, now),
+                    status: self.statuses.clone(),
+                })
+            }
+            false => None,
+        }
+    }
+}
+
+/// Collects every node's statuses and groups them by name, `robot_fw/hid`
+/// and `robot_fw/signal` both go under a `robot_fw` group that is as bad
+/// as the worst of them
+pub struct Aggregator {
+    pub stale_us: i64,
+    pub seq: u32,
+    published: Option<i64>,
+    /// latest status by name, with when it was heard
+    pub entries: BTreeMap<String, (DiagnosticStatus, i64)>,
+}
+
+impl Aggregator {
+    pub fn new(stale_us: i64) -> Aggregator {
+        Aggregator {
+            stale_us,
+            seq: 0,
+            published: None,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Remember statuses heard at `now` (our time, node clocks can't be trusted to agree)
+    pub fn hear(&mut self, array: DiagnosticArray, now: i64) {
+        array.status.into_iter().for_each(|status| {
+            self.entries.insert(status.name.clone(), (status, now));
+        });
+    }
+
+    /// Every status heard, the ones that went quiet are marked stale
+    pub fn leaves(&self, now: i64) -> Vec<DiagnosticStatus> {
+        self.entries
+            .values()
+            .map(|(status, heard)| match now - heard > self.stale_us {
+                true => DiagnosticStatus {
+                    level: DiagnosticLevel::Stale,
+                    ..status.clone()
+                }
+                .with(
+                    "last heard (s)",
+                    format!("{:.1}", (now - heard) as f64 * 1E-6),
+                ),
+                false => status.clone(),
+            })
+            .collect()
+    }
+
+    /// Leaves and the groups above them sorted by name (groups come
+    /// before their children)
+    pub fn statuses(&self, now: i64) -> Vec<DiagnosticStatus> {
+        let leaves = self.leaves(now);
+        let mut groups: BTreeMap<String, Vec<&DiagnosticStatus>> = BTreeMap::new();
+
+        leaves.iter().for_each(|leaf| {
+            let parts: Vec<&str> = leaf.name.split('/').collect();
+            (1..parts.len()).for_each(|depth| {
+                groups
+                    .entry(parts[..depth].join("/"))
+                    .or_default()
+                    .push(leaf);
+            });
+        });
+
+        let mut statuses: Vec<DiagnosticStatus> = groups
+            .into_iter()
+            .filter(|(name, _)| !self.entries.contains_key(name))
+            .map(|(name, children)| Aggregator::group(&name, &children))
+            .collect();
+        statuses.extend(leaves.iter().cloned());
+        statuses.sort_by(|a, b| a.name.cmp(&b.name));
+        statuses
+    }
+
+    /// A group is as bad as its worst child, the message says which one
+    fn group(name: &str, children: &[&DiagnosticStatus]) -> DiagnosticStatus {
+        let worst = children.iter().max_by_key(|child| child.level).unwrap();
+        let count = |level: DiagnosticLevel| children.iter().filter(|c| c.level == level).count();
+
+        let message = match worst.level {
+            DiagnosticLevel::Ok => "all ok".to_string(),
+            _ => format!(
+                "{}: {}",
+                worst.name.trim_start_matches(&format!("{name}/")),
+                worst.message
+            ),
+        };
+
+        DiagnosticStatus::new(name, worst.level, &message)
+            .with("ok", count(DiagnosticLevel::Ok))
+            .with("warn", count(DiagnosticLevel::Warn))
+            .with("error", count(DiagnosticLevel::Error))
+            .with("stale", count(DiagnosticLevel::Stale))
+    }
+
+    /// The worst of everything
+    pub fn level(&self, now: i64) -> DiagnosticLevel {
+        self.leaves(now)
+            .iter()
+            .map(|status| status.level)
+            .max()
+            .unwrap_or(DiagnosticLevel::Ok)
+    }
+
+    pub fn summary(&mut self, now: i64) -> DiagnosticArray {
+        self.seq += 1;
+        DiagnosticArray {
+            header: Header::new(DIAGNOSTICS_AGG_TOPIC, self.seq, now),
+            status: self.statuses(now),
+        }
+    }
+
+    /// Hear what `sock` (a sinc on [`DIAGNOSTICS_TOPIC`]) received and publish
+    /// the summary on the sock's name when it's due, the summary is returned
+    pub fn spin_once(&mut self, sock: &mut Sock) -> Option<DiagnosticArray> {
+        let mut buffer = [0u8; UDP_PACKET_SIZE];
+        if let Some(i) = sock.try_rx(&mut buffer) {
+            match sock.decode_message::<DiagnosticArray>(i) {
+                Ok(array) => self.hear(array, sock.now()),
+                Err(e) => warn!("[{}]: bad diagnostics {e}", sock.name),
+            }
+        }
+
+        let now = sock.now();
+        match self
+            .published
+            .is_none_or(|published| now - published >= DIAGNOSTICS_PERIOD_US)
+        {
+            true => {
+                self.published = Some(now);
+                let summary = self.summary(now);
+                sock.tx_payload(summary.clone());
+                Some(summary)
+            }
+            false => None,
+        }
+    }
+}
+
+/// Print statuses as a tree, children are indented under their group
+pub fn diagnostics_tree(statuses: &[DiagnosticStatus]) -> String {
+    statuses
+        .iter()
+        .map(|status| {
+            let depth = status.name.matches('/').count();
+            let short = status.name.rsplit('/').next().unwrap_or(&status.name);
+            let values = match status.values.is_empty() {
+                true => String::new(),
+                false => format!(
+                    " ({})",
+                    status
+                        .values
+                        .iter()
+                        .map(|kv| format!("{}: {}", kv.key, kv.value))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ),
+            };
+            format!(
+                "{}[{}] {short}: {}{values}",
+                "  ".repeat(depth),
+                status.level,
+                status.message
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
@@ -11,12 +11,12 @@
  *
  ********************************************************************************/
 
-use dyse_rust::socks::sockapi;
+use dyse_rust::socks::{logging, names::strip_remaps, sockapi};
 use std::env;
 
 fn main() {
-    let mut args: Vec<String> = env::args().collect();
-    args.remove(0);
+    logging::init_from_env();
+    let args = strip_remaps(env::args().skip(1).collect());
 
-    sockapi::echo::<f64>("echo", args.iter().map(|s| s as &str).collect());
+    sockapi::inspect(args.iter().map(|s| s as &str).collect());
 }
@@ -0,0 +1,22 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use dyse_rust::socks::{logging, names::strip_remaps, sockapi};
+use std::env;
+
+fn main() {
+    logging::init_from_env();
+    let args = strip_remaps(env::args().skip(1).collect());
+
+    sockapi::logger(args.iter().map(|s| s as &str).collect());
+}
@@ -0,0 +1,280 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::msgs::{logging::*, standard::Header};
+use chrono::Utc;
+use crossbeam_channel::{bounded, Receiver, Sender};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock, RwLock,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Log records from every node are published here, see [`crate::socks::sockapi::logger`]
+pub const LOG_TOPIC: &str = "rosout";
+/// Filter used by [`init_from_env`], i.e. `DYSE_LOG=warn,dyse_rust::rid=debug`
+pub const LOG_ENV: &str = "DYSE_LOG";
+pub const LOG_DEFAULT: &str = "info";
+/// Records waiting for a sock to publish them, the oldest are dropped when it's full
+pub const LOG_QUEUE_SIZE: usize = 1024;
+
+/// Levels by module, a target uses the longest module that it's in
+/// (`dyse_rust::rid` covers `dyse_rust::rid::layer`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogFilter {
+    pub default: LevelFilter,
+    pub modules: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    pub fn new(default: LevelFilter) -> LogFilter {
+        LogFilter {
+            default,
+            modules: vec![],
+        }
+    }
+
+    /// Parse an env_logger style filter, `warn,dyse_rust::rid=debug,dyse_rust::socks=off`
+    pub fn parse(spec: &str) -> Option<LogFilter> {
+        let mut filter = LogFilter::new(LevelFilter::Info);
+
+        for entry in spec.split(',').map(|e| e.trim()).filter(|e| !e.is_empty()) {
+            match entry.split_once('=') {
+                Some((module, level)) => filter
+                    .modules
+                    .push((module.trim().to_string(), level.trim().parse().ok()?)),
+                None => match entry.parse() {
+                    Ok(level) => filter.default = level,
+                    // a bare module name turns everything in it on
+                    Err(_) => filter.modules.push((entry.to_string(), LevelFilter::Trace)),
+                },
+            }
+        }
+
+        Some(filter)
+    }
+
+    pub fn module(mut self, module: &str, level: LevelFilter) -> LogFilter {
+        self.modules.push((module.to_string(), level));
+        self
+    }
+
+    pub fn level(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(module, _)| target == module || target.starts_with(&format!("{module}::")))
+            .max_by_key(|(module, _)| module.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    pub fn enabled(&self, target: &str, level: Level) -> bool {
+        level <= self.level(target)
+    }
+
+    /// The most verbose level anything is allowed
+    pub fn max(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .chain([self.default])
+            .max()
+            .unwrap_or(self.default)
+    }
+}
+
+/// The [`Log`] behind the `log` macros, records are printed and (once a sock
+/// calls [`crate::socks::socks::Sock::forward_logs`]) queued to be published
+pub struct SockLogger {
+    filter: RwLock<LogFilter>,
+    forwarding: AtomicBool,
+    stderr: AtomicBool,
+    tx: Sender<LogRecord>,
+    rx: Receiver<LogRecord>,
+}
+
+static LOGGER: OnceLock<SockLogger> = OnceLock::new();
+
+impl SockLogger {
+    pub fn filter(&self) -> LogFilter {
+        self.filter.read().unwrap().clone()
+    }
+
+    pub fn set_filter(&self, filter: LogFilter) {
+        log::set_max_level(filter.max());
+        *self.filter.write().unwrap() = filter;
+    }
+
+    /// Queue records for socks to publish (otherwise they're only printed)
+    pub fn forward(&self, forwarding: bool) {
+        self.forwarding.store(forwarding, Ordering::Relaxed);
+    }
+
+    /// Print to stderr instead of stdout, for tools whose stdout is their output
+    pub fn print_to_stderr(&self, stderr: bool) {
+        self.stderr.store(stderr, Ordering::Relaxed);
+    }
+
+    /// Everything queued so far
+    pub fn take(&self) -> Vec<LogRecord> {
+        self.rx.try_iter().collect()
+    }
+
+    pub fn record(record: &Record) -> LogRecord {
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |t| t.as_micros() as i64);
+
+        LogRecord {
+            header: Header::new("", 0, stamp),
+            level: record.level().into(),
+            node: String::new(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+            file: record.file().unwrap_or_default().to_string(),
+            line: record.line().unwrap_or(0),
+        }
+    }
+}
+
+impl Log for SockLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter
+            .read()
+            .unwrap()
+            .enabled(metadata.target(), metadata.level())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = format!(
+            "[{}][{}]: {}",
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        match self.stderr.load(Ordering::Relaxed) {
+            true => eprintln!("{line}"),
+            false => println!("{line}"),
+        }
+
+        if self.forwarding.load(Ordering::Relaxed) {
+            let record = SockLogger::record(record);
+            if self.tx.is_full() {
+                let _ = self.rx.try_recv();
+            }
+            let _ = self.tx.try_send(record);
+        }
+    }
+
+    fn flush(&self) {
+        let _ = io::stdout().flush();
+        let _ = io::stderr().flush();
+    }
+}
+
+/// Install the logger, later calls only change the filter
+pub fn init(filter: LogFilter) -> &'static SockLogger {
+    let mut installed = false;
+    let logger = LOGGER.get_or_init(|| {
+        let (tx, rx) = bounded(LOG_QUEUE_SIZE);
+        installed = true;
+        SockLogger {
+            filter: RwLock::new(filter.clone()),
+            forwarding: AtomicBool::new(false),
+            stderr: AtomicBool::new(false),
+            tx,
+            rx,
+        }
+    });
+
+    if installed && log::set_logger(logger).is_err() {
+        println!("[Logging]: another logger is installed, records won't be published");
+    }
+    logger.set_filter(filter);
+    logger
+}
+
+/// [`init`] with the filter in [`LOG_ENV`] (or [`LOG_DEFAULT`])
+pub fn init_from_env() -> &'static SockLogger {
+    let spec = env::var(LOG_ENV).unwrap_or(LOG_DEFAULT.to_string());
+    match LogFilter::parse(&spec) {
+        Some(filter) => init(filter),
+        None => {
+            let logger = init(LogFilter::parse(LOG_DEFAULT).unwrap());
+            log::warn!("bad {LOG_ENV} filter {spec:?}, using {LOG_DEFAULT}");
+            logger
+        }
+    }
+}
+
+/// The installed logger, if [`init`] was called
+pub fn logger() -> Option<&'static SockLogger> {
+    LOGGER.get()
+}
+
+/// Appends records to a file, one per line
+pub struct LogFile {
+    pub path: String,
+    pub level: LogLevel,
+    pub written: u64,
+    file: fs::File,
+}
+
+impl LogFile {
+    /// `$PROJECT_ROOT/data/logs/rosout_<date>.log`
+    pub fn default_path() -> String {
+        let project_root = env::var("PROJECT_ROOT").unwrap_or(".".to_string());
+        format!(
+            "{project_root}/data/logs/rosout_{}.log",
+            Utc::now().format("%Y%m%d_%H%M%S")
+        )
+    }
+
+    /// Open (or create) `path`, records less severe than `level` are skipped
+    pub fn open(path: &str, level: LogLevel) -> io::Result<LogFile> {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        Ok(LogFile {
+            path: path.to_string(),
+            level,
+            written: 0,
+            file: fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?,
+        })
+    }
+
+    /// Write `record` if it's severe enough, returns whether it was written
+    pub fn write(&mut self, record: &LogRecord) -> io::Result<bool> {
+        match record.level <= self.level {
+            true => {
+                writeln!(self.file, "{record}")?;
+                self.written += 1;
+                Ok(true)
+            }
+            false => Ok(false),
+        }
+    }
+}
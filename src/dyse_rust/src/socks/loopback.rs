@@ -0,0 +1,210 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::socks::{
+    clocks::Clock,
+    message::{UdpPacket, UDP_PACKET_SIZE},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    collections::VecDeque,
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
+};
+
+/// Faults the loopback network applies to every delivered packet,
+/// probabilities are 0.0 to 1.0 and times are clock micros.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoopbackConfig {
+    pub loss: f64,
+    pub duplication: f64,
+    pub reorder: f64,
+    pub latency: u64,
+    pub jitter: u64,
+    pub seed: u64,
+}
+
+impl LoopbackConfig {
+    /// A perfect network
+    pub fn ideal() -> LoopbackConfig {
+        LoopbackConfig {
+            loss: 0.0,
+            duplication: 0.0,
+            reorder: 0.0,
+            latency: 0,
+            jitter: 0,
+            seed: 0,
+        }
+    }
+}
+
+/// Packets waiting for a port, with the clock micros they can be
+/// read at and the port that sent them
+type PortQueue = VecDeque<(u64, usize, UdpPacket)>;
+
+struct LoopbackState {
+    config: LoopbackConfig,
+    rng: StdRng,
+    ports: Vec<PortQueue>,
+    dropped: usize,
+}
+
+/// In-process stand in for the multicast group.
+///
+/// Every packet sent by a port is delivered to every port (including the
+/// sender, like multicast loop). Clones share the same network so any
+/// number of socks in one process can talk without a real interface.
+#[derive(Clone)]
+pub struct LoopbackNetwork {
+    clock: Clock,
+    state: Arc<Mutex<LoopbackState>>,
+}
+
+impl LoopbackNetwork {
+    pub fn new(clock: Clock, config: LoopbackConfig) -> LoopbackNetwork {
+        LoopbackNetwork {
+            clock,
+            state: Arc::new(Mutex::new(LoopbackState {
+                rng: StdRng::seed_from_u64(config.seed),
+                config,
+                ports: vec![],
+                dropped: 0,
+            })),
+        }
+    }
+
+    /// A perfect network on a real clock
+    pub fn ideal() -> LoopbackNetwork {
+        LoopbackNetwork::new(Clock::real(), LoopbackConfig::ideal())
+    }
+
+    pub fn clock(&self) -> Clock {
+        self.clock.clone()
+    }
+
+    /// Change the faults applied to packets sent from now on
+    pub fn configure(&self, config: LoopbackConfig) {
+        let mut state = self.state.lock().unwrap();
+        state.rng = StdRng::seed_from_u64(config.seed);
+        state.config = config;
+    }
+
+    /// Number of packets lost so far
+    pub fn dropped(&self) -> usize {
+        self.state.lock().unwrap().dropped
+    }
+
+    /// Attach a new port to the network
+    pub fn port(&self) -> LoopbackPort {
+        let mut state = self.state.lock().unwrap();
+        state.ports.push(VecDeque::new());
+
+        LoopbackPort {
+            id: state.ports.len() - 1,
+            network: self.clone(),
+        }
+    }
+
+    fn send(&self, sender: usize, packet: &UdpPacket) {
+        let now = self.clock.micros();
+        let mut state = self.state.lock().unwrap();
+        let config = state.config.clone();
+
+        (0..state.ports.len()).for_each(|i| {
+            if state.rng.gen_bool(config.loss) {
+                state.dropped += 1;
+                return;
+            }
+
+            let copies = match state.rng.gen_bool(config.duplication) {
+                true => 2,
+                false => 1,
+            };
+
+            (0..copies).for_each(|_| {
+                let jitter = match config.jitter {
+                    0 => 0,
+                    _ => state.rng.gen_range(0..=config.jitter),
+                };
+                let reorder = state.rng.gen_bool(config.reorder);
+                let port = &mut state.ports[i];
+                let item = (now + config.latency + jitter, sender, *packet);
+
+                match reorder && !port.is_empty() {
+                    true => port.insert(port.len() - 1, item),
+                    false => port.push_back(item),
+                }
+            });
+        });
+    }
+
+    fn recv(&self, id: usize, peek: bool) -> Option<(usize, UdpPacket)> {
+        let now = self.clock.micros();
+        let mut state = self.state.lock().unwrap();
+        let port = &mut state.ports[id];
+
+        match port.iter().position(|&(ready, _, _)| ready <= now) {
+            Some(i) => match peek {
+                true => Some((port[i].1, port[i].2)),
+                false => port.remove(i).map(|(_, sender, packet)| (sender, packet)),
+            },
+            None => None,
+        }
+    }
+}
+
+/// One sock's connection to a [`LoopbackNetwork`]
+#[derive(Clone)]
+pub struct LoopbackPort {
+    pub id: usize,
+    network: LoopbackNetwork,
+}
+
+impl LoopbackPort {
+    /// Fake address for a port, ports are told apart by the port number
+    pub fn addr(id: usize) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), id as u16)
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        LoopbackPort::addr(self.id)
+    }
+
+    pub fn send_to(&self, buffer: &[u8]) -> io::Result<usize> {
+        let mut packet = [0u8; UDP_PACKET_SIZE];
+        let n = buffer.len().min(UDP_PACKET_SIZE);
+        packet[0..n].copy_from_slice(&buffer[0..n]);
+        self.network.send(self.id, &packet);
+        Ok(n)
+    }
+
+    fn read(&self, buffer: &mut [u8], peek: bool) -> io::Result<(usize, SocketAddr)> {
+        match self.network.recv(self.id, peek) {
+            Some((sender, packet)) => {
+                let n = buffer.len().min(UDP_PACKET_SIZE);
+                buffer[0..n].copy_from_slice(&packet[0..n]);
+                Ok((n, LoopbackPort::addr(sender)))
+            }
+            None => Err(io::Error::from(io::ErrorKind::WouldBlock)),
+        }
+    }
+
+    pub fn recv_from(&self, buffer: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.read(buffer, false)
+    }
+
+    pub fn peek_from(&self, buffer: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        self.read(buffer, true)
+    }
+}
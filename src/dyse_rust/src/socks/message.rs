@@ -11,7 +11,8 @@
  *
  ********************************************************************************/
 
-use std::time::Instant;
+use crate::socks::codec::CodecId;
+use chrono::Utc;
 
 pub const UDP_PACKET_SIZE: usize = 1024;
 pub const SOCK_HEADER_LEN: usize = 64;
@@ -22,6 +23,48 @@ pub const MAX_FRAGMENT_SIZE: usize = UDP_PACKET_SIZE - PAYLOAD_IDX;
 pub type UdpPacket = [u8; UDP_PACKET_SIZE];
 pub type UdpPayload = Vec<u8>;
 
+/// Wall clock time in microseconds since the UNIX epoch,
+/// this is what gets written into message headers.
+pub fn stamp_micros() -> i64 {
+    Utc::now().timestamp_micros()
+}
+
+/// When a message was produced (by the source) and when
+/// it was fully collected (by the receiver), both are
+/// microseconds since the UNIX epoch. Also carries the
+/// codec the payload was encoded with.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MessageStamp {
+    pub source: i64,
+    pub received: i64,
+    pub codec: CodecId,
+}
+
+impl MessageStamp {
+    pub fn new(source: i64, received: i64) -> MessageStamp {
+        MessageStamp {
+            source,
+            received,
+            codec: CodecId::Bincode,
+        }
+    }
+
+    /// Seconds between the source producing and the receiver collecting
+    pub fn latency(&self) -> f64 {
+        1E-6 * (self.received - self.source) as f64
+    }
+
+    /// Source time in seconds since the UNIX epoch
+    pub fn source_secs(&self) -> f64 {
+        1E-6 * self.source as f64
+    }
+
+    /// Receive time in seconds since the UNIX epoch
+    pub fn received_secs(&self) -> f64 {
+        1E-6 * self.received as f64
+    }
+}
+
 pub fn get8_bytes(idx: usize, buffer: &[u8]) -> [u8; 8] {
     [
         buffer[idx],
@@ -112,7 +155,9 @@ impl MessageFragment {
 #[derive(Clone)]
 pub struct Message {
     pub fragments: Vec<MessageFragment>,
-    pub timestamp: Instant,
+    /// clock micros of the last completed collect
+    pub timestamp: u64,
+    pub stamp: MessageStamp,
     pub micros_rate: u64,
     pub ntx: i64,
 }
@@ -121,7 +166,8 @@ impl Message {
     pub fn new() -> Message {
         Message {
             fragments: vec![],
-            timestamp: Instant::now(),
+            timestamp: 0,
+            stamp: MessageStamp::default(),
             micros_rate: u64::MAX,
             ntx: 0,
         }
@@ -145,7 +191,8 @@ impl Message {
 
         Message {
             fragments: fragments,
-            timestamp: Instant::now(),
+            timestamp: 0,
+            stamp: MessageStamp::default(),
             micros_rate: u64::MAX,
             ntx: 0,
         }
@@ -164,12 +211,18 @@ impl Message {
         };
     }
 
-    pub fn is_available(&self) -> bool {
-        (self.micros_rate / 2) as u128 > self.timestamp.elapsed().as_micros()
-            && self.micros_rate != u64::MAX
+    /// True if the message is fresher than half its rate at clock time `now`
+    pub fn is_available(&self, now: u64) -> bool {
+        (self.micros_rate / 2) > now.saturating_sub(self.timestamp) && self.micros_rate != u64::MAX
     }
 
-    pub fn collect(&mut self, ntx: i64, micros: u64, fragment: MessageFragment) -> bool {
+    pub fn collect(
+        &mut self,
+        ntx: i64,
+        micros: u64,
+        stamp: i64,
+        fragment: MessageFragment,
+    ) -> bool {
         if self.fragments.len() == 0 || ntx > self.ntx {
             self.init_fragments(fragment.total_fragments);
         }
@@ -182,7 +235,7 @@ impl Message {
         match (0..self.fragments.len()).find(|&i| self.fragments[i].offset != i) {
             Some(_) => false,
             None => {
-                self.timestamp = Instant::now();
+                self.stamp = MessageStamp::new(stamp, stamp_micros());
                 true
             }
         }
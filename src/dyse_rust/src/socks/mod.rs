@@ -1,6 +1,17 @@
 pub mod sock_tests;
 
+pub mod clocks;
+pub mod codec;
+pub mod diagnostics;
+pub mod logging;
+pub mod loopback;
 pub mod message;
+pub mod names;
+pub mod params;
+pub mod schema;
 pub mod sockapi;
 pub mod socks;
 pub mod task;
+pub mod throttle;
+pub mod time_sync;
+pub mod topics;
@@ -0,0 +1,186 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::{
+    socks::{clocks::CLOCK_TOPIC, params::*, time_sync::*, topics::TOPIC_ANNOUNCE_NAME},
+    utilities::loaders::{BuffYamlUtil, ByuParseError},
+};
+use std::{env, fmt};
+use yaml_rust::yaml::Yaml;
+
+/// Set this to put every sock of a process in a namespace
+pub const NAMESPACE_ENV: &str = "DYSE_NAMESPACE";
+/// Command line argument that sets the namespace (`__ns:=/robot1`)
+pub const NAMESPACE_ARG: &str = "__ns";
+/// Separates the two sides of a remap argument (`imu:=/robot1/imu`)
+pub const REMAP_SEPARATOR: &str = ":=";
+
+/// Names the socks protocol itself uses, these are never namespaced or remapped
+pub const SYSTEM_NAMES: [&str; 9] = [
+    "shutdown",
+    "identify",
+    CLOCK_TOPIC,
+    CLOCK_REQUEST_NAME,
+    CLOCK_REPLY_NAME,
+    TOPIC_ANNOUNCE_NAME,
+    PARAM_REQUEST_NAME,
+    PARAM_REPLY_NAME,
+    PARAM_EVENT_NAME,
+];
+
+/// Clean up a path: no empty segments and no leading or trailing '/'
+pub fn normalize_name(name: &str) -> String {
+    name.split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+/// Join two names, `join_name("sin", "ctrl")` is "sin/ctrl"
+pub fn join_name(base: &str, name: &str) -> String {
+    normalize_name(&format!("{base}/{name}"))
+}
+
+/// The arguments that aren't remaps, [`NameResolver::from_env`] reads those
+pub fn strip_remaps(args: Vec<String>) -> Vec<String> {
+    args.into_iter()
+        .filter(|arg| !arg.contains(REMAP_SEPARATOR))
+        .collect()
+}
+
+/// Turns the names used in code into the names used on the network.
+///
+/// - `/robot1/imu` is absolute and used as is
+/// - `imu` is relative and goes in the namespace (`/robot1/imu`)
+/// - `~imu` is private and goes under the node (`/robot1/<node>/imu`)
+///
+/// Remap rules are matched against the resolved name. The global namespace
+/// is empty and names are sent without the leading '/', so un-namespaced
+/// names look the same on the wire as they always have.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NameResolver {
+    pub namespace: String,
+    pub remaps: Vec<(String, String)>,
+}
+
+impl NameResolver {
+    pub fn new(namespace: &str) -> NameResolver {
+        NameResolver {
+            namespace: normalize_name(namespace),
+            remaps: vec![],
+        }
+    }
+
+    /// Read the namespace from [`NAMESPACE_ENV`] and remaps from the process arguments
+    pub fn from_env() -> NameResolver {
+        let namespace = env::var(NAMESPACE_ENV).unwrap_or_default();
+        NameResolver::from_args(&namespace, env::args().skip(1).collect())
+    }
+
+    /// Apply `from:=to` arguments on top of `namespace`, `__ns:=` replaces the
+    /// namespace. Arguments that aren't remaps are ignored.
+    pub fn from_args(namespace: &str, args: Vec<String>) -> NameResolver {
+        let rules: Vec<(String, String)> = args
+            .iter()
+            .filter_map(|arg| arg.split_once(REMAP_SEPARATOR))
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect();
+
+        let namespace = rules
+            .iter()
+            .rev()
+            .find(|(from, _)| from == NAMESPACE_ARG)
+            .map_or(namespace, |(_, to)| to.as_str());
+
+        let mut names = NameResolver::new(namespace);
+        rules
+            .iter()
+            .filter(|(from, _)| from != NAMESPACE_ARG)
+            .for_each(|(from, to)| names.remap(from, to));
+
+        names
+    }
+
+    /// Read `namespace` and `remap` ({from: to}) from a yaml item
+    pub fn from_byu(byu: &BuffYamlUtil, data: &Yaml) -> Result<NameResolver, ByuParseError> {
+        let mut names = NameResolver::new(&byu.parse_str("namespace", data).unwrap_or_default());
+
+        match &data["remap"] {
+            Yaml::Hash(remaps) => {
+                remaps
+                    .iter()
+                    .try_for_each(|(from, to)| match (from.as_str(), to.as_str()) {
+                        (Some(from), Some(to)) => {
+                            names.remap(from, to);
+                            Ok(())
+                        }
+                        _ => Err(ByuParseError::string("remap", &byu.yaml_path)),
+                    })?
+            }
+            Yaml::BadValue => {}
+            _ => return Err(ByuParseError::item("remap", &byu.yaml_path)),
+        }
+
+        Ok(names)
+    }
+
+    /// Add a rule, both sides are resolved in this namespace.
+    /// Later rules for the same name replace earlier ones.
+    pub fn remap(&mut self, from: &str, to: &str) {
+        let from = self.expand(from, "");
+        let to = self.expand(to, "");
+
+        self.remaps.retain(|(f, _)| *f != from);
+        self.remaps.push((from, to));
+    }
+
+    pub fn is_system(name: &str) -> bool {
+        SYSTEM_NAMES.contains(&name)
+    }
+
+    /// Resolve without remapping
+    fn expand(&self, name: &str, node: &str) -> String {
+        match name.chars().next() {
+            Some('/') => normalize_name(name),
+            Some('~') => join_name(node, &name[1..]),
+            _ => join_name(&self.namespace, name),
+        }
+    }
+
+    /// Network name of `name` for the node named `node` (already resolved)
+    pub fn resolve_private(&self, name: &str, node: &str) -> String {
+        if NameResolver::is_system(name) {
+            return name.to_string();
+        }
+
+        let name = self.expand(name, node);
+        match self.remaps.iter().find(|(from, _)| *from == name) {
+            Some((_, to)) => to.clone(),
+            None => name,
+        }
+    }
+
+    /// Network name of `name`, private names resolve to the namespace
+    pub fn resolve(&self, name: &str) -> String {
+        self.resolve_private(name, &self.namespace)
+    }
+}
+
+impl fmt::Display for NameResolver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "/{}", self.namespace)?;
+        self.remaps
+            .iter()
+            .try_for_each(|(from, to)| write!(f, " /{from}:=/{to}"))
+    }
+}
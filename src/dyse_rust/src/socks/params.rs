@@ -0,0 +1,563 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::utilities::loaders::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use yaml_rust::{yaml::Hash, Yaml};
+
+/// System message names used by the parameter service
+pub const PARAM_REQUEST_NAME: &str = "param/req";
+pub const PARAM_REPLY_NAME: &str = "param/rep";
+pub const PARAM_EVENT_NAME: &str = "param/event";
+
+/// Parameter messages have to fit in one packet (a value and its default),
+/// bigger values are rejected and long lists are split over several replies
+pub const PARAM_MAX_BYTES: usize = 400;
+pub const PARAM_PACKET_BYTES: usize = 900;
+/// How long clients wait for a node to answer
+pub const PARAM_TIMEOUT_MS: u128 = 1000;
+/// Events heard from other nodes that are kept around
+pub const PARAM_EVENT_HISTORY: usize = 64;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ParamValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Floats(Vec<f64>),
+}
+
+impl ParamValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ParamValue::Bool(_) => "bool",
+            ParamValue::Int(_) => "int",
+            ParamValue::Float(_) => "float",
+            ParamValue::String(_) => "string",
+            ParamValue::Floats(_) => "floats",
+        }
+    }
+
+    /// Convert to the type of `like`, ints are accepted as floats
+    pub fn coerce(self, like: &ParamValue) -> Option<ParamValue> {
+        match (self, like) {
+            (ParamValue::Int(v), ParamValue::Float(_)) => Some(ParamValue::Float(v as f64)),
+            (ParamValue::Float(v), ParamValue::Floats(_)) => Some(ParamValue::Floats(vec![v])),
+            (ParamValue::Int(v), ParamValue::Floats(_)) => Some(ParamValue::Floats(vec![v as f64])),
+            (value, like) if value.type_name() == like.type_name() => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Parse text (from the command line) as the type of `like`
+    pub fn parse(text: &str, like: &ParamValue) -> Option<ParamValue> {
+        match like {
+            ParamValue::Bool(_) => text.parse().ok().map(ParamValue::Bool),
+            ParamValue::Int(_) => text.parse().ok().map(ParamValue::Int),
+            ParamValue::Float(_) => text.parse().ok().map(ParamValue::Float),
+            ParamValue::String(_) => Some(ParamValue::String(text.to_string())),
+            ParamValue::Floats(_) => text
+                .trim_matches(|c| c == '[' || c == ']')
+                .split(',')
+                .filter(|v| !v.trim().is_empty())
+                .map(|v| v.trim().parse().ok())
+                .collect::<Option<Vec<f64>>>()
+                .map(ParamValue::Floats),
+        }
+    }
+
+    pub fn from_yaml(data: &Yaml) -> Option<ParamValue> {
+        match data {
+            Yaml::Boolean(v) => Some(ParamValue::Bool(*v)),
+            Yaml::Integer(v) => Some(ParamValue::Int(*v)),
+            Yaml::Real(_) => data.as_f64().map(ParamValue::Float),
+            Yaml::String(v) => Some(ParamValue::String(v.clone())),
+            Yaml::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    Yaml::Integer(v) => Some(*v as f64),
+                    _ => item.as_f64(),
+                })
+                .collect::<Option<Vec<f64>>>()
+                .map(ParamValue::Floats),
+            _ => None,
+        }
+    }
+
+    pub fn to_yaml(&self) -> Yaml {
+        match self {
+            ParamValue::Bool(v) => Yaml::Boolean(*v),
+            ParamValue::Int(v) => Yaml::Integer(*v),
+            ParamValue::Float(v) => Yaml::Real(format!("{v:?}")),
+            ParamValue::String(v) => Yaml::String(v.clone()),
+            ParamValue::Floats(v) => {
+                Yaml::Array(v.iter().map(|x| Yaml::Real(format!("{x:?}"))).collect())
+            }
+        }
+    }
+
+    /// Every number in the value
+    pub fn numbers(&self) -> Vec<f64> {
+        match self {
+            ParamValue::Int(v) => vec![*v as f64],
+            ParamValue::Float(v) => vec![*v],
+            ParamValue::Floats(v) => v.clone(),
+            _ => vec![],
+        }
+    }
+}
+
+impl fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamValue::Bool(v) => write!(f, "{v}"),
+            ParamValue::Int(v) => write!(f, "{v}"),
+            ParamValue::Float(v) => write!(f, "{v:?}"),
+            ParamValue::String(v) => write!(f, "{v:?}"),
+            ParamValue::Floats(v) => write!(f, "{v:?}"),
+        }
+    }
+}
+
+macro_rules! param_conversions {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(impl From<$ty> for ParamValue {
+            fn from(value: $ty) -> ParamValue {
+                ParamValue::$variant(value)
+            }
+        }
+
+        impl TryFrom<ParamValue> for $ty {
+            type Error = ParamValue;
+
+            fn try_from(value: ParamValue) -> Result<$ty, ParamValue> {
+                match value {
+                    ParamValue::$variant(v) => Ok(v),
+                    value => Err(value),
+                }
+            }
+        })*
+    };
+}
+
+param_conversions!(
+    bool => Bool,
+    i64 => Int,
+    f64 => Float,
+    String => String,
+    Vec<f64> => Floats,
+);
+
+impl From<&str> for ParamValue {
+    fn from(value: &str) -> ParamValue {
+        ParamValue::String(value.to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ParamError {
+    Unknown(String),
+    Exists(String),
+    Type {
+        name: String,
+        expected: String,
+    },
+    Bounds {
+        name: String,
+        min: f64,
+        max: f64,
+    },
+    Length {
+        name: String,
+        min: usize,
+        max: usize,
+    },
+    Size(String),
+    ReadOnly(String),
+    Timeout(String),
+}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamError::Unknown(name) => write!(f, "no parameter {name}"),
+            ParamError::Exists(name) => write!(f, "{name} is already declared"),
+            ParamError::Type { name, expected } => write!(f, "{name} must be a {expected}"),
+            ParamError::Bounds { name, min, max } => {
+                write!(f, "{name} must be in [{min}, {max}]")
+            }
+            ParamError::Length { name, min, max } => match min == max {
+                true => write!(f, "{name} must have {min} values"),
+                false => write!(f, "{name} must have {min} to {max} values"),
+            },
+            ParamError::Size(name) => write!(f, "{name} is too big to send"),
+            ParamError::ReadOnly(name) => write!(f, "{name} is read only"),
+            ParamError::Timeout(node) => write!(f, "{node} didn't answer"),
+        }
+    }
+}
+
+/// A typed value with a default and optional bounds (numbers only,
+/// each element of a list is checked) and list lengths
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub value: ParamValue,
+    pub default: ParamValue,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// fewest and most values a list can have
+    pub length: Option<(usize, usize)>,
+    /// keeps its default, requests and yaml can't set it
+    pub read_only: bool,
+    pub description: String,
+}
+
+impl Param {
+    pub fn new(name: &str, default: ParamValue) -> Param {
+        Param {
+            name: name.to_string(),
+            value: default.clone(),
+            default,
+            min: None,
+            max: None,
+            length: None,
+            read_only: false,
+            description: String::new(),
+        }
+    }
+
+    pub fn bounded(mut self, min: f64, max: f64) -> Param {
+        self.min = Some(min);
+        self.max = Some(max);
+        self
+    }
+
+    pub fn sized(mut self, min: usize, max: usize) -> Param {
+        self.length = Some((min, max));
+        self
+    }
+
+    pub fn read_only(mut self) -> Param {
+        self.read_only = true;
+        self
+    }
+
+    pub fn describe(mut self, description: &str) -> Param {
+        self.description = description.to_string();
+        self
+    }
+
+    /// The value `value` would be set to, if it's allowed
+    pub fn check(&self, value: ParamValue) -> Result<ParamValue, ParamError> {
+        let value = value.coerce(&self.default).ok_or(ParamError::Type {
+            name: self.name.clone(),
+            expected: self.default.type_name().to_string(),
+        })?;
+
+        let (min, max) = (
+            self.min.unwrap_or(f64::NEG_INFINITY),
+            self.max.unwrap_or(f64::INFINITY),
+        );
+        if value.numbers().iter().any(|x| !(min..=max).contains(x)) {
+            return Err(ParamError::Bounds {
+                name: self.name.clone(),
+                min,
+                max,
+            });
+        }
+
+        if let (Some((min, max)), ParamValue::Floats(values)) = (self.length, &value) {
+            if !(min..=max).contains(&values.len()) {
+                return Err(ParamError::Length {
+                    name: self.name.clone(),
+                    min,
+                    max,
+                });
+            }
+        }
+
+        match bincode::serialized_size(&value).unwrap_or(u64::MAX) as usize > PARAM_MAX_BYTES {
+            true => Err(ParamError::Size(self.name.clone())),
+            false => Ok(value),
+        }
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} ({}",
+            self.name,
+            self.value,
+            self.default.type_name()
+        )?;
+        if self.min.is_some() || self.max.is_some() {
+            write!(
+                f,
+                " in [{}, {}]",
+                self.min.unwrap_or(f64::NEG_INFINITY),
+                self.max.unwrap_or(f64::INFINITY)
+            )?;
+        }
+        match self.length {
+            Some((min, max)) if min == max => write!(f, " of {min} values")?,
+            Some((min, max)) => write!(f, " of {min} to {max} values")?,
+            None => {}
+        }
+        write!(f, ", default {}", self.default)?;
+        if self.read_only {
+            write!(f, ", read only")?;
+        }
+        write!(f, ")")?;
+        match self.description.is_empty() {
+            true => Ok(()),
+            false => write!(f, " {}", self.description),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ParamOp {
+    List,
+    Get(String),
+    Set(String, ParamValue),
+}
+
+/// Sent by `client` to the node named `node`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ParamRequest {
+    pub client: String,
+    pub id: u32,
+    pub node: String,
+    pub op: ParamOp,
+}
+
+/// Part `part` of an answer in `parts`, the client waits for all of them
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ParamReply {
+    pub client: String,
+    pub id: u32,
+    pub part: u16,
+    pub parts: u16,
+    pub params: Vec<Param>,
+    pub error: Option<ParamError>,
+}
+
+/// Broadcast whenever a node's parameter changes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ParamEvent {
+    pub node: String,
+    pub name: String,
+    pub value: ParamValue,
+    pub stamp: i64,
+}
+
+impl fmt::Display for ParamEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {} = {}", self.node, self.name, self.value)
+    }
+}
+
+/// Parameters a node declared, and the client side of asking other nodes
+/// for theirs. Requests, replies and events are system messages handled
+/// by [`crate::socks::socks::Sock::try_rx`].
+#[derive(Default)]
+pub struct ParamServer {
+    pub params: Vec<Param>,
+    /// names set since the node last checked (see [`ParamServer::take_changes`])
+    pub changes: Vec<String>,
+    /// events heard from other nodes, oldest first
+    pub events: Vec<ParamEvent>,
+    pub replies: Vec<ParamReply>,
+    pub next_id: u32,
+}
+
+impl ParamServer {
+    pub fn new() -> ParamServer {
+        ParamServer::default()
+    }
+
+    pub fn declare(&mut self, param: Param) -> Result<(), ParamError> {
+        if self.param(&param.name).is_some() {
+            return Err(ParamError::Exists(param.name));
+        }
+
+        let value = param.check(param.default.clone())?;
+        self.params.push(Param { value, ..param });
+        Ok(())
+    }
+
+    pub fn param(&self, name: &str) -> Option<&Param> {
+        self.params.iter().find(|param| param.name == name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ParamValue> {
+        self.param(name).map(|param| &param.value)
+    }
+
+    /// Set a declared parameter, returns the value it was set to
+    pub fn set(&mut self, name: &str, value: ParamValue) -> Result<ParamValue, ParamError> {
+        let param = self
+            .params
+            .iter_mut()
+            .find(|param| param.name == name)
+            .ok_or(ParamError::Unknown(name.to_string()))?;
+        if param.read_only {
+            return Err(ParamError::ReadOnly(name.to_string()));
+        }
+
+        param.value = param.check(value)?;
+        if !self.changes.iter().any(|changed| changed == name) {
+            self.changes.push(name.to_string());
+        }
+
+        Ok(param.value.clone())
+    }
+
+    pub fn take_changes(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.changes)
+    }
+
+    /// Overwrite declared parameters with the values in a yaml (like one
+    /// made by [`ParamServer::to_byu`]), unknown and read only names are skipped
+    pub fn load(&mut self, byu: &BuffYamlUtil) -> Vec<ParamError> {
+        let values: Vec<(String, Option<ParamValue>)> = match byu.data().as_hash() {
+            Some(hash) => hash
+                .iter()
+                .filter_map(|(key, data)| {
+                    key.as_str()
+                        .map(|name| (name.to_string(), ParamValue::from_yaml(data)))
+                })
+                .collect(),
+            None => vec![],
+        };
+
+        let mut errors = vec![];
+        values.into_iter().for_each(|(name, value)| {
+            let expected = match self.param(&name) {
+                Some(param) if !param.read_only => param.default.type_name().to_string(),
+                _ => return,
+            };
+
+            match value.map(|value| self.set(&name, value)) {
+                Some(Ok(_)) => {}
+                Some(Err(e)) => errors.push(e),
+                None => errors.push(ParamError::Type { name, expected }),
+            }
+        });
+
+        errors
+    }
+
+    /// Every parameter's value as yaml, in the order they were declared
+    pub fn to_byu(&self) -> BuffYamlUtil {
+        let mut hash = Hash::new();
+        self.params.iter().for_each(|param| {
+            hash.insert(Yaml::String(param.name.clone()), param.value.to_yaml());
+        });
+
+        BuffYamlUtil::from_yaml(Yaml::Hash(hash))
+    }
+
+    /// Answer `params` (or an error) in parts that fit in a packet
+    pub fn replies(
+        request: &ParamRequest,
+        answer: Result<Vec<Param>, ParamError>,
+    ) -> Vec<ParamReply> {
+        let reply = |params: Vec<Param>, error: Option<ParamError>| ParamReply {
+            client: request.client.clone(),
+            id: request.id,
+            part: 0,
+            parts: 1,
+            params,
+            error,
+        };
+
+        let mut replies = match answer {
+            Ok(params) => {
+                let mut parts = vec![reply(vec![], None)];
+                params.into_iter().for_each(|param| {
+                    let part = parts.last_mut().unwrap();
+                    part.params.push(param);
+                    if part.params.len() > 1
+                        && bincode::serialized_size(part).unwrap_or(0) as usize > PARAM_PACKET_BYTES
+                    {
+                        let param = part.params.pop().unwrap();
+                        parts.push(reply(vec![param], None));
+                    }
+                });
+                parts
+            }
+            Err(e) => vec![reply(vec![], Some(e))],
+        };
+
+        let parts = replies.len() as u16;
+        replies.iter_mut().enumerate().for_each(|(i, reply)| {
+            reply.part = i as u16;
+            reply.parts = parts;
+        });
+        replies
+    }
+
+    /// Build a request for `node`, send it and look for the answer with
+    /// [`ParamServer::take_reply`]
+    pub fn request(&mut self, client: &str, node: &str, op: ParamOp) -> ParamRequest {
+        self.next_id = self.next_id.wrapping_add(1);
+        ParamRequest {
+            client: client.to_string(),
+            id: self.next_id,
+            node: node.to_string(),
+            op,
+        }
+    }
+
+    pub fn receive(&mut self, reply: ParamReply) {
+        self.replies.push(reply);
+    }
+
+    pub fn hear(&mut self, event: ParamEvent) {
+        self.events.push(event);
+        if self.events.len() > PARAM_EVENT_HISTORY {
+            self.events.remove(0);
+        }
+    }
+
+    /// The whole answer to request `id`, once every part arrived
+    pub fn take_reply(&mut self, id: u32) -> Option<Result<Vec<Param>, ParamError>> {
+        let parts: Vec<&ParamReply> = self.replies.iter().filter(|reply| reply.id == id).collect();
+
+        match parts
+            .first()
+            .is_some_and(|first| parts.len() >= first.parts as usize)
+        {
+            true => {
+                let (mut parts, rest): (Vec<ParamReply>, Vec<ParamReply>) =
+                    std::mem::take(&mut self.replies)
+                        .into_iter()
+                        .partition(|reply| reply.id == id);
+                self.replies = rest;
+                parts.sort_by_key(|part| part.part);
+
+                match parts.iter().find_map(|part| part.error.clone()) {
+                    Some(e) => Some(Err(e)),
+                    None => Some(Ok(parts.into_iter().flat_map(|part| part.params).collect())),
+                }
+            }
+            false => None,
+        }
+    }
+}
@@ -0,0 +1,486 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Shape of a serialized message, enough to decode it without the rust type
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum Schema {
+    Unit,
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+    String,
+    Option(Box<Schema>),
+    Seq(Box<Schema>),
+    Tuple(Vec<Schema>),
+    /// type name and named fields
+    Struct(String, Vec<(String, Schema)>),
+    /// type name and unit variants
+    Enum(String, Vec<String>),
+}
+
+/// Types that can describe themselves, use [`crate::schema`] on your own structs
+pub trait HasSchema {
+    fn schema() -> Schema;
+}
+
+/// Define a serde struct (or unit enum) and its [`HasSchema`] impl together
+/// so the schema can't drift from the type.
+/// ```
+/// use dyse_rust::schema;
+/// use serde::{Deserialize, Serialize};
+///
+/// schema! {
+///     #[derive(Serialize, Deserialize)]
+///     pub struct Pose {
+///         pub x: f64,
+///         pub y: f64,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! schema {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident {
+        $($(#[$fmeta:meta])* $fvis:vis $field:ident : $ty:ty),* $(,)?
+    }) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$fmeta])* $fvis $field: $ty),*
+        }
+
+        impl $crate::socks::schema::HasSchema for $name {
+            fn schema() -> $crate::socks::schema::Schema {
+                $crate::socks::schema::Schema::Struct(
+                    stringify!($name).to_string(),
+                    vec![$((
+                        stringify!($field).to_string(),
+                        <$ty as $crate::socks::schema::HasSchema>::schema(),
+                    )),*],
+                )
+            }
+        }
+    };
+
+    ($(#[$meta:meta])* $vis:vis enum $name:ident {
+        $($(#[$vmeta:meta])* $variant:ident),* $(,)?
+    }) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($(#[$vmeta])* $variant),*
+        }
+
+        impl $crate::socks::schema::HasSchema for $name {
+            fn schema() -> $crate::socks::schema::Schema {
+                $crate::socks::schema::Schema::Enum(
+                    stringify!($name).to_string(),
+                    vec![$(stringify!($variant).to_string()),*],
+                )
+            }
+        }
+    };
+}
+
+macro_rules! primitive_schema {
+    ($($ty:ty => $schema:ident),* $(,)?) => {
+        $(impl HasSchema for $ty {
+            fn schema() -> Schema {
+                Schema::$schema
+            }
+        })*
+    };
+}
+
+primitive_schema!(
+    () => Unit,
+    bool => Bool,
+    u8 => U8,
+    u16 => U16,
+    u32 => U32,
+    u64 => U64,
+    usize => U64,
+    i8 => I8,
+    i16 => I16,
+    i32 => I32,
+    i64 => I64,
+    isize => I64,
+    f32 => F32,
+    f64 => F64,
+    String => String,
+);
+
+impl<T: HasSchema> HasSchema for Option<T> {
+    fn schema() -> Schema {
+        Schema::Option(Box::new(T::schema()))
+    }
+}
+
+impl<T: HasSchema> HasSchema for Vec<T> {
+    fn schema() -> Schema {
+        Schema::Seq(Box::new(T::schema()))
+    }
+}
+
+impl<T: HasSchema, const N: usize> HasSchema for [T; N] {
+    fn schema() -> Schema {
+        Schema::Tuple((0..N).map(|_| T::schema()).collect())
+    }
+}
+
+impl<A: HasSchema, B: HasSchema> HasSchema for (A, B) {
+    fn schema() -> Schema {
+        Schema::Tuple(vec![A::schema(), B::schema()])
+    }
+}
+
+impl<A: HasSchema, B: HasSchema, C: HasSchema> HasSchema for (A, B, C) {
+    fn schema() -> Schema {
+        Schema::Tuple(vec![A::schema(), B::schema(), C::schema()])
+    }
+}
+
+/// A payload didn't match its schema
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaError {
+    pub expected: String,
+    pub offset: usize,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected {} at byte {}", self.expected, self.offset)
+    }
+}
+
+/// Decoded message, prints as JSON
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Look up a field of an object
+    pub fn get(&self, field: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == field)
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Int(v) => Some(*v as f64),
+            Value::UInt(v) => Some(*v as f64),
+            Value::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Value> for Value {
+    fn from(json: serde_json::Value) -> Value {
+        match json {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(v) => Value::Bool(v),
+            serde_json::Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+                (Some(v), _) => Value::UInt(v),
+                (_, Some(v)) => Value::Int(v),
+                _ => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            serde_json::Value::String(v) => Value::String(v),
+            serde_json::Value::Array(items) => {
+                Value::Array(items.into_iter().map(Value::from).collect())
+            }
+            serde_json::Value::Object(fields) => Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(name, field)| (name, Value::from(field)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(v) => write!(f, "{v}"),
+            Value::Int(v) => write!(f, "{v}"),
+            Value::UInt(v) => write!(f, "{v}"),
+            Value::Float(v) => match v.is_finite() {
+                true => write!(f, "{v:?}"),
+                false => write!(f, "null"),
+            },
+            Value::String(v) => write!(f, "{v:?}"),
+            Value::Array(items) => {
+                write!(f, "[")?;
+                items.iter().enumerate().try_for_each(|(i, item)| match i {
+                    0 => write!(f, "{item}"),
+                    _ => write!(f, ", {item}"),
+                })?;
+                write!(f, "]")
+            }
+            Value::Object(fields) => {
+                write!(f, "{{")?;
+                fields
+                    .iter()
+                    .enumerate()
+                    .try_for_each(|(i, (name, value))| match i {
+                        0 => write!(f, "{name:?}: {value}"),
+                        _ => write!(f, ", {name:?}: {value}"),
+                    })?;
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// Reads bincode (little endian, u64 lengths, u32 variants) one item at a time
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize, expected: &Schema) -> Result<&'a [u8], SchemaError> {
+        match self.bytes.len() >= self.offset + n {
+            true => {
+                self.offset += n;
+                Ok(&self.bytes[self.offset - n..self.offset])
+            }
+            false => Err(self.error(expected)),
+        }
+    }
+
+    fn error(&self, expected: &Schema) -> SchemaError {
+        SchemaError {
+            expected: expected.to_string(),
+            offset: self.offset,
+        }
+    }
+
+    fn read<const N: usize>(&mut self, expected: &Schema) -> Result<[u8; N], SchemaError> {
+        Ok(self.take(N, expected)?.try_into().unwrap())
+    }
+
+    fn len(&mut self, expected: &Schema) -> Result<usize, SchemaError> {
+        let len = u64::from_le_bytes(self.read(expected)?) as usize;
+        // a length longer than what's left is garbage, don't try to allocate it
+        match len <= self.bytes.len() - self.offset {
+            true => Ok(len),
+            false => Err(self.error(expected)),
+        }
+    }
+
+    fn value(&mut self, schema: &Schema) -> Result<Value, SchemaError> {
+        Ok(match schema {
+            Schema::Unit => Value::Null,
+            Schema::Bool => match self.read::<1>(schema)?[0] {
+                0 => Value::Bool(false),
+                1 => Value::Bool(true),
+                _ => return Err(self.error(schema)),
+            },
+            Schema::U8 => Value::UInt(u8::from_le_bytes(self.read(schema)?) as u64),
+            Schema::U16 => Value::UInt(u16::from_le_bytes(self.read(schema)?) as u64),
+            Schema::U32 => Value::UInt(u32::from_le_bytes(self.read(schema)?) as u64),
+            Schema::U64 => Value::UInt(u64::from_le_bytes(self.read(schema)?)),
+            Schema::I8 => Value::Int(i8::from_le_bytes(self.read(schema)?) as i64),
+            Schema::I16 => Value::Int(i16::from_le_bytes(self.read(schema)?) as i64),
+            Schema::I32 => Value::Int(i32::from_le_bytes(self.read(schema)?) as i64),
+            Schema::I64 => Value::Int(i64::from_le_bytes(self.read(schema)?)),
+            Schema::F32 => Value::Float(f32::from_le_bytes(self.read(schema)?) as f64),
+            Schema::F64 => Value::Float(f64::from_le_bytes(self.read(schema)?)),
+            Schema::String => {
+                let len = self.len(schema)?;
+                let offset = self.offset;
+                match String::from_utf8(self.take(len, schema)?.to_vec()) {
+                    Ok(s) => Value::String(s),
+                    Err(_) => {
+                        return Err(SchemaError {
+                            expected: schema.to_string(),
+                            offset,
+                        })
+                    }
+                }
+            }
+            Schema::Option(inner) => match self.read::<1>(schema)?[0] {
+                0 => Value::Null,
+                1 => self.value(inner)?,
+                _ => return Err(self.error(schema)),
+            },
+            Schema::Seq(inner) => {
+                let len = self.len(schema)?;
+                Value::Array(
+                    (0..len)
+                        .map(|_| self.value(inner))
+                        .collect::<Result<Vec<Value>, SchemaError>>()?,
+                )
+            }
+            Schema::Tuple(items) => Value::Array(
+                items
+                    .iter()
+                    .map(|item| self.value(item))
+                    .collect::<Result<Vec<Value>, SchemaError>>()?,
+            ),
+            Schema::Struct(_, fields) => Value::Object(
+                fields
+                    .iter()
+                    .map(|(name, field)| Ok((name.clone(), self.value(field)?)))
+                    .collect::<Result<Vec<(String, Value)>, SchemaError>>()?,
+            ),
+            Schema::Enum(_, variants) => {
+                let variant = u32::from_le_bytes(self.read(schema)?) as usize;
+                match variants.get(variant) {
+                    Some(name) => Value::String(name.clone()),
+                    None => return Err(self.error(schema)),
+                }
+            }
+        })
+    }
+}
+
+impl Schema {
+    pub fn of<T: HasSchema>() -> Schema {
+        T::schema()
+    }
+
+    /// Decode a bincode payload, every byte has to be used
+    pub fn decode(&self, bytes: &[u8]) -> Result<Value, SchemaError> {
+        let mut reader = Reader { bytes, offset: 0 };
+        let value = reader.value(self)?;
+
+        match reader.offset == bytes.len() {
+            true => Ok(value),
+            false => Err(SchemaError {
+                expected: "end of payload".to_string(),
+                offset: reader.offset,
+            }),
+        }
+    }
+}
+
+impl Schema {
+    /// Check a value from a self describing codec (json, msgpack) against
+    /// the schema, struct fields come back in declaration order
+    pub fn conform(&self, value: Value) -> Result<Value, SchemaError> {
+        let error = || SchemaError {
+            expected: self.to_string(),
+            offset: 0,
+        };
+
+        match (self, value) {
+            (Schema::Unit, Value::Null) => Ok(Value::Null),
+            (Schema::Option(_), Value::Null) => Ok(Value::Null),
+            (Schema::Option(inner), value) => inner.conform(value),
+            (Schema::Bool, Value::Bool(v)) => Ok(Value::Bool(v)),
+            (Schema::F32 | Schema::F64, value) => {
+                value.as_f64().map(Value::Float).ok_or_else(error)
+            }
+            (Schema::U8 | Schema::U16 | Schema::U32 | Schema::U64, Value::UInt(v)) => {
+                Ok(Value::UInt(v))
+            }
+            (Schema::I8 | Schema::I16 | Schema::I32 | Schema::I64, Value::Int(v)) => {
+                Ok(Value::Int(v))
+            }
+            (Schema::I8 | Schema::I16 | Schema::I32 | Schema::I64, Value::UInt(v)) => {
+                Ok(Value::Int(v as i64))
+            }
+            (Schema::String, Value::String(v)) => Ok(Value::String(v)),
+            (Schema::Enum(_, variants), Value::String(v)) if variants.contains(&v) => {
+                Ok(Value::String(v))
+            }
+            (Schema::Seq(inner), Value::Array(items)) => Ok(Value::Array(
+                items
+                    .into_iter()
+                    .map(|item| inner.conform(item))
+                    .collect::<Result<Vec<Value>, SchemaError>>()?,
+            )),
+            (Schema::Tuple(schemas), Value::Array(items)) if schemas.len() == items.len() => {
+                Ok(Value::Array(
+                    schemas
+                        .iter()
+                        .zip(items)
+                        .map(|(schema, item)| schema.conform(item))
+                        .collect::<Result<Vec<Value>, SchemaError>>()?,
+                ))
+            }
+            (Schema::Struct(_, fields), Value::Object(mut values)) => Ok(Value::Object(
+                fields
+                    .iter()
+                    .map(|(name, schema)| {
+                        match values.iter().position(|(field, _)| field == name) {
+                            Some(i) => Ok((name.clone(), schema.conform(values.remove(i).1)?)),
+                            None => Err(SchemaError {
+                                expected: format!("{self}.{name}"),
+                                offset: 0,
+                            }),
+                        }
+                    })
+                    .collect::<Result<Vec<(String, Value)>, SchemaError>>()?,
+            )),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Schema::Unit => write!(f, "()"),
+            Schema::Bool => write!(f, "bool"),
+            Schema::U8 => write!(f, "u8"),
+            Schema::U16 => write!(f, "u16"),
+            Schema::U32 => write!(f, "u32"),
+            Schema::U64 => write!(f, "u64"),
+            Schema::I8 => write!(f, "i8"),
+            Schema::I16 => write!(f, "i16"),
+            Schema::I32 => write!(f, "i32"),
+            Schema::I64 => write!(f, "i64"),
+            Schema::F32 => write!(f, "f32"),
+            Schema::F64 => write!(f, "f64"),
+            Schema::String => write!(f, "String"),
+            Schema::Option(inner) => write!(f, "Option<{inner}>"),
+            Schema::Seq(inner) => write!(f, "Vec<{inner}>"),
+            Schema::Tuple(items) => write!(
+                f,
+                "({})",
+                items
+                    .iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Schema::Struct(name, _) | Schema::Enum(name, _) => write!(f, "{name}"),
+        }
+    }
+}
@@ -11,12 +11,12 @@
  *
  ********************************************************************************/
 
-use dyse_rust::socks::socks::*;
+use dyse_rust::socks::{logging, names::strip_remaps, socks::*};
 use std::env;
 
 fn main() {
-    let mut args: Vec<String> = env::args().collect();
-    args.remove(0);
+    logging::init_from_env();
+    let mut args = strip_remaps(env::args().skip(1).collect());
 
     let name = args[0].clone();
     args.remove(0);
@@ -24,5 +24,7 @@ fn main() {
     let data: Vec<f64> = args.iter().map(|arg| arg.parse::<f64>().unwrap()).collect();
 
     let mut sock = Sock::source(&name);
+    let topic = sock.name.clone();
+    sock.advertise_schema::<Vec<f64>>(&topic);
     sock.tx_payload(data);
 }
@@ -15,9 +15,20 @@
 use more_asserts::assert_le;
 
 use crate::{
-    add_task, build_fn, ipv4, sock_uri,
-    socks::{message::*, sockapi, socks::*, task::*},
+    add_task, build_fn, ipv4,
+    msgs::{
+        diagnostics::*,
+        firmware::{TaskMarshall, TaskMarshallType},
+        logging::*,
+    },
+    rid::robot_firmware::TaskCommunication,
+    sock_uri,
+    socks::{
+        clocks::*, codec::*, diagnostics::*, logging::*, loopback::*, message::*, names::*,
+        params::*, schema::*, sockapi, socks::*, task::*, throttle::*, time_sync::*, topics::*,
+    },
     sync, unsync,
+    utilities::loaders::BuffYamlUtil,
 };
 use std::{
     env, io,
@@ -138,7 +149,7 @@ pub mod message {
         let mut new_message = Message::new();
 
         packets.into_iter().for_each(|packet| {
-            new_message.collect(0, 0, MessageFragment::from_bytes(packet).1);
+            new_message.collect(0, 0, 0, MessageFragment::from_bytes(packet).1);
         });
 
         let new_payload = new_message.to_payload();
@@ -154,13 +165,13 @@ pub mod message {
     pub fn message_from_sock() {
         let msg = Message::from_payload(vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
 
-        let packets = msg.packets([0; SOCK_HEADER_LEN]);
+        let sock = Sock::source("node0");
+        let packets = msg.packets(sock.header_bytes("node1", 0, 0, CodecId::Bincode));
         let (header, _) = MessageFragment::from_bytes(packets[0]);
 
-        let sock = Sock::source("node0");
-        let (name1, _, _, _) = sock.header_from_bytes(header);
+        let (id1, _, _, _, _, _) = sock.header_from_bytes(header);
 
-        assert_eq!(name1, "node1", "name1 was wrong");
+        assert_eq!(id1, topic_id("node1"), "id1 was wrong");
 
         let big_msg = Message::from_payload(vec![1; 2048]);
 
@@ -169,4 +180,1304 @@ pub mod message {
         assert_eq!(big_msg.fragments[1].n_bytes, 950);
         assert_eq!(big_msg.fragments[2].n_bytes, 148);
     }
+
+    #[test]
+    pub fn message_stamp() {
+        let sock = Sock::source("node0");
+        let stamp = stamp_micros();
+        let header = sock.header_bytes("node1", 1000, stamp, CodecId::Bincode);
+
+        let (id, _, _, activity, source, codec) = sock.header_from_bytes(header);
+        assert_eq!(id, topic_id("node1"), "id was wrong");
+        assert_eq!(activity, 1000, "activity was wrong");
+        assert_eq!(source, stamp, "source stamp was wrong");
+        assert_eq!(codec, Some(CodecId::Bincode), "codec was wrong");
+
+        let msg = Message::from_payload(vec![1, 2, 3]);
+        let mut new_message = Message::new();
+        msg.packets(header).into_iter().for_each(|packet| {
+            let (header, fragment) = MessageFragment::from_bytes(packet);
+            let (_, ntx, _, activity, source, _) = sock.header_from_bytes(header);
+            new_message.collect(ntx, activity, source, fragment);
+        });
+
+        assert_eq!(
+            new_message.stamp.source, stamp,
+            "message lost its source stamp"
+        );
+        assert_le!(new_message.stamp.source, new_message.stamp.received);
+        assert_le!(0.0, new_message.stamp.latency());
+    }
+}
+
+#[cfg(test)]
+pub mod clock_sync {
+    use super::*;
+
+    /// Build the reply a reference `offset` micros ahead would send
+    /// with a symmetric one way `delay`
+    pub fn fake_reply(request: ClockRequest, offset: i64, delay: i64) -> (ClockReply, i64) {
+        let t2 = request.t1 + delay + offset;
+        let t3 = t2 + 50;
+        let t4 = t3 - offset + delay;
+        (
+            ClockReply {
+                name: request.name,
+                t1: request.t1,
+                t2,
+                t3,
+            },
+            t4,
+        )
+    }
+
+    #[test]
+    pub fn clock_offset() {
+        let mut sync = TimeSync::follower();
+        assert!(!sync.is_synced());
+
+        let request = sync.request("node0").expect("follower didn't request");
+        assert!(sync.request("node0").is_none(), "requested too often");
+
+        let (reply, t4) = fake_reply(request, 5000, 100);
+        assert!(sync.update("node0", reply, t4), "reply was rejected");
+
+        assert!(sync.is_synced());
+        assert_eq!(sync.delay, 200);
+        assert_eq!(sync.correct(t4), t4 + 5000);
+    }
+
+    #[test]
+    pub fn clock_drift() {
+        let mut sync = TimeSync::follower();
+
+        (0..CLOCK_SYNC_SAMPLES).for_each(|i| {
+            let request = sync
+                .request_at("node0", 1_000_000 * i as i64)
+                .expect("follower didn't request");
+            // drifts 10us per second
+            let (reply, t4) = fake_reply(request, 2000 + 10 * i as i64, 100);
+            assert!(sync.update("node0", reply, t4), "reply was rejected");
+        });
+
+        assert_le!((sync.drift * 1E6 - 10.0).abs(), 0.01, "wrong drift");
+        let t = 1_000_000 * CLOCK_SYNC_SAMPLES as i64;
+        assert_le!(
+            (sync.correct(t) - (t + 2000 + 10 * CLOCK_SYNC_SAMPLES as i64)).abs(),
+            2
+        );
+    }
+
+    #[test]
+    pub fn clock_rejects() {
+        let mut sync = TimeSync::follower();
+        let request = sync.request("node0").unwrap();
+
+        let (reply, t4) = fake_reply(request.clone(), 5000, 100);
+        assert!(
+            !sync.update("node1", reply.clone(), t4),
+            "took another socks reply"
+        );
+
+        let (slow_reply, t4) = fake_reply(request, 5000, CLOCK_SYNC_MAX_DELAY_US);
+        assert!(!sync.update("node0", slow_reply, t4), "took a slow reply");
+        assert!(!sync.update("node0", reply, t4), "took a stale reply");
+
+        let reference = TimeSync::reference();
+        assert!(reference.is_synced());
+        assert_eq!(reference.correct(t4), t4);
+        assert!(TimeSync::follower()
+            .reply(
+                ClockRequest {
+                    name: "node0".to_string(),
+                    t1: 0
+                },
+                0
+            )
+            .is_none());
+    }
+}
+
+#[cfg(test)]
+pub mod sim_time {
+    use super::*;
+
+    /// Hand a payload to the sock as if `name` sent it at `micros_rate`
+    pub fn deliver<T: serde::Serialize>(sock: &mut Sock, name: &str, data: T, micros_rate: u64) {
+        let idx = sock.is_target(name).expect("not a target");
+        let msg = Message::from_payload(bincode::serialize(&data).unwrap());
+        let header = sock.header_bytes(name, micros_rate, sock.now(), CodecId::Bincode);
+
+        msg.packets(header).into_iter().for_each(|packet| {
+            let (header, fragment) = MessageFragment::from_bytes(packet);
+            let (_, ntx, _, activity, stamp, codec) = sock.header_from_bytes(header);
+            sock.collect(idx, ntx, activity, stamp, codec.unwrap(), fragment);
+        });
+    }
+
+    #[test]
+    pub fn sim_clock() {
+        let clock = Clock::sim();
+        let shared = clock.clone();
+
+        assert_eq!(clock.micros(), 0);
+        shared.advance(1500);
+        assert_eq!(clock.micros(), 1500);
+        clock.set(1000);
+        assert_eq!(shared.micros(), 1500, "sim time went backwards");
+        clock.set(2000);
+        assert_eq!(shared.elapsed(500), 1500);
+
+        let real = Clock::real();
+        real.advance(1_000_000);
+        assert_le!(real.micros(), 1_000_000, "advanced a real clock");
+    }
+
+    #[test]
+    pub fn sim_message_available() {
+        let clock = Clock::sim();
+        let mut sock = Sock::sinc("sim_sinc", vec!["signal"]);
+        sock.set_clock(clock.clone());
+
+        clock.advance(1000);
+        deliver(&mut sock, "signal", 1.0f64, 10_000);
+
+        assert_eq!(sock.available_messages(), vec![0]);
+        assert_eq!(sock.messages[0].stamp.source, 1000);
+        assert_eq!(sock.messages[0].stamp.latency(), 0.0);
+
+        clock.advance(4_999);
+        assert_eq!(sock.available_messages(), vec![0]);
+
+        clock.advance(1);
+        assert_eq!(sock.available_messages(), Vec::<usize>::new());
+    }
+
+    #[test]
+    pub fn sim_synced_task() {
+        let clock = Clock::sim();
+        let mut sock = sync!(
+            "sim_relay",
+            vec!["a", "b"],
+            "sum",
+            0.0f64,
+            |ctx: f64, t, a: f64, b: f64| {
+                ctx += a + b;
+                (t, ctx)
+            }
+        );
+        sock.set_clock(clock.clone());
+
+        clock.advance(10_000);
+        deliver(&mut sock, "a", 1.0f64, 10_000);
+        deliver(&mut sock, "b", 2.0f64, 10_000);
+
+        let output: (f64, f64) = bincode::deserialize(&sock.sync_call(0)).unwrap();
+        assert_eq!(output, (0.01, 3.0));
+        assert_eq!(
+            sock.sync_call(0),
+            Vec::<u8>::new(),
+            "ran twice without new data"
+        );
+
+        clock.advance(10_000);
+        deliver(&mut sock, "a", 1.0f64, 10_000);
+        deliver(&mut sock, "b", 2.0f64, 10_000);
+
+        let output: (f64, f64) = bincode::deserialize(&sock.sync_call(0)).unwrap();
+        assert_eq!(output, (0.02, 6.0));
+        assert_eq!(sock.tasks[0].get_context::<f64>(), 6.0);
+    }
+}
+
+#[cfg(test)]
+pub mod loopback {
+    use super::*;
+
+    /// Read everything ready on the sock, returns the completed message indices
+    pub fn drain(sock: &mut Sock) -> Vec<usize> {
+        let mut buffer = [0u8; UDP_PACKET_SIZE];
+        let mut completed = vec![];
+        while sock.socket.peek_from(&mut [0; 1]).is_ok() {
+            if let Some(i) = sock.try_rx(&mut buffer) {
+                completed.push(i);
+            }
+        }
+        completed
+    }
+
+    pub fn network(config: LoopbackConfig) -> LoopbackNetwork {
+        LoopbackNetwork::new(Clock::sim(), config)
+    }
+
+    #[test]
+    pub fn loopback_fragments() {
+        let mut config = LoopbackConfig::ideal();
+        config.reorder = 1.0;
+        let net = network(config);
+
+        let mut source = Sock::loopback("big_source", vec![], &net);
+        let mut sinc = Sock::loopback("sinc", vec!["big_source"], &net);
+
+        let payload: Vec<u8> = (0..2048).map(|i| (i % 251) as u8).collect();
+        source.tx_payload(payload.clone());
+
+        assert_eq!(drain(&mut sinc), vec![0]);
+        let received: Vec<u8> = bincode::deserialize(&sinc.messages[0].to_payload()).unwrap();
+        assert_eq!(
+            received, payload,
+            "reordered fragments were not reassembled"
+        );
+    }
+
+    #[test]
+    pub fn loopback_loss() {
+        let mut config = LoopbackConfig::ideal();
+        config.loss = 1.0;
+        let net = network(config);
+
+        let mut source = Sock::loopback("source", vec![], &net);
+        let mut sinc = Sock::loopback("sinc", vec!["source"], &net);
+
+        source.tx_payload(vec![0u8; 2048]);
+
+        assert_eq!(drain(&mut sinc), Vec::<usize>::new());
+        assert_eq!(
+            net.dropped(),
+            8,
+            "each fragment (and the topic announcement) should be lost at both ports"
+        );
+
+        net.configure(LoopbackConfig::ideal());
+        source.tx_payload(1.0f64);
+        assert_eq!(drain(&mut sinc), vec![0]);
+    }
+
+    #[test]
+    pub fn loopback_duplication() {
+        let mut config = LoopbackConfig::ideal();
+        config.duplication = 1.0;
+        let net = network(config);
+
+        let mut source = Sock::loopback("source", vec![], &net);
+        let mut sinc = Sock::loopback("sinc", vec!["source"], &net);
+
+        source.tx_payload(vec![7u8; 2048]);
+
+        assert!(!drain(&mut sinc).is_empty(), "never completed the message");
+        let received: Vec<u8> = bincode::deserialize(&sinc.messages[0].to_payload()).unwrap();
+        assert_eq!(received, vec![7u8; 2048]);
+    }
+
+    #[test]
+    pub fn loopback_latency() {
+        let mut config = LoopbackConfig::ideal();
+        config.latency = 5000;
+        config.jitter = 1000;
+        let net = network(config);
+        let clock = net.clock();
+
+        let mut source = Sock::loopback("source", vec![], &net);
+        let mut sinc = Sock::loopback("sinc", vec!["source"], &net);
+
+        source.tx_payload(1.0f64);
+
+        clock.advance(4999);
+        assert_eq!(drain(&mut sinc), Vec::<usize>::new(), "arrived too early");
+
+        clock.advance(1001);
+        assert_eq!(drain(&mut sinc), vec![0]);
+        assert_le!(0.005, sinc.messages[0].stamp.latency());
+        assert_le!(sinc.messages[0].stamp.latency(), 0.006);
+    }
+
+    #[test]
+    pub fn loopback_graph() {
+        let net = network(LoopbackConfig::ideal());
+        let clock = net.clock();
+
+        let mut source = Sock::loopback("signal1", vec![], &net);
+        let mut relay = Sock::loopback("relay", vec![], &net);
+        let mut sinc = Sock::loopback("sinc", vec!["double"], &net);
+        add_task!(
+            relay,
+            vec!["signal1"],
+            "double",
+            0,
+            |_ctx: u8, data: f64| { 2.0 * data[0] }
+        );
+
+        (1..4).for_each(|i| {
+            clock.advance(1000);
+            source.tx_payload(i as f64);
+
+            drain(&mut relay)
+                .into_iter()
+                .for_each(|idx| relay.try_all_tasks(idx));
+
+            assert_eq!(drain(&mut sinc), vec![0]);
+            // task outputs are bincode bytes sent as a payload
+            let bytes: Vec<u8> = bincode::deserialize(&sinc.messages[0].to_payload()).unwrap();
+            let value: f64 = bincode::deserialize(&bytes).unwrap();
+            assert_eq!(value, 2.0 * i as f64);
+        });
+    }
+
+    #[test]
+    pub fn loopback_clock_sync() {
+        let net = network(LoopbackConfig::ideal());
+
+        let mut reference = Sock::loopback("reference", vec![], &net);
+        reference.time_sync = TimeSync::reference();
+        // like RobotFirmware, never spins, only reads
+        let mut follower = Sock::loopback("follower", vec![], &net);
+
+        let mut buffer = [0u8; UDP_PACKET_SIZE];
+        assert_eq!(follower.try_rx(&mut buffer), None);
+        assert!(!follower.time_sync.is_synced());
+
+        drain(&mut reference);
+        drain(&mut follower);
+        assert!(
+            follower.time_sync.is_synced(),
+            "reading didn't sync the clock"
+        );
+    }
+}
+
+#[cfg(test)]
+pub mod names {
+    use super::*;
+    use crate::utilities::loaders::BuffYamlUtil;
+
+    #[test]
+    pub fn names_resolve() {
+        let names = NameResolver::new("/robot1/");
+
+        assert_eq!(names.resolve("imu/data"), "robot1/imu/data");
+        assert_eq!(names.resolve("/imu//data/"), "imu/data");
+        assert_eq!(
+            names.resolve_private("~data", "robot1/imu"),
+            "robot1/imu/data"
+        );
+        assert_eq!(names.resolve("shutdown"), "shutdown");
+        assert_eq!(names.resolve(CLOCK_TOPIC), CLOCK_TOPIC);
+
+        let global = NameResolver::default();
+        assert_eq!(global.resolve("imu"), "imu");
+        assert_eq!(global.resolve("/imu"), "imu");
+    }
+
+    #[test]
+    pub fn names_remap() {
+        let names = NameResolver::from_args(
+            "robot2",
+            vec![
+                "--verbose".to_string(),
+                "__ns:=/robot1".to_string(),
+                "imu:=/sensors/imu".to_string(),
+                "/ctrl:=ctrl".to_string(),
+            ],
+        );
+
+        assert_eq!(names.namespace, "robot1");
+        assert_eq!(names.resolve("imu"), "sensors/imu");
+        assert_eq!(names.resolve("/robot1/imu"), "sensors/imu");
+        assert_eq!(names.resolve("ctrl"), "robot1/ctrl");
+        assert_eq!(names.resolve("/ctrl"), "robot1/ctrl");
+        assert_eq!(
+            names.to_string(),
+            "/robot1 /robot1/imu:=/sensors/imu /ctrl:=/robot1/ctrl"
+        );
+
+        let byu = BuffYamlUtil::new("node:\n  namespace: robot3\n  remap:\n    imu: /imu");
+        let names = NameResolver::from_byu(&byu, byu.item("node").unwrap()).unwrap();
+        assert_eq!(names.resolve("imu"), "imu");
+        assert_eq!(names.resolve("gps"), "robot3/gps");
+
+        // the launcher passes remaps next to the targets, they aren't topics
+        let args: Vec<String> = ["imu", "__ns:=/robot1", "gps", "imu:=/sensors/imu"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        assert_eq!(strip_remaps(args.clone()), vec!["imu", "gps"]);
+        let names = NameResolver::from_args("", args);
+        assert_eq!(names.resolve("gps"), "robot1/gps");
+    }
+
+    #[test]
+    pub fn names_side_by_side() {
+        let net = loopback::network(LoopbackConfig::ideal());
+        let robot = |ns: &str, name: &str, targets: Vec<&str>| {
+            let mut sock = Sock::with_names(
+                name,
+                targets,
+                vec![],
+                SockSocket::Loopback(net.port()),
+                NameResolver::new(ns),
+            );
+            sock.set_clock(net.clock());
+            sock
+        };
+
+        let mut imu1 = robot("robot1", "imu", vec![]);
+        let mut imu2 = robot("robot2", "imu", vec![]);
+        let mut sinc1 = robot("robot1", "sinc", vec!["imu"]);
+        let mut sinc2 = robot("robot2", "sinc", vec!["imu", "/robot1/imu"]);
+
+        assert_eq!(imu1.name, "robot1/imu");
+        assert_eq!(sinc2.targets, vec!["robot2/imu", "robot1/imu"]);
+
+        imu1.tx_payload(1.0f64);
+        imu2.tx_payload(2.0f64);
+
+        assert_eq!(loopback::drain(&mut sinc1), vec![0]);
+        assert_eq!(loopback::drain(&mut sinc2), vec![1, 0]);
+        assert_eq!(
+            bincode::deserialize::<f64>(&sinc1.messages[0].to_payload()).unwrap(),
+            1.0
+        );
+        assert_eq!(
+            bincode::deserialize::<f64>(&sinc2.messages[0].to_payload()).unwrap(),
+            2.0
+        );
+    }
+}
+
+#[cfg(test)]
+pub mod topics {
+    use super::*;
+
+    #[test]
+    pub fn topics_long_names() {
+        let net = loopback::network(LoopbackConfig::ideal());
+
+        let long = "robot1/perception/front_camera/detections/filtered";
+        let similar = "robot1/perception/front_camera/detections/raw";
+        let mut source = Sock::loopback(long, vec![], &net);
+        let mut other = Sock::loopback(similar, vec![], &net);
+        let mut sinc = Sock::loopback("sinc", vec![long, similar], &net);
+
+        assert_eq!(source.name, long, "long name was truncated");
+
+        source.tx_payload(1.0f64);
+        other.tx_payload(2.0f64);
+
+        assert_eq!(loopback::drain(&mut sinc), vec![0, 1]);
+        assert_eq!(
+            bincode::deserialize::<f64>(&sinc.messages[0].to_payload()).unwrap(),
+            1.0
+        );
+        assert_eq!(
+            bincode::deserialize::<f64>(&sinc.messages[1].to_payload()).unwrap(),
+            2.0
+        );
+    }
+
+    #[test]
+    pub fn topics_announce() {
+        let net = loopback::network(LoopbackConfig::ideal());
+
+        let mut source = Sock::loopback("source", vec![], &net);
+        let mut listener = Sock::loopback("listener", vec![], &net);
+
+        source.tx_payload(1.0f64);
+        source.tx_payload(2.0f64);
+        loopback::drain(&mut listener);
+
+        assert_eq!(listener.topics.name(topic_id("source")), Some("source"));
+        assert_eq!(source.topics.advertised, vec![topic_id("source")]);
+        assert!(
+            source.topics.announcements().is_empty(),
+            "announced too often"
+        );
+
+        source.topics.last_announce = None;
+        assert_eq!(source.topics.announcements().len(), 1);
+    }
+
+    #[test]
+    pub fn topics_announce_batches() {
+        let net = loopback::network(LoopbackConfig::ideal());
+
+        let mut source = Sock::loopback("robot", vec![], &net);
+        let mut listener = Sock::loopback("listener", vec![], &net);
+
+        let names: Vec<String> = (0..16).map(|i| format!("robot/task{i}")).collect();
+        names.iter().for_each(|name| {
+            source.advertise_schema::<TaskCommunication>(name);
+            source.topics.advertise(topic_id(name));
+        });
+        let everything = TopicAnnouncement {
+            topics: names
+                .iter()
+                .filter_map(|name| source.topics.info(topic_id(name)))
+                .collect(),
+        };
+        assert!(!everything.fits(), "needs more than one fragment");
+
+        let announcements = source.topics.announcements();
+        assert!(announcements.len() > 1);
+        assert!(announcements.iter().all(|announcement| announcement.fits()));
+        assert_eq!(
+            everything.topics,
+            announcements
+                .into_iter()
+                .flat_map(|announcement| announcement.topics)
+                .collect::<Vec<TopicInfo>>()
+        );
+
+        source.topics.last_announce = None;
+        source.announce_topics();
+        loopback::drain(&mut listener);
+        names.iter().for_each(|name| {
+            assert_eq!(
+                listener.topics.schema(topic_id(name)),
+                Some(&TaskCommunication::schema()),
+                "{name} wasn't learned"
+            )
+        });
+    }
+
+    #[test]
+    pub fn topics_collision() {
+        // these two have the same FNV-1a hash
+        assert_eq!(topic_id("glbvs"), topic_id("yacxa"));
+
+        let mut topics = TopicRegistry::new(&SYSTEM_NAMES);
+        assert!(topics.register("glbvs").is_ok());
+        assert!(topics.register("glbvs").is_ok());
+
+        let collision = topics.register("yacxa").unwrap_err();
+        assert_eq!(collision.existing, "glbvs");
+
+        let net = loopback::network(LoopbackConfig::ideal());
+        let mut source = Sock::loopback("yacxa", vec![], &net);
+        let mut sinc = Sock::loopback("sinc", vec!["glbvs"], &net);
+
+        source.tx_payload(1.0f64);
+        assert_eq!(loopback::drain(&mut sinc), Vec::<usize>::new());
+        assert_eq!(sinc.topics.collisions.len(), 1, "collision not detected");
+
+        source.tx_any_payload("glbvs", &1.0f64, 0);
+        assert_eq!(loopback::drain(&mut sinc), Vec::<usize>::new());
+
+        let result = std::panic::catch_unwind(|| {
+            Sock::loopback("sinc", vec!["glbvs", "yacxa"], &net);
+        });
+        assert!(result.is_err(), "colliding targets were allowed");
+    }
+}
+
+#[cfg(test)]
+pub mod schema {
+    use super::*;
+
+    #[test]
+    pub fn schema_decode() {
+        let packet = TaskCommunication {
+            name: "sin".to_string(),
+            latch: 1,
+            rate: 100.0,
+            pc_time: 1.5,
+            mcu_time: 1.25,
+            run_time: 0.001,
+            data: vec![0.5, -0.5],
+        };
+
+        let value = TaskCommunication::schema()
+            .decode(&bincode::serialize(&packet).unwrap())
+            .unwrap();
+
+        assert_eq!(value.get("name"), Some(&Value::String("sin".to_string())));
+        assert_eq!(value.get("latch"), Some(&Value::UInt(1)));
+        assert_eq!(
+            value.to_string(),
+            "{\"name\": \"sin\", \"latch\": 1, \"rate\": 100.0, \"pc_time\": 1.5, \"mcu_time\": 1.25, \"run_time\": 0.001, \"data\": [0.5, -0.5]}"
+        );
+
+        let marshall = TaskMarshall {
+            name: "pid".to_string(),
+            data: vec![],
+            mode: TaskMarshallType::Parameter,
+        };
+        let value = TaskMarshall::schema()
+            .decode(&bincode::serialize(&marshall).unwrap())
+            .unwrap();
+        assert_eq!(
+            value.get("mode"),
+            Some(&Value::String("Parameter".to_string()))
+        );
+
+        let value = Schema::of::<(Option<u8>, [i16; 2], bool)>()
+            .decode(&bincode::serialize(&(Some(7u8), [-1i16, 2], true)).unwrap())
+            .unwrap();
+        assert_eq!(value.to_string(), "[7, [-1, 2], true]");
+    }
+
+    #[test]
+    pub fn schema_rejects() {
+        let bytes = bincode::serialize(&vec![1.0f64, 2.0]).unwrap();
+
+        assert!(Schema::of::<Vec<f64>>().decode(&bytes).is_ok());
+        assert!(Schema::of::<Vec<f64>>().decode(&bytes[..20]).is_err());
+        assert!(Schema::of::<f64>().decode(&bytes).is_err(), "extra bytes");
+        assert!(Schema::of::<String>().decode(&[255; 8]).is_err());
+        assert!(TaskMarshallType::schema()
+            .decode(&7u32.to_le_bytes())
+            .is_err());
+    }
+
+    #[test]
+    pub fn schema_discovery() {
+        let net = loopback::network(LoopbackConfig::ideal());
+
+        let mut source = Sock::loopback("fw/sin", vec![], &net);
+        let mut plain = Sock::loopback("plain", vec![], &net);
+        let mut sinc = Sock::loopback("inspect", vec!["fw/sin", "plain"], &net);
+
+        source.advertise_schema::<Vec<f64>>("fw/sin");
+        source.tx_payload(vec![1.0f64, 2.0]);
+        plain.tx_payload(3.0f64);
+
+        assert_eq!(loopback::drain(&mut sinc), vec![0, 1]);
+        assert_eq!(
+            sinc.decode(0),
+            Some(Ok(Value::Array(vec![Value::Float(1.0), Value::Float(2.0)])))
+        );
+        assert_eq!(sinc.decode(1), None, "plain never advertised a schema");
+    }
+}
+
+#[cfg(test)]
+pub mod codec {
+    use super::*;
+
+    #[test]
+    pub fn codec_round_trip() {
+        let marshall = TaskMarshall {
+            name: "pid".to_string(),
+            data: vec![0.5, -1.0],
+            mode: TaskMarshallType::Parameter,
+        };
+
+        [CodecId::Bincode, CodecId::Json, CodecId::MessagePack]
+            .into_iter()
+            .for_each(|codec| {
+                let bytes = codec.encode(&marshall).unwrap();
+                assert_eq!(codec.decode::<TaskMarshall>(&bytes).unwrap(), marshall);
+                assert_eq!(CodecId::from_byte(codec.byte()), Some(codec));
+                assert_eq!(CodecId::from_name(&codec.to_string()), Some(codec));
+            });
+
+        assert_eq!(
+            Json::encode(&marshall).unwrap(),
+            b"{\"name\":\"pid\",\"data\":[0.5,-1.0],\"mode\":\"Parameter\"}"
+        );
+        assert!(Json::decode::<TaskMarshall>(b"{\"name\":\"pid\"}").is_err());
+        assert_eq!(CodecId::from_byte(9), None);
+    }
+
+    #[test]
+    pub fn codec_auto_decode() {
+        let net = loopback::network(LoopbackConfig::ideal());
+
+        let mut json = Sock::loopback("json", vec![], &net);
+        let mut msgpack = Sock::loopback("msgpack", vec![], &net);
+        let mut sinc = Sock::loopback("sinc", vec!["json", "msgpack"], &net);
+
+        json.set_codec(CodecId::Json);
+        msgpack.set_topic_codec("msgpack", CodecId::MessagePack);
+        json.tx_payload(vec![1.0f64, 2.0]);
+        msgpack.tx_payload(vec![3.0f64]);
+
+        assert_eq!(loopback::drain(&mut sinc), vec![0, 1]);
+        assert_eq!(sinc.messages[0].stamp.codec, CodecId::Json);
+        assert_eq!(sinc.messages[1].stamp.codec, CodecId::MessagePack);
+        assert_eq!(sinc.decode_message::<Vec<f64>>(0), Ok(vec![1.0, 2.0]));
+        assert_eq!(sinc.decode_message::<Vec<f64>>(1), Ok(vec![3.0]));
+        assert!(sinc.decode_message::<String>(0).is_err());
+    }
+
+    #[test]
+    pub fn codec_task_inputs() {
+        let net = loopback::network(LoopbackConfig::ideal());
+
+        let mut json = Sock::loopback("json", vec![], &net);
+        let mut relay = Sock::loopback("relay", vec![], &net);
+        let mut sinc = Sock::loopback("sinc", vec!["sum"], &net);
+        relay.set_codec(CodecId::MessagePack);
+        add_task!(relay, vec!["json"], "sum", 0, |_ctx: u8, data: Vec<f64>| {
+            data[0].iter().sum::<f64>()
+        });
+
+        json.set_codec(CodecId::Json);
+        net.clock().advance(1000);
+        json.tx_payload(vec![1.0f64, 2.0, 3.5]);
+        loopback::drain(&mut relay)
+            .into_iter()
+            .for_each(|idx| relay.try_all_tasks(idx));
+
+        // task outputs are bincode bytes, sent with the sock's codec
+        assert_eq!(loopback::drain(&mut sinc), vec![0]);
+        assert_eq!(sinc.messages[0].stamp.codec, CodecId::MessagePack);
+        let bytes = sinc.decode_message::<Vec<u8>>(0).unwrap();
+        assert_eq!(bincode::deserialize::<f64>(&bytes).unwrap(), 6.5);
+    }
+
+    #[test]
+    pub fn codec_inspect() {
+        let net = loopback::network(LoopbackConfig::ideal());
+
+        let mut source = Sock::loopback("fw/pid", vec![], &net);
+        let mut sinc = Sock::loopback("inspect", vec!["fw/pid"], &net);
+
+        source.set_codec(CodecId::MessagePack);
+        source.advertise_schema::<TaskMarshall>("fw/pid");
+        source.tx_payload(TaskMarshall {
+            name: "pid".to_string(),
+            data: vec![0.5],
+            mode: TaskMarshallType::Parameter,
+        });
+
+        assert_eq!(loopback::drain(&mut sinc), vec![0]);
+        assert_eq!(
+            sinc.decode(0).unwrap().unwrap().to_string(),
+            "{\"name\": \"pid\", \"data\": [0.5], \"mode\": \"Parameter\"}"
+        );
+
+        // json without a schema decodes as is (object keys come out sorted)
+        let mut plain = Sock::loopback("plain", vec![], &net);
+        let mut sinc = Sock::loopback("inspect2", vec!["plain"], &net);
+        plain.set_codec(CodecId::Json);
+        plain.tx_payload((1u8, "two".to_string()));
+
+        assert_eq!(loopback::drain(&mut sinc), vec![0]);
+        assert_eq!(sinc.decode(0).unwrap().unwrap().to_string(), "[1, \"two\"]");
+    }
+}
+
+#[cfg(test)]
+pub mod throttle {
+    use super::*;
+
+    #[test]
+    pub fn throttle_rate() {
+        let mut throttle = TopicThrottle::new(Throttle::Rate(50.0));
+
+        // 1kHz with jitter for a second
+        (0..1000u64).for_each(|i| {
+            let jitter = [0, 300, 700, 100][i as usize % 4];
+            throttle.admit(1000 * i + jitter);
+        });
+
+        assert_eq!(throttle.offered, 1000);
+        assert_eq!(throttle.sent, 50);
+        assert_eq!(throttle.dropped, 950);
+
+        // a pause doesn't save up a burst
+        assert!(throttle.admit(5_000_000));
+        assert!(!throttle.admit(5_001_000));
+        assert!(throttle.admit(5_020_000));
+    }
+
+    #[test]
+    pub fn throttle_decimate() {
+        let mut throttle = TopicThrottle::new(Throttle::Decimate(10));
+        let sent: Vec<u64> = (0..35).filter(|&i| throttle.admit(i)).collect();
+
+        assert_eq!(sent, vec![0, 10, 20, 30]);
+        assert_eq!(throttle.dropped, 31);
+        assert_eq!(throttle.to_string(), "1/10 sent 4/35 (31 dropped)");
+        assert_eq!(Throttle::Decimate(10).period(1000), 10000);
+        assert_eq!(Throttle::Rate(50.0).period(1000), 20000);
+        assert_eq!(Throttle::Rate(5000.0).period(1000), 1000);
+    }
+
+    #[test]
+    pub fn throttle_yaml() {
+        let byu = BuffYamlUtil::new(
+            "viz: 50\nplot: 12.5\nlog: {decimate: 10}\nfast: {rate: 200.0}\nzero: 0\nnone: {decimate: 0}\nbad: fast",
+        );
+        let data = byu.data();
+
+        assert_eq!(
+            Throttle::from_yaml(&byu, "viz", data).unwrap(),
+            Throttle::Rate(50.0)
+        );
+        assert_eq!(
+            Throttle::from_yaml(&byu, "plot", data).unwrap(),
+            Throttle::Rate(12.5)
+        );
+        assert_eq!(
+            Throttle::from_yaml(&byu, "log", data).unwrap(),
+            Throttle::Decimate(10)
+        );
+        assert_eq!(
+            Throttle::from_yaml(&byu, "fast", data).unwrap(),
+            Throttle::Rate(200.0)
+        );
+        assert!(Throttle::from_yaml(&byu, "zero", data).is_err());
+        assert!(Throttle::from_yaml(&byu, "none", data).is_err());
+        assert!(Throttle::from_yaml(&byu, "bad", data).is_err());
+        assert!(Throttle::from_yaml(&byu, "missing", data).is_err());
+    }
+
+    #[test]
+    pub fn throttle_republish() {
+        let net = loopback::network(LoopbackConfig::ideal());
+        let clock = net.clock();
+
+        let mut source = Sock::loopback("fw", vec![], &net);
+        let mut logger = Sock::loopback("logger", vec!["signal"], &net);
+        let mut viz = Sock::loopback("viz", vec!["signal/viz"], &net);
+        source.set_throttle("signal/viz", Throttle::Rate(50.0));
+
+        let (mut logged, mut plotted) = (0, 0);
+        (0..1000).for_each(|i| {
+            clock.advance(1000);
+            source.tx_any_payload("signal", &(i as f64), 1000);
+            source.tx_any_payload("signal/viz", &(i as f64), 20000);
+            logged += loopback::drain(&mut logger).len();
+            plotted += loopback::drain(&mut viz).len();
+        });
+
+        assert_eq!(logged, 1000, "full rate copy was throttled");
+        assert_eq!(plotted, 50);
+        assert_eq!(source.dropped("signal/viz"), 950);
+        assert_eq!(source.dropped("signal"), 0);
+    }
+}
+
+#[cfg(test)]
+pub mod params {
+    use super::*;
+
+    #[test]
+    pub fn params_declare() {
+        let mut sock = Sock::loopback(
+            "controller",
+            vec![],
+            &loopback::network(LoopbackConfig::ideal()),
+        );
+
+        sock.declare_param(Param::new("gain", 1.0.into()).bounded(0.0, 10.0))
+            .unwrap();
+        sock.declare_param(Param::new("mode", "fast".into()))
+            .unwrap();
+        sock.declare_param(Param::new("weights", vec![0.5, 0.5].into()).bounded(0.0, 1.0))
+            .unwrap();
+        sock.declare_param(Param::new("pair", vec![0.0, 1.0].into()).sized(2, 2))
+            .unwrap();
+
+        assert_eq!(sock.param::<f64>("gain"), Some(1.0));
+        assert_eq!(sock.param::<String>("gain"), None, "wrong type");
+        assert_eq!(sock.param::<String>("mode"), Some("fast".to_string()));
+
+        sock.set_param("gain", ParamValue::Int(3)).unwrap();
+        assert_eq!(sock.param::<f64>("gain"), Some(3.0), "ints are floats");
+        assert_eq!(
+            sock.set_param("gain", ParamValue::Float(11.0)),
+            Err(ParamError::Bounds {
+                name: "gain".to_string(),
+                min: 0.0,
+                max: 10.0
+            })
+        );
+        assert!(sock.set_param("weights", vec![0.5, 2.0].into()).is_err());
+        assert_eq!(
+            sock.set_param("pair", 1.0.into()),
+            Err(ParamError::Length {
+                name: "pair".to_string(),
+                min: 2,
+                max: 2
+            })
+        );
+        assert!(sock.set_param("mode", true.into()).is_err());
+        assert_eq!(
+            sock.set_param("missing", 1.0.into()),
+            Err(ParamError::Unknown("missing".to_string()))
+        );
+        assert_eq!(
+            sock.param::<f64>("gain"),
+            Some(3.0),
+            "bad sets change nothing"
+        );
+        assert_eq!(sock.param_changes(), vec!["gain".to_string()]);
+        assert!(sock.param_changes().is_empty());
+
+        assert!(sock.declare_param(Param::new("gain", 2.0.into())).is_err());
+        assert!(sock
+            .declare_param(Param::new("out", 20.0.into()).bounded(0.0, 10.0))
+            .is_err());
+        assert_eq!(
+            sock.declare_param(Param::new("huge", vec![0.0; 100].into())),
+            Err(ParamError::Size("huge".to_string()))
+        );
+
+        assert_eq!(
+            ParamValue::parse("[1, 2.5]", &vec![0.0].into()),
+            Some(ParamValue::Floats(vec![1.0, 2.5]))
+        );
+        assert_eq!(ParamValue::parse("x", &1i64.into()), None);
+    }
+
+    #[test]
+    pub fn params_service() {
+        let net = loopback::network(LoopbackConfig::ideal());
+
+        let mut node = Sock::loopback("controller", vec![], &net);
+        let mut client = Sock::loopback("param", vec![], &net);
+        let mut watcher = Sock::loopback("watcher", vec![], &net);
+        node.declare_param(
+            Param::new("gain", 1.0.into())
+                .bounded(0.0, 10.0)
+                .describe("p gain"),
+        )
+        .unwrap();
+        node.declare_param(Param::new("enabled", true.into()))
+            .unwrap();
+
+        let call = |client: &mut Sock, node: &mut Sock, op: ParamOp| {
+            let id = client.request_param("controller", op);
+            loopback::drain(node);
+            loopback::drain(client);
+            client.params.take_reply(id)
+        };
+
+        let params = call(&mut client, &mut node, ParamOp::List)
+            .unwrap()
+            .unwrap();
+        assert_eq!(params.len(), 2);
+        assert_eq!(
+            params[0].to_string(),
+            "gain: 1.0 (float in [0, 10], default 1.0) p gain"
+        );
+
+        let params = call(
+            &mut client,
+            &mut node,
+            ParamOp::Set("gain".to_string(), 2.5.into()),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(params[0].value, ParamValue::Float(2.5));
+        assert_eq!(node.param::<f64>("gain"), Some(2.5));
+
+        loopback::drain(&mut watcher);
+        assert_eq!(watcher.params.events.len(), 1);
+        assert_eq!(
+            watcher.params.events[0].to_string(),
+            "[controller] gain = 2.5"
+        );
+
+        assert_eq!(
+            call(
+                &mut client,
+                &mut node,
+                ParamOp::Set("gain".to_string(), 20.0.into())
+            ),
+            Some(Err(ParamError::Bounds {
+                name: "gain".to_string(),
+                min: 0.0,
+                max: 10.0
+            }))
+        );
+        assert_eq!(
+            call(&mut client, &mut node, ParamOp::Get("nope".to_string())),
+            Some(Err(ParamError::Unknown("nope".to_string())))
+        );
+
+        let id = client.request_param("someone_else", ParamOp::List);
+        loopback::drain(&mut node);
+        loopback::drain(&mut client);
+        assert_eq!(
+            client.params.take_reply(id),
+            None,
+            "only the named node answers"
+        );
+    }
+
+    #[test]
+    pub fn params_split_replies() {
+        let request = ParamRequest {
+            client: "param".to_string(),
+            id: 7,
+            node: "controller".to_string(),
+            op: ParamOp::List,
+        };
+        let params: Vec<Param> = (0..40)
+            .map(|i| Param::new(&format!("weight{i}"), vec![0.0; 4].into()))
+            .collect();
+
+        let replies = ParamServer::replies(&request, Ok(params.clone()));
+        assert!(replies.len() > 1);
+        assert!(replies
+            .iter()
+            .all(|reply| reply.parts as usize == replies.len()));
+        replies.iter().for_each(|reply| {
+            assert_le!(
+                bincode::serialized_size(reply).unwrap() as usize,
+                MAX_FRAGMENT_SIZE
+            )
+        });
+
+        // out of order and missing one part
+        let mut server = ParamServer::new();
+        replies
+            .iter()
+            .skip(1)
+            .rev()
+            .for_each(|reply| server.receive(reply.clone()));
+        assert_eq!(server.take_reply(7), None);
+        server.receive(replies[0].clone());
+        assert_eq!(server.take_reply(7), Some(Ok(params)));
+        assert!(server.replies.is_empty());
+    }
+
+    #[test]
+    pub fn params_yaml() {
+        let mut server = ParamServer::new();
+        server.declare(Param::new("gain", 1.0.into())).unwrap();
+        server.declare(Param::new("steps", 3i64.into())).unwrap();
+        server.declare(Param::new("name", "pid".into())).unwrap();
+        server
+            .declare(Param::new("weights", vec![0.5, 1.0].into()))
+            .unwrap();
+        server.set("gain", 2.0.into()).unwrap();
+
+        let dump = server.to_byu().dump();
+        assert_eq!(
+            dump,
+            "---\ngain: 2.0\nsteps: 3\nname: pid\nweights:\n  - 0.5\n  - 1.0\n"
+        );
+
+        let mut loaded = ParamServer::new();
+        loaded.declare(Param::new("gain", 1.0.into())).unwrap();
+        loaded
+            .declare(Param::new("weights", vec![0.0].into()))
+            .unwrap();
+        loaded.declare(Param::new("steps", 0i64.into())).unwrap();
+        assert!(loaded.load(&BuffYamlUtil::new(&dump)).is_empty());
+        assert_eq!(loaded.get("gain"), Some(&ParamValue::Float(2.0)));
+        assert_eq!(
+            loaded.get("weights"),
+            Some(&ParamValue::Floats(vec![0.5, 1.0]))
+        );
+
+        let errors = loaded.load(&BuffYamlUtil::new("steps: many\nweights: [1, 2]"));
+        assert_eq!(
+            errors,
+            vec![ParamError::Type {
+                name: "steps".to_string(),
+                expected: "int".to_string()
+            }]
+        );
+        assert_eq!(
+            loaded.get("weights"),
+            Some(&ParamValue::Floats(vec![1.0, 2.0]))
+        );
+    }
+}
+
+#[cfg(test)]
+pub mod diagnostics {
+    use super::*;
+
+    #[test]
+    pub fn diagnostics_aggregate() {
+        let mut aggregator = Aggregator::new(DIAGNOSTICS_STALE_US);
+        let robot_fw = DiagnosticArray {
+            header: Default::default(),
+            status: vec![
+                DiagnosticStatus::ok("robot_fw/signal", "running"),
+                DiagnosticStatus::warn("robot_fw/hid", "over cycled").with("over cycles", 3),
+            ],
+        };
+        let camera = DiagnosticArray {
+            header: Default::default(),
+            status: vec![DiagnosticStatus::ok("camera/driver", "streaming")],
+        };
+
+        aggregator.hear(robot_fw, 0);
+        aggregator.hear(camera, 4_000_000);
+
+        let statuses = aggregator.statuses(5_000_000);
+        let names: Vec<&str> = statuses.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "camera",
+                "camera/driver",
+                "robot_fw",
+                "robot_fw/hid",
+                "robot_fw/signal"
+            ]
+        );
+        assert_eq!(statuses[0].level, DiagnosticLevel::Ok);
+        assert_eq!(statuses[2].level, DiagnosticLevel::Warn);
+        assert_eq!(statuses[2].message, "hid: over cycled");
+        assert_eq!(statuses[2].get("warn"), Some("1"));
+        assert_eq!(aggregator.level(5_000_000), DiagnosticLevel::Warn);
+
+        // robot_fw goes quiet
+        let statuses = aggregator.statuses(6_000_000);
+        assert_eq!(statuses[2].level, DiagnosticLevel::Stale);
+        assert_eq!(statuses[3].level, DiagnosticLevel::Stale);
+        assert_eq!(
+            statuses[3].get("over cycles"),
+            Some("3"),
+            "keeps its details"
+        );
+        assert_eq!(statuses[0].level, DiagnosticLevel::Ok);
+
+        assert_eq!(
+            diagnostics_tree(&aggregator.statuses(5_000_000)[..2]),
+            "[OK] camera: all ok (ok: 1, warn: 0, error: 0, stale: 0)\n  [OK] driver: streaming"
+        );
+    }
+
+    #[test]
+    pub fn diagnostics_updates() {
+        let mut diagnostics = Diagnostics::new();
+        assert!(diagnostics.due("node", 0).is_none(), "nothing to send");
+
+        diagnostics.update(DiagnosticStatus::ok("node/a", "fine"));
+        assert_eq!(diagnostics.due("node", 0).unwrap().status.len(), 1);
+
+        // same level isn't news, a new level is
+        diagnostics.update(DiagnosticStatus::ok("node/a", "still fine"));
+        assert!(diagnostics.due("node", 1000).is_none());
+        diagnostics.update(DiagnosticStatus::error("node/a", "broken"));
+        let array = diagnostics.due("node", 2000).unwrap();
+        assert_eq!(array.level(), DiagnosticLevel::Error);
+        assert_eq!(array.header.seq, 2);
+
+        // and everything gets resent once a period
+        assert!(diagnostics
+            .due("node", 2000 + DIAGNOSTICS_PERIOD_US - 1)
+            .is_none());
+        assert!(diagnostics
+            .due("node", 2000 + DIAGNOSTICS_PERIOD_US)
+            .is_some());
+    }
+
+    #[test]
+    pub fn diagnostics_loopback() {
+        let net = loopback::network(LoopbackConfig::ideal());
+
+        let mut node = Sock::loopback("robot_fw", vec![], &net);
+        let mut agg = Sock::loopback(DIAGNOSTICS_AGG_TOPIC, vec![DIAGNOSTICS_TOPIC], &net);
+        let mut viewer = Sock::loopback("diag", vec![DIAGNOSTICS_AGG_TOPIC], &net);
+        let mut aggregator = Aggregator::new(DIAGNOSTICS_STALE_US);
+
+        node.diagnose(DiagnosticStatus::ok("signal", "running"));
+        node.diagnose(DiagnosticStatus::error("hid", "mcu disconnected"));
+        assert_eq!(
+            node.diagnostics.status("robot_fw/hid").unwrap().hardware_id,
+            "robot_fw"
+        );
+
+        while agg.socket.peek_from(&mut [0; 1]).is_ok() {
+            aggregator.spin_once(&mut agg);
+        }
+        net.clock().advance(DIAGNOSTICS_PERIOD_US as u64);
+        let summary = aggregator.spin_once(&mut agg).unwrap();
+
+        assert_eq!(summary.level(), DiagnosticLevel::Error);
+        assert_eq!(summary.status[0].name, "robot_fw");
+        assert_eq!(summary.status[0].message, "hid: mcu disconnected");
+
+        let heard: Vec<DiagnosticArray> = loopback::drain(&mut viewer)
+            .into_iter()
+            .map(|i| viewer.decode_message(i).unwrap())
+            .collect();
+        assert_eq!(heard.last(), Some(&summary));
+    }
+}
+
+#[cfg(test)]
+pub mod logging {
+    use super::*;
+    use log::{Level, LevelFilter};
+
+    #[test]
+    pub fn logging_filter() {
+        let filter =
+            LogFilter::parse("warn, dyse_rust::rid=debug,dyse_rust::rid::layer=off,launch")
+                .unwrap();
+
+        assert_eq!(filter.level("dyse_rust::socks::socks"), LevelFilter::Warn);
+        assert_eq!(filter.level("dyse_rust::rid"), LevelFilter::Debug);
+        assert_eq!(
+            filter.level("dyse_rust::rid::interface"),
+            LevelFilter::Debug
+        );
+        assert_eq!(filter.level("dyse_rust::rid::layer"), LevelFilter::Off);
+        assert_eq!(
+            filter.level("dyse_rust::ridx"),
+            LevelFilter::Warn,
+            "whole modules only"
+        );
+        assert_eq!(filter.level("launch::launcher"), LevelFilter::Trace);
+        assert_eq!(filter.max(), LevelFilter::Trace);
+
+        assert!(filter.enabled("dyse_rust::rid::reader", Level::Debug));
+        assert!(!filter.enabled("dyse_rust::socks", Level::Info));
+        assert_eq!(
+            LogFilter::parse("").unwrap(),
+            LogFilter::new(LevelFilter::Info)
+        );
+        assert!(LogFilter::parse("dyse_rust=loud").is_none());
+    }
+
+    #[test]
+    pub fn logging_forward() {
+        let logger =
+            init(LogFilter::new(LevelFilter::Warn).module("logging_forward", LevelFilter::Info));
+        let net = loopback::network(LoopbackConfig::ideal());
+        let mut node = Sock::loopback("robot_fw", vec![], &net);
+        let mut collector = Sock::loopback("logger", vec![LOG_TOPIC], &net);
+
+        node.forward_logs();
+        log::info!(target: "logging_forward", "calibrated");
+        log::debug!(target: "logging_forward", "too quiet");
+        log::info!(target: "logging_forward::imu", "imu ok");
+        log::info!(target: "elsewhere", "filtered");
+        node.publish_logs();
+        logger.forward(false);
+
+        // a sinc only keeps the latest message, decode each one as it arrives
+        let mut records: Vec<LogRecord> = vec![];
+        let mut buffer = [0u8; UDP_PACKET_SIZE];
+        while collector.socket.peek_from(&mut [0; 1]).is_ok() {
+            if let Some(i) = collector.try_rx(&mut buffer) {
+                records.push(collector.decode_message(i).unwrap());
+            }
+        }
+        records.retain(|record| record.target.starts_with("logging_forward"));
+        let messages: Vec<&str> = records.iter().map(|r| r.message.as_str()).collect();
+
+        assert_eq!(messages, vec!["calibrated", "imu ok"]);
+        assert_eq!(records[0].node, "robot_fw");
+        assert_eq!(records[0].level, LogLevel::Info);
+        assert!(records[0].file.ends_with("sock_tests.rs"));
+
+        let path = env::temp_dir()
+            .join(format!("logging_forward_{}.log", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let mut file = LogFile::open(&path, LogLevel::Warn).unwrap();
+        let warning = LogRecord {
+            level: LogLevel::Warn,
+            ..records[0].clone()
+        };
+        assert!(!file.write(&records[0]).unwrap(), "info is below warn");
+        assert!(file.write(&warning).unwrap());
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.ends_with("[WARN][robot_fw] logging_forward: calibrated\n"));
+        assert_eq!(text.lines().count(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -12,8 +12,26 @@
  ********************************************************************************/
 // use std::thread::{Builder, JoinHandle};
 
-use crate::socks::{message::UdpPayload, socks::*};
-use std::{fmt::Debug, time::Instant};
+use crate::msgs::{
+    diagnostics::DiagnosticArray,
+    firmware::{TaskMarshall, TaskMarshallType, FIRMWARE_CTRL_NAME},
+    logging::*,
+};
+use crate::socks::{
+    clocks::{Clock, CLOCK_TOPIC},
+    codec::CodecId,
+    diagnostics::*,
+    logging::*,
+    message::{MessageStamp, UdpPayload, UDP_PACKET_SIZE},
+    params::*,
+    socks::*,
+    time_sync::TimeSync,
+};
+use std::{
+    fmt::Debug,
+    thread,
+    time::{Duration, Instant},
+};
 
 #[macro_export]
 macro_rules! sync {
@@ -23,6 +41,9 @@ macro_rules! sync {
     ($name:expr, $target_names:expr, $task_name:expr, $default_context:expr,  |$context:ident: $U:ty, $t:ident, $($target:ident: $T:ty),+| $body:expr) => (
         Sock::synced($name, $target_names, $task_name, $default_context, build_fn!(|$context: $U, $t, $($target: $T),+| $body))
     );
+    ($name:expr, $target_names:expr, $task_name:expr, $default_context:expr,  |$context:ident: $U:ty, $t:ident, $s:ident, $($target:ident: $T:ty),+| $body:expr) => (
+        Sock::synced($name, $target_names, $task_name, $default_context, build_fn!(|$context: $U, $t, $s, $($target: $T),+| $body))
+    );
 }
 
 #[macro_export]
@@ -33,6 +54,9 @@ macro_rules! unsync {
     ($name:expr, $target_names:expr, $task_name:expr, $default_context:expr,  |$context:ident: $U:ty, $t:ident, $($target:ident: $T:ty),+| $body:expr) => (
         Sock::unsynced($name, $target_names, $task_name, $default_context, build_fn!(|$context: $U, $t, $($target: $T),+| $body))
     );
+    ($name:expr, $target_names:expr, $task_name:expr, $default_context:expr,  |$context:ident: $U:ty, $t:ident, $s:ident, $($target:ident: $T:ty),+| $body:expr) => (
+        Sock::unsynced($name, $target_names, $task_name, $default_context, build_fn!(|$context: $U, $t, $s, $($target: $T),+| $body))
+    );
 }
 
 #[macro_export]
@@ -44,6 +68,10 @@ macro_rules! add_task {
     ($sock:expr, $target_name:expr, $task_name:expr, $default_context:expr,  |$context:ident: $U:ty, $t:ident, $($target:ident: $T:ty),+| $body:expr) => (
         $sock.link_task($task_name, $target_name, $default_context, build_fn!(|$context: $U, $t, $($target: $T),+| $body));
     );
+
+    ($sock:expr, $target_name:expr, $task_name:expr, $default_context:expr,  |$context:ident: $U:ty, $t:ident, $s:ident, $($target:ident: $T:ty),+| $body:expr) => (
+        $sock.link_task($task_name, $target_name, $default_context, build_fn!(|$context: $U, $t, $s, $($target: $T),+| $body));
+    );
 }
 
 ///
@@ -70,6 +98,39 @@ pub fn shutdown() {
     sock.tx_payload(0);
 }
 
+/// Run the reference clock that other socks synchronize to
+pub fn clock_reference() {
+    let mut sock = Sock::source("clock");
+    sock.time_sync = TimeSync::reference();
+    sock.spin();
+    sock.log_heavy("");
+}
+
+/// Publish simulated time on the clock topic, socks running a sim
+/// clock will follow it. `rate` is how often (Hz, real time) the clock
+/// is published and `speed` is how many sim seconds pass per real second.
+pub fn sim_clock(rate: f64, speed: f64) {
+    let mut sock = Sock::source("sim_clock");
+    sock.set_clock(Clock::sim());
+
+    let real_step = Duration::from_micros((1E6 / rate) as u64);
+    let sim_step = (speed * 1E6 / rate) as u64;
+
+    while !*sock.shutdown.read().unwrap() {
+        let t = Instant::now();
+        let mut buffer = [0u8; UDP_PACKET_SIZE];
+
+        sock.clock.advance(sim_step);
+        let micros = sock.clock.micros();
+        sock.tx_any_payload(CLOCK_TOPIC, &micros, sim_step);
+        sock.try_rx(&mut buffer);
+
+        thread::sleep(real_step.saturating_sub(t.elapsed()));
+    }
+
+    sock.log_heavy(sock.clock.secs());
+}
+
 pub fn sync_echo<T: PartialEq + Debug + for<'a> serde::de::Deserialize<'a>>(
     name: &str,
     targets: Vec<&str>,
@@ -79,11 +140,13 @@ pub fn sync_echo<T: PartialEq + Debug + for<'a> serde::de::Deserialize<'a>>(
         targets,
         name,
         0usize,
-        |data: Vec<UdpPayload>, ctx: &mut UdpPayload, t: f64| {
+        |data: Vec<UdpPayload>, ctx: &mut UdpPayload, t: f64, stamps: &[MessageStamp]| {
             let payloads: Vec<T> = data
                 .iter()
-                .map(|task_in| {
-                    bincode::deserialize::<T>(&task_in)
+                .enumerate()
+                .map(|(i, task_in)| {
+                    CodecId::of_input(stamps, i)
+                        .decode::<T>(task_in)
                         .expect("Failed to deserialize input (sync_echo)")
                 })
                 .collect();
@@ -91,7 +154,8 @@ pub fn sync_echo<T: PartialEq + Debug + for<'a> serde::de::Deserialize<'a>>(
                 .expect("Failed to deserialize context (sync_echo)");
             context += 1;
             *ctx = bincode::serialize(&context).expect("Failed to serialize context (sync_echo)");
-            println!("[{t:.6}] {payloads:?}");
+            let latency: Vec<f64> = stamps.iter().map(|stamp| stamp.latency()).collect();
+            println!("[{t:.6}] {payloads:?} (latency {latency:.6?}s)");
             (0, vec![])
         },
     );
@@ -109,14 +173,18 @@ pub fn echo<T: PartialEq + Debug + for<'a> serde::de::Deserialize<'a>>(
         targets,
         name,
         0,
-        |data: Vec<UdpPayload>, _ctx: &mut UdpPayload, t: f64| {
+        |data: Vec<UdpPayload>, _ctx: &mut UdpPayload, t: f64, stamps: &[MessageStamp]| {
             let payloads: Vec<T> = data
                 .iter()
-                .map(|task_in| {
-                    bincode::deserialize::<T>(&task_in).expect("Failed to deserialize input (echo)")
+                .enumerate()
+                .map(|(i, task_in)| {
+                    CodecId::of_input(stamps, i)
+                        .decode::<T>(task_in)
+                        .expect("Failed to deserialize input (echo)")
                 })
                 .collect();
-            println!("[{t:.6}] {payloads:?}");
+            let latency: Vec<f64> = stamps.iter().map(|stamp| stamp.latency()).collect();
+            println!("[{t:.6}] {payloads:?} (latency {latency:.6?}s)");
             (0, vec![])
         },
     );
@@ -125,6 +193,32 @@ pub fn echo<T: PartialEq + Debug + for<'a> serde::de::Deserialize<'a>>(
     sock.log_heavy(sock.tasks[0].get_context::<usize>());
 }
 
+/// Print any topic without knowing its type, messages are decoded with the
+/// schema their publisher announced (raw bytes are printed until one arrives)
+pub fn inspect(targets: Vec<&str>) {
+    let mut sock = Sock::sinc("inspect", targets);
+    let mut buffer = [0u8; UDP_PACKET_SIZE];
+
+    while !*sock.shutdown.read().unwrap() {
+        if let Some(i) = sock.try_rx(&mut buffer) {
+            let latency = sock.messages[i].stamp.latency();
+            match sock.decode(i) {
+                Some(Ok(value)) => {
+                    println!("[{}] {value} (latency {latency:.6}s)", sock.targets[i])
+                }
+                Some(Err(e)) => println!("[{}] bad message: {e}", sock.targets[i]),
+                None => println!(
+                    "[{}] {:?} (no schema)",
+                    sock.targets[i],
+                    sock.messages[i].to_payload()
+                ),
+            }
+        }
+    }
+
+    sock.log_heavy("");
+}
+
 pub fn hz<T: PartialEq + Debug + for<'a> serde::de::Deserialize<'a>>(
     name: &str,
     targets: Vec<&str>,
@@ -134,7 +228,7 @@ pub fn hz<T: PartialEq + Debug + for<'a> serde::de::Deserialize<'a>>(
         targets,
         name,
         0.0f64,
-        |_data: Vec<UdpPayload>, ctx: &mut UdpPayload, t: f64| {
+        |_data: Vec<UdpPayload>, ctx: &mut UdpPayload, t: f64, _stamps: &[MessageStamp]| {
             let t1: f64 = bincode::deserialize(ctx).expect("Failed to deserialze context (hz)");
             *ctx = bincode::serialize(&t).unwrap();
             println!("[{t:.6}] {:.4}", 1.0 / (t - t1));
@@ -155,7 +249,7 @@ pub fn plot<T: PartialEq + Debug + for<'a> serde::de::Deserialize<'a>>(
         targets,
         name,
         0.0f64,
-        |_data: Vec<UdpPayload>, ctx: &mut UdpPayload, t: f64| {
+        |_data: Vec<UdpPayload>, ctx: &mut UdpPayload, t: f64, _stamps: &[MessageStamp]| {
             let t1: f64 = bincode::deserialize(ctx).expect("Failed to deserialze context (hz)");
             *ctx = bincode::serialize(&t).unwrap();
             println!("[{t:.6}] {:.4}", 1.0 / (t - t1));
@@ -166,3 +260,184 @@ pub fn plot<T: PartialEq + Debug + for<'a> serde::de::Deserialize<'a>>(
     sock.spin();
     sock.log_heavy(sock.tasks[0].get_context::<usize>());
 }
+
+/// Ask `node` about its parameters and wait (up to [`PARAM_TIMEOUT_MS`]) for the answer
+pub fn param_call(sock: &mut Sock, node: &str, op: ParamOp) -> Result<Vec<Param>, ParamError> {
+    let id = sock.request_param(node, op);
+    let t = Instant::now();
+    let mut buffer = [0u8; UDP_PACKET_SIZE];
+
+    while t.elapsed().as_millis() < PARAM_TIMEOUT_MS {
+        sock.try_rx(&mut buffer);
+        if let Some(answer) = sock.params.take_reply(id) {
+            return answer;
+        }
+    }
+
+    Err(ParamError::Timeout(node.to_string()))
+}
+
+/// Parameter command line client
+/// ```text
+/// param list <node>
+/// param get <node> <name>
+/// param set <node> <name> <value>
+/// param dump <node> [file.yaml]
+/// param save
+/// param watch
+/// ```
+pub fn param(args: Vec<&str>) {
+    let mut sock = Sock::source("param");
+
+    let answer = match args.as_slice() {
+        ["list", node] => param_call(&mut sock, node, ParamOp::List),
+        ["get", node, name] => param_call(&mut sock, node, ParamOp::Get(name.to_string())),
+        ["set", node, name, value] => param_call(&mut sock, node, ParamOp::Get(name.to_string()))
+            .and_then(
+                |params| match ParamValue::parse(value, &params[0].default) {
+                    Some(value) => {
+                        param_call(&mut sock, node, ParamOp::Set(name.to_string(), value))
+                    }
+                    None => Err(ParamError::Type {
+                        name: name.to_string(),
+                        expected: params[0].default.type_name().to_string(),
+                    }),
+                },
+            ),
+        ["dump", node, path @ ..] => param_call(&mut sock, node, ParamOp::List).map(|params| {
+            let byu = ParamServer {
+                params,
+                ..ParamServer::new()
+            }
+            .to_byu();
+            match path.first() {
+                Some(path) => match byu.save(path) {
+                    Ok(_) => println!("[Param]: saved {node} to {path}"),
+                    Err(e) => println!("[Param]: couldn't save {path}, {e}"),
+                },
+                None => print!("{}", byu.dump()),
+            }
+            vec![]
+        }),
+        ["save"] => {
+            // the firmware writes its task parameters to firmware_tasks.yaml
+            let marshall = TaskMarshall {
+                name: FIRMWARE_CTRL_NAME.to_string(),
+                data: vec![],
+                mode: TaskMarshallType::Save,
+            };
+            sock.tx_any_payload(FIRMWARE_CTRL_NAME, &marshall, 0);
+            println!("[Param]: asked robot_fw to save its parameters");
+            Ok(vec![])
+        }
+        ["watch"] => {
+            let mut buffer = [0u8; UDP_PACKET_SIZE];
+            while !*sock.shutdown.read().unwrap() {
+                sock.try_rx(&mut buffer);
+                sock.params
+                    .events
+                    .drain(..)
+                    .for_each(|event| println!("{event}"));
+            }
+            Ok(vec![])
+        }
+        _ => {
+            println!(
+                "[Param]: usage: param list|get|set|dump <node> [name] [value], param save|watch"
+            );
+            Ok(vec![])
+        }
+    };
+
+    match answer {
+        Ok(params) => params.iter().for_each(|param| println!("{param}")),
+        Err(e) => println!("[Param]: {e}"),
+    }
+}
+
+/// Run the diagnostics aggregator, every node's statuses are grouped by
+/// name and the summary is published on diagnostics_agg
+pub fn diagnostics_aggregator() {
+    let mut sock = Sock::sinc(DIAGNOSTICS_AGG_TOPIC, vec![DIAGNOSTICS_TOPIC]);
+    let name = sock.name.clone();
+    sock.advertise_schema::<DiagnosticArray>(&name);
+    let mut aggregator = Aggregator::new(DIAGNOSTICS_STALE_US);
+
+    while !*sock.shutdown.read().unwrap() {
+        sock.announce_topics();
+        aggregator.spin_once(&mut sock);
+    }
+
+    sock.log_heavy(aggregator.level(sock.now()));
+}
+
+/// Diagnostics command line
+/// ```text
+/// diag        print the aggregator's summaries
+/// diag agg    run the aggregator
+/// ```
+pub fn diagnostics(args: Vec<&str>) {
+    match args.as_slice() {
+        ["agg"] => diagnostics_aggregator(),
+        [] => {
+            let mut sock = Sock::sinc("diag", vec![DIAGNOSTICS_AGG_TOPIC]);
+            let mut buffer = [0u8; UDP_PACKET_SIZE];
+
+            while !*sock.shutdown.read().unwrap() {
+                if let Some(i) = sock.try_rx(&mut buffer) {
+                    match sock.decode_message::<DiagnosticArray>(i) {
+                        Ok(summary) => println!(
+                            "===== [{}] {} =====\n{}",
+                            summary.level(),
+                            summary.header.seq,
+                            diagnostics_tree(&summary.status)
+                        ),
+                        Err(e) => println!("[Diag]: bad summary {e}"),
+                    }
+                }
+            }
+        }
+        _ => println!("[Diag]: usage: diag [agg]"),
+    }
+}
+
+/// Collect every node's log records into a file
+/// ```text
+/// logger [file.log] [level]
+/// ```
+pub fn logger(args: Vec<&str>) {
+    let path = args
+        .first()
+        .map_or(LogFile::default_path(), |path| path.to_string());
+    let level = args
+        .get(1)
+        .and_then(|level| level.parse::<log::Level>().ok())
+        .map_or(LogLevel::Trace, LogLevel::from);
+
+    let mut file = match LogFile::open(&path, level) {
+        Ok(file) => file,
+        Err(e) => {
+            log::error!("couldn't open {path}, {e}");
+            return;
+        }
+    };
+
+    let mut sock = Sock::sinc("logger", vec![LOG_TOPIC]);
+    let mut buffer = [0u8; UDP_PACKET_SIZE];
+    log::info!("writing {level} and worse to {path}");
+
+    while !*sock.shutdown.read().unwrap() {
+        if let Some(i) = sock.try_rx(&mut buffer) {
+            match sock
+                .decode_message::<LogRecord>(i)
+                .map_err(|e| e.to_string())
+                .and_then(|record| file.write(&record).map_err(|e| e.to_string()))
+            {
+                Ok(_) => {}
+                Err(e) => log::warn!("dropped a record, {e}"),
+            }
+        }
+    }
+
+    sock.log_heavy(file.written);
+}
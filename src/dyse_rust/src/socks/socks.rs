@@ -12,15 +12,30 @@
  ********************************************************************************/
 use socket2::{Domain, Protocol, Socket, Type};
 use std::{
+    io,
     net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
     sync::{Arc, RwLock},
     time::{Duration, Instant},
 };
 
 use crate::ipv4;
+use crate::msgs::diagnostics::*;
+use crate::msgs::logging::LogRecord;
 use crate::sock_uri;
+use crate::socks::clocks::*;
+use crate::socks::codec::*;
+use crate::socks::diagnostics::*;
+use crate::socks::logging::*;
+use crate::socks::loopback::*;
 use crate::socks::message::*;
+use crate::socks::names::*;
+use crate::socks::params::*;
+use crate::socks::schema::*;
 use crate::socks::task::*;
+use crate::socks::throttle::*;
+use crate::socks::time_sync::*;
+use crate::socks::topics::*;
+use log::{info, warn};
 
 #[macro_export]
 macro_rules! ipv4 {
@@ -53,12 +68,14 @@ pub const INADDR_ANY: SocketAddr = sock_uri!();
 pub const DEFAULT_URI: SocketAddr = sock_uri!(1331);
 pub const MULTICAST_URI: SocketAddr = sock_uri!(MULTICAST_IP, 1331);
 
-pub const SOCK_NAME_LEN_IDX: usize = 0;
-pub const SOCK_NUM_TXS_IDX: usize = SOCK_NAME_LEN_IDX + 1;
+pub const SOCK_TOPIC_IDX: usize = 0;
+pub const SOCK_NUM_TXS_IDX: usize = SOCK_TOPIC_IDX + 4;
 pub const SOCK_NUM_RXS_IDX: usize = SOCK_NUM_TXS_IDX + 8;
 pub const SOCK_ACTIVITY_IDX: usize = SOCK_NUM_RXS_IDX + 8;
-pub const SOCK_NAME_IDX: usize = SOCK_ACTIVITY_IDX + 8;
-pub const MAX_SOCK_NAME_LEN: usize = SOCK_HEADER_LEN - SOCK_NAME_IDX;
+pub const SOCK_STAMP_IDX: usize = SOCK_ACTIVITY_IDX + 8;
+pub const SOCK_CODEC_IDX: usize = SOCK_STAMP_IDX + 8;
+/// first unused header byte, the rest of the header is zeros
+pub const SOCK_RESERVED_IDX: usize = SOCK_CODEC_IDX + 1;
 
 pub const SOCK_IO_LIMIT: u128 = 5;
 
@@ -83,27 +100,75 @@ fn new_multicast() -> UdpSocket {
     socket.try_into().unwrap()
 }
 
-pub fn truncate_name(name: &str) -> Option<String> {
+/// Where a sock sends and receives packets, the multicast group
+/// or an in-process [`LoopbackNetwork`] (for tests)
+pub enum SockSocket {
+    Udp(UdpSocket),
+    Loopback(LoopbackPort),
+}
+
+impl SockSocket {
+    pub fn multicast() -> SockSocket {
+        SockSocket::Udp(new_multicast())
+    }
+
+    pub fn send_to(&self, buffer: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        match self {
+            SockSocket::Udp(socket) => socket.send_to(buffer, addr),
+            SockSocket::Loopback(port) => port.send_to(buffer),
+        }
+    }
+
+    pub fn recv_from(&self, buffer: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        match self {
+            SockSocket::Udp(socket) => socket.recv_from(buffer),
+            SockSocket::Loopback(port) => port.recv_from(buffer),
+        }
+    }
+
+    pub fn peek_from(&self, buffer: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        match self {
+            SockSocket::Udp(socket) => socket.peek_from(buffer),
+            SockSocket::Loopback(port) => port.peek_from(buffer),
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            SockSocket::Udp(socket) => socket.local_addr(),
+            SockSocket::Loopback(port) => Ok(port.local_addr()),
+        }
+    }
+}
+
+/// Names go on the wire as a [`TopicId`] so they can be any length,
+/// they just can't be empty
+pub fn validate_name(name: &str) -> Option<String> {
     match name.len() > 0 {
-        true => Some(
-            name.chars()
-                .take(MAX_SOCK_NAME_LEN)
-                .map(|c| c.to_ascii_lowercase())
-                .collect(),
-        ),
+        true => Some(name.chars().map(|c| c.to_ascii_lowercase()).collect()),
         false => None,
     }
 }
 
 pub struct Sock {
-    pub socket: UdpSocket,
-    pub lifetime: Instant,
-    pub activity: Instant,
+    pub socket: SockSocket,
+    pub clock: Clock,
+    pub lifetime: u64,
+    pub activity: u64,
     pub ntx: i64,
     pub nrx: i64,
 
     pub name: String,
+    pub names: NameResolver,
+    pub topics: TopicRegistry,
+    pub params: ParamServer,
+    pub diagnostics: Diagnostics,
+    /// publishes this process's log records (see [`Sock::forward_logs`])
+    pub logging: bool,
+    /// codec used to send payloads (unless the topic has its own)
+    pub codec: CodecId,
     pub shutdown: Arc<RwLock<bool>>,
+    pub time_sync: TimeSync,
 
     pub tasks: Vec<Task>,
     pub targets: Vec<String>,
@@ -112,28 +177,94 @@ pub struct Sock {
 
 impl Sock {
     pub fn new(name: &str, targets: Vec<&str>, tasks: Vec<Task>) -> Sock {
-        let short_name =
-            truncate_name(name).expect(format!("Invalid name for sock: {name}").as_str());
+        Sock::with_socket(name, targets, tasks, SockSocket::multicast())
+    }
+
+    pub fn with_socket(
+        name: &str,
+        targets: Vec<&str>,
+        tasks: Vec<Task>,
+        socket: SockSocket,
+    ) -> Sock {
+        Sock::with_names(name, targets, tasks, socket, NameResolver::from_env())
+    }
+
+    /// Build a sock whose name, targets and task names are resolved by `names`
+    pub fn with_names(
+        name: &str,
+        targets: Vec<&str>,
+        mut tasks: Vec<Task>,
+        socket: SockSocket,
+        names: NameResolver,
+    ) -> Sock {
+        let short_name = validate_name(&names.resolve(name))
+            .expect(format!("Invalid name for sock: {name}").as_str());
 
         let n_targets = targets.len();
 
-        Sock {
-            socket: new_multicast(),
-            lifetime: Instant::now(),
-            activity: Instant::now(),
+        tasks.iter_mut().for_each(|task| {
+            task.name = validate_name(&names.resolve_private(&task.name, &short_name))
+                .unwrap_or(task.name.clone());
+        });
+
+        let mut sock = Sock {
+            socket,
+            clock: Clock::real(),
+            lifetime: 0,
+            activity: 0,
             ntx: 0,
             nrx: 0,
 
+            targets: targets
+                .into_iter()
+                .map(|target| names.resolve_private(target, &short_name))
+                .collect(),
+
             name: short_name,
+            names,
+            topics: TopicRegistry::new(&SYSTEM_NAMES),
+            params: ParamServer::new(),
+            diagnostics: Diagnostics::new(),
+            logging: false,
+            codec: CodecId::Bincode,
             shutdown: Arc::new(RwLock::new(false)),
+            time_sync: TimeSync::follower(),
 
             tasks: tasks,
-            targets: targets
-                .into_iter()
-                .map(|target| target.to_string())
-                .collect(),
             messages: vec![Message::new(); n_targets],
-        }
+        };
+
+        let local: Vec<String> = [sock.name.clone()]
+            .into_iter()
+            .chain(sock.targets.iter().cloned())
+            .chain(sock.tasks.iter().map(|task| task.name.clone()))
+            .collect();
+        local.iter().for_each(|name| {
+            sock.topics
+                .register(name)
+                .unwrap_or_else(|collision| panic!("Invalid names for sock: {collision}"));
+        });
+
+        sock.set_clock(Clock::from_env());
+        sock
+    }
+
+    /// Run the sock and all of its tasks on `clock`
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.lifetime = clock.micros();
+        self.activity = clock.micros();
+        self.tasks
+            .iter_mut()
+            .for_each(|task| task.set_clock(clock.clone()));
+        self.clock = clock;
+    }
+
+    /// A sock attached to `network` that runs on the network's clock
+    pub fn loopback(name: &str, targets: Vec<&str>, network: &LoopbackNetwork) -> Sock {
+        let mut sock =
+            Sock::with_socket(name, targets, vec![], SockSocket::Loopback(network.port()));
+        sock.set_clock(network.clock());
+        sock
     }
 
     pub fn source(name: &str) -> Sock {
@@ -152,8 +283,8 @@ impl Sock {
         task: TaskFn,
         task_targets: Vec<usize>,
     ) -> Sock {
-        let short_task_name = truncate_name(task_name).unwrap_or(
-            truncate_name(name)
+        let short_task_name = validate_name(task_name).unwrap_or(
+            validate_name(name)
                 .expect(format!("Invalid names for sock: {} {}", name, task_name).as_str()),
         );
 
@@ -187,33 +318,92 @@ impl Sock {
         Sock::event_task(name, targets, task_name, context, task, task_targets)
     }
 
-    pub fn header_from_bytes(&self, buffer: [u8; SOCK_HEADER_LEN]) -> (String, i64, i64, u64) {
-        let name_len = buffer[SOCK_NAME_LEN_IDX] as usize;
+    /// Synchronized time in micros since the UNIX epoch, this is
+    /// what gets used to stamp outgoing messages.
+    pub fn now(&self) -> i64 {
+        match self.clock.is_sim() {
+            true => self.clock.micros() as i64,
+            false => self.time_sync.now(),
+        }
+    }
+
+    /// Parse a header into (topic id, ntx, nrx, activity, source stamp, codec),
+    /// the codec is None if this build doesn't know it
+    pub fn header_from_bytes(
+        &self,
+        buffer: [u8; SOCK_HEADER_LEN],
+    ) -> (TopicId, i64, i64, u64, i64, Option<CodecId>) {
+        let id =
+            TopicId::from_be_bytes(buffer[SOCK_TOPIC_IDX..SOCK_NUM_TXS_IDX].try_into().unwrap());
         let ntx = i64::from_be_bytes(get8_bytes(SOCK_NUM_TXS_IDX, &buffer));
         let nrx = i64::from_be_bytes(get8_bytes(SOCK_NUM_TXS_IDX, &buffer));
         let activity = u64::from_be_bytes(get8_bytes(SOCK_ACTIVITY_IDX, &buffer));
-        let name =
-            String::from_utf8(buffer[SOCK_NAME_IDX..name_len + SOCK_NAME_IDX].to_vec()).unwrap();
+        let stamp = i64::from_be_bytes(get8_bytes(SOCK_STAMP_IDX, &buffer));
+        let codec = CodecId::from_byte(buffer[SOCK_CODEC_IDX]);
 
-        (name, ntx, nrx, activity)
+        (id, ntx, nrx, activity, stamp, codec)
     }
 
-    pub fn header_bytes(&self, name: &str, micros: u64) -> [u8; SOCK_HEADER_LEN] {
-        let name_bytes = name.as_bytes().to_vec();
-        let pad = MAX_SOCK_NAME_LEN - name_bytes.len();
-
-        [name_bytes.len() as u8]
+    pub fn header_bytes(
+        &self,
+        name: &str,
+        micros: u64,
+        stamp: i64,
+        codec: CodecId,
+    ) -> [u8; SOCK_HEADER_LEN] {
+        topic_id(name)
+            .to_be_bytes()
             .into_iter()
             .chain(self.ntx.to_be_bytes())
             .chain(self.nrx.to_be_bytes())
             .chain(micros.to_be_bytes())
-            .chain(name_bytes)
-            .chain(vec![0; pad])
+            .chain(stamp.to_be_bytes())
+            .chain([codec.byte()])
+            .chain([0; SOCK_HEADER_LEN - SOCK_RESERVED_IDX])
             .collect::<Vec<u8>>()
             .try_into()
             .unwrap()
     }
 
+    /// Describe the messages published on `name` so tools can decode them,
+    /// call before the first message is sent to include it in the first announcement
+    pub fn advertise_schema<T: HasSchema>(&mut self, name: &str) {
+        match self.topics.register(name) {
+            Ok(id) => self.topics.set_schema(id, T::schema()),
+            Err(collision) => warn!("[{}]: no schema, {collision}", self.name),
+        }
+    }
+
+    /// Decode the last message collected for target `idx` without its type.
+    /// Bincode needs the schema its publisher advertised, json and msgpack
+    /// decode without one (and are checked against it if there is one).
+    pub fn decode(&self, idx: usize) -> Option<Result<Value, SchemaError>> {
+        let payload = self.messages[idx].to_payload();
+        let codec = self.messages[idx].stamp.codec;
+        let schema = self.topics.schema(topic_id(&self.targets[idx]));
+
+        match codec.is_self_describing() {
+            true => Some(
+                codec
+                    .decode::<serde_json::Value>(&payload)
+                    .map_err(|e| SchemaError {
+                        expected: e.to_string(),
+                        offset: 0,
+                    })
+                    .and_then(|json| match schema {
+                        Some(schema) => schema.conform(Value::from(json)),
+                        None => Ok(Value::from(json)),
+                    }),
+            ),
+            false => schema.map(|schema| schema.decode(&payload)),
+        }
+    }
+
+    /// Network name of `name` as seen from this sock
+    pub fn resolve(&self, name: &str) -> String {
+        self.names.resolve_private(name, &self.name)
+    }
+
     pub fn is_target(&self, name: &str) -> Option<usize> {
         (0..self.targets.len()).find(|&i| self.targets[i as usize] == *name)
     }
@@ -225,25 +415,38 @@ impl Sock {
         context: T,
         task: TaskFn,
     ) {
-        let target_idxs = targets
+        let name = validate_name(&self.resolve(name)).unwrap_or(name.to_string());
+        let targets: Vec<String> = targets.iter().map(|target| self.resolve(target)).collect();
+        [name.clone()]
             .iter()
-            .map(|target| match self.is_target(target) {
+            .chain(targets.iter())
+            .for_each(|name| {
+                self.topics
+                    .register(name)
+                    .unwrap_or_else(|collision| panic!("Invalid names for task: {collision}"));
+            });
+        let target_idxs = targets
+            .into_iter()
+            .map(|target| match self.is_target(&target) {
                 Some(i) => i,
                 _ => {
-                    self.targets.push(target.to_string());
+                    self.targets.push(target);
                     self.messages.push(Message::new());
                     self.targets.len() - 1
                 }
             })
             .collect();
 
-        self.tasks.retain(|task| task.name != name);
-        self.tasks.push(Task::new(
-            name,
+        let mut task = Task::new(
+            &name,
             target_idxs,
             bincode::serialize(&context).expect("Failed to serialize default context"),
             task,
-        ));
+        );
+        task.set_clock(self.clock.clone());
+
+        self.tasks.retain(|task| task.name != name);
+        self.tasks.push(task);
     }
 
     pub fn tx(&mut self, mut buffer: UdpPacket, addr: SocketAddr) -> bool {
@@ -276,22 +479,114 @@ impl Sock {
     }
 
     pub fn tx_payload<T: serde::Serialize>(&mut self, payload: T) {
-        let msg = Message::from_payload(
-            bincode::serialize(&payload).expect("Failed to serialize payload (user)"),
+        let name = self.name.clone();
+        self.tx_stamped_payload(
+            &name,
+            &payload,
+            self.clock.elapsed(self.activity),
+            self.now(),
         );
-        msg.packets(self.header_bytes(&self.name, self.activity.elapsed().as_micros() as u64))
-            .iter()
-            .for_each(|buffer| {
-                self.tx(*buffer, MULTICAST_URI);
-            });
-        self.activity = Instant::now();
+        self.activity = self.clock.micros();
     }
 
     pub fn tx_any_payload<T: serde::Serialize>(&mut self, name: &str, payload: &T, micros: u64) {
-        let msg = Message::from_payload(
-            bincode::serialize(payload).expect("Failed to serialize payload (user)"),
-        );
-        msg.packets(self.header_bytes(name, micros))
+        self.tx_stamped_payload(name, payload, micros, self.now());
+    }
+
+    /// Send a payload that was produced at `stamp` (micros since the UNIX epoch)
+    /// instead of now, useful when relaying data from another source.
+    pub fn tx_stamped_payload<T: serde::Serialize>(
+        &mut self,
+        name: &str,
+        payload: &T,
+        micros: u64,
+        stamp: i64,
+    ) {
+        let codec = self.codec_of(name);
+        match codec.encode(payload) {
+            Ok(bytes) => self.tx_raw_payload(name, bytes, micros, stamp, codec),
+            Err(e) => warn!("[{}]: not sending {name}, {e}", self.name),
+        }
+    }
+
+    /// Codec used to send `name`, system messages are always bincode
+    pub fn codec_of(&self, name: &str) -> CodecId {
+        match NameResolver::is_system(name) {
+            true => CodecId::Bincode,
+            false => self.topics.codec(topic_id(name)).unwrap_or(self.codec),
+        }
+    }
+
+    /// Send every payload with `codec` (topics with their own codec keep it)
+    pub fn set_codec(&mut self, codec: CodecId) {
+        self.codec = codec;
+    }
+
+    /// Send `name` with `codec` no matter what the sock uses
+    pub fn set_topic_codec(&mut self, name: &str, codec: CodecId) {
+        match self.topics.register(name) {
+            Ok(id) => self.topics.set_codec(id, codec),
+            Err(collision) => warn!("[{}]: no codec, {collision}", self.name),
+        }
+    }
+
+    /// Limit how often `name` is sent, messages over the limit are dropped
+    pub fn set_throttle(&mut self, name: &str, throttle: Throttle) {
+        match self.topics.register(name) {
+            Ok(id) => self.topics.set_throttle(id, throttle),
+            Err(collision) => warn!("[{}]: no throttle, {collision}", self.name),
+        }
+    }
+
+    /// Messages on `name` dropped by its throttle
+    pub fn dropped(&self, name: &str) -> u64 {
+        self.topics
+            .throttle(topic_id(name))
+            .map_or(0, |throttle| throttle.dropped)
+    }
+
+    /// Decode the last message collected for target `idx` with the codec it was sent with
+    pub fn decode_message<T: serde::de::DeserializeOwned>(
+        &self,
+        idx: usize,
+    ) -> Result<T, CodecError> {
+        self.messages[idx]
+            .stamp
+            .codec
+            .decode(&self.messages[idx].to_payload())
+    }
+
+    /// Send bytes that are already serialized (like task outputs).
+    /// Topics are announced the first time they are sent (and periodically
+    /// after) and nothing is sent on a name that collides with a known topic.
+    pub fn tx_raw_payload(
+        &mut self,
+        name: &str,
+        payload: UdpPayload,
+        micros: u64,
+        stamp: i64,
+        codec: CodecId,
+    ) {
+        match self.topics.register(name) {
+            Ok(id) => {
+                if !NameResolver::is_system(name) {
+                    if self.topics.advertise(id) {
+                        self.topics.last_announce = None;
+                    }
+                    self.announce_topics();
+                }
+                if !self.topics.admit(id, self.clock.micros()) {
+                    return;
+                }
+            }
+            Err(collision) => {
+                warn!("[{}]: not sending, {collision}", self.name);
+                return;
+            }
+        }
+
+        let msg = Message::from_payload(payload);
+        msg.packets(self.header_bytes(name, micros, stamp, codec))
             .iter()
             .for_each(|buffer| {
                 self.tx(*buffer, MULTICAST_URI);
@@ -303,18 +598,33 @@ impl Sock {
         idx: usize,
         ntx: i64,
         activity: u64,
+        stamp: i64,
+        codec: CodecId,
         fragment: MessageFragment,
     ) -> Option<usize> {
-        match self.messages[idx].collect(ntx, activity, fragment) {
-            true => Some(idx),
+        match self.messages[idx].collect(ntx, activity, stamp, fragment) {
+            true => {
+                self.messages[idx].timestamp = self.clock.micros();
+                self.messages[idx].stamp.received = self.now();
+                self.messages[idx].stamp.codec = codec;
+                Some(idx)
+            }
             _ => None,
         }
     }
 
+    /// Read one packet, keeping our clock synced with the reference on the way
     pub fn try_rx(&mut self, buffer: &mut UdpPacket) -> Option<usize> {
+        self.sync_clock();
+
         match self.rx(buffer) {
             Some((header, fragment)) => {
-                let (name, ntx, _, activity) = self.header_from_bytes(header);
+                let (id, ntx, _, activity, stamp, codec) = self.header_from_bytes(header);
+                let name = match (self.topics.is_collided(id), codec) {
+                    (false, Some(_)) => self.topics.name(id).unwrap_or_default().to_string(),
+                    // can't tell which topic it is or can't read it
+                    _ => String::new(),
+                };
 
                 match name.as_str() {
                     // this should be handled better, kill sock is bad
@@ -322,6 +632,73 @@ impl Sock {
                         *self.shutdown.write().unwrap() = true;
                         None
                     }
+                    CLOCK_TOPIC => {
+                        if let Ok(micros) =
+                            bincode::deserialize::<u64>(&fragment.payload[0..fragment.n_bytes])
+                        {
+                            self.clock.set(micros);
+                        }
+                        None
+                    }
+                    CLOCK_REQUEST_NAME => {
+                        let t2 = self.now();
+                        if let Ok(request) = bincode::deserialize::<ClockRequest>(
+                            &fragment.payload[0..fragment.n_bytes],
+                        ) {
+                            if let Some(reply) = self.time_sync.reply(request, t2) {
+                                self.tx_any_payload(CLOCK_REPLY_NAME, &reply, 0);
+                            }
+                        }
+                        None
+                    }
+                    CLOCK_REPLY_NAME => {
+                        let t4 = stamp_micros();
+                        if let Ok(reply) = bincode::deserialize::<ClockReply>(
+                            &fragment.payload[0..fragment.n_bytes],
+                        ) {
+                            self.time_sync.update(&self.name, reply, t4);
+                        }
+                        None
+                    }
+                    TOPIC_ANNOUNCE_NAME => {
+                        if let Ok(announcement) = bincode::deserialize::<TopicAnnouncement>(
+                            &fragment.payload[0..fragment.n_bytes],
+                        ) {
+                            self.topics
+                                .learn(announcement)
+                                .iter()
+                                .for_each(|collision| warn!("[{}]: {collision}", self.name));
+                        }
+                        None
+                    }
+                    PARAM_REQUEST_NAME => {
+                        if let Ok(request) = bincode::deserialize::<ParamRequest>(
+                            &fragment.payload[0..fragment.n_bytes],
+                        ) {
+                            if request.node == self.name {
+                                self.answer_param(request);
+                            }
+                        }
+                        None
+                    }
+                    PARAM_REPLY_NAME => {
+                        if let Ok(reply) = bincode::deserialize::<ParamReply>(
+                            &fragment.payload[0..fragment.n_bytes],
+                        ) {
+                            if reply.client == self.name {
+                                self.params.receive(reply);
+                            }
+                        }
+                        None
+                    }
+                    PARAM_EVENT_NAME => {
+                        if let Ok(event) = bincode::deserialize::<ParamEvent>(
+                            &fragment.payload[0..fragment.n_bytes],
+                        ) {
+                            self.params.hear(event);
+                        }
+                        None
+                    }
                     "identify" => {
                         self.tx_any_payload(
                             &format!("id/{}", self.name),
@@ -337,7 +714,7 @@ impl Sock {
                             Some(i) => {
                                 self.nrx += 1;
                                 // check the mode, maybe don't collect (instead respond with info maybe)
-                                self.collect(i, ntx, activity, fragment)
+                                self.collect(i, ntx, activity, stamp, codec?, fragment)
                             }
                             _ => None,
                         }
@@ -349,31 +726,34 @@ impl Sock {
     }
 
     pub fn task_available(&self, task_idx: usize) -> bool {
+        let now = self.clock.micros();
         (0..self.tasks[task_idx].targets.len())
             .map(|i| {
                 let msg_idx = self.tasks[task_idx].targets[i];
-                match self.messages[msg_idx].is_available() {
+                match self.messages[msg_idx].is_available(now) {
                     true => {
-                        (self.messages[msg_idx].timestamp.elapsed().as_micros() / 5)
-                            + (4 * self.messages[msg_idx].micros_rate as u128 / 5)
+                        (self.clock.elapsed(self.messages[msg_idx].timestamp) / 5)
+                            + (4 * self.messages[msg_idx].micros_rate / 5)
                     }
-                    false => u128::MAX,
+                    false => u64::MAX,
                 }
             })
             .max()
-            .unwrap_or(u128::MAX)
-            < self.tasks[task_idx].timestamp.elapsed().as_micros()
+            .unwrap_or(u64::MAX)
+            < self.clock.elapsed(self.tasks[task_idx].timestamp)
     }
 
     pub fn available_messages(&self) -> Vec<usize> {
+        let now = self.clock.micros();
         (0..self.messages.len())
-            .filter(|&i| self.messages[i].is_available())
+            .filter(|&i| self.messages[i].is_available(now))
             .collect()
     }
 
     pub fn recv_available(&self) -> Vec<UdpPayload> {
+        let now = self.clock.micros();
         (0..self.messages.len())
-            .filter_map(|i| match self.messages[i].is_available() {
+            .filter_map(|i| match self.messages[i].is_available(now) {
                 true => Some(self.messages[i].to_payload()),
                 false => None,
             })
@@ -388,22 +768,34 @@ impl Sock {
             .collect()
     }
 
+    pub fn chain_stamps(&self, idx: usize) -> Vec<MessageStamp> {
+        self.tasks[idx]
+            .targets
+            .iter()
+            .map(|&i| self.messages[i].stamp)
+            .collect()
+    }
+
     pub fn sync_call(&mut self, task_idx: usize) -> UdpPayload {
         match self.task_available(task_idx) {
             true => {
                 let payload = self.chain_payloads(task_idx);
-                self.tasks[task_idx].execute(payload).unwrap()
+                let stamps = self.chain_stamps(task_idx);
+                self.tasks[task_idx].execute(payload, stamps).unwrap()
             }
             false => vec![],
         }
     }
 
     pub fn unsync_call(&mut self, task_idx: usize, msg_idx: usize) -> UdpPayload {
-        match self.tasks[task_idx].timestamp.elapsed().as_micros()
-            > self.messages[msg_idx].timestamp.elapsed().as_micros()
+        match self.clock.elapsed(self.tasks[task_idx].timestamp)
+            > self.clock.elapsed(self.messages[msg_idx].timestamp)
         {
             true => self.tasks[task_idx]
-                .execute(vec![self.messages[msg_idx].to_payload()])
+                .execute(
+                    vec![self.messages[msg_idx].to_payload()],
+                    vec![self.messages[msg_idx].stamp],
+                )
                 .unwrap(),
             false => vec![],
         }
@@ -411,7 +803,7 @@ impl Sock {
 
     pub fn try_all_tasks(&mut self, msg_idx: usize) {
         (0..self.tasks.len()).for_each(|i| {
-            let ts = self.tasks[i].timestamp.elapsed().as_micros() as u64;
+            let ts = self.clock.elapsed(self.tasks[i].timestamp);
             let output = match self.tasks[i].targets.len() == 0 {
                 // unsynced calls use
                 true => self.unsync_call(i, msg_idx),
@@ -425,11 +817,142 @@ impl Sock {
         });
     }
 
+    /// Ask the reference for the time when a sync is due, [`Sock::try_rx`]
+    /// does this for every sock
+    pub fn sync_clock(&mut self) {
+        if let Some(request) = self.time_sync.request(&self.name) {
+            self.tx_any_payload(CLOCK_REQUEST_NAME, &request, 0);
+        }
+    }
+
+    /// Declare a parameter other nodes can get and set
+    pub fn declare_param(&mut self, param: Param) -> Result<(), ParamError> {
+        self.params.declare(param)
+    }
+
+    /// Value of a declared parameter, None if it isn't declared as a `T`
+    pub fn param<T: TryFrom<ParamValue>>(&self, name: &str) -> Option<T> {
+        self.params
+            .get(name)
+            .and_then(|value| T::try_from(value.clone()).ok())
+    }
+
+    /// Set one of our parameters and tell everyone it changed
+    pub fn set_param(&mut self, name: &str, value: ParamValue) -> Result<(), ParamError> {
+        let value = self.params.set(name, value)?;
+        let event = ParamEvent {
+            node: self.name.clone(),
+            name: name.to_string(),
+            value,
+            stamp: self.now(),
+        };
+        self.tx_any_payload(PARAM_EVENT_NAME, &event, 0);
+        Ok(())
+    }
+
+    /// Names of our parameters that were set since the last call
+    pub fn param_changes(&mut self) -> Vec<String> {
+        self.params.take_changes()
+    }
+
+    /// Ask `node` about its parameters, returns the id to wait on with
+    /// [`ParamServer::take_reply`]
+    pub fn request_param(&mut self, node: &str, op: ParamOp) -> u32 {
+        let node = self.resolve(node);
+        let request = self.params.request(&self.name, &node, op);
+        self.tx_any_payload(PARAM_REQUEST_NAME, &request, 0);
+        request.id
+    }
+
+    pub fn answer_param(&mut self, request: ParamRequest) {
+        let answer = match &request.op {
+            ParamOp::List => Ok(self.params.params.clone()),
+            ParamOp::Get(name) => self
+                .params
+                .param(name)
+                .map(|param| vec![param.clone()])
+                .ok_or(ParamError::Unknown(name.clone())),
+            ParamOp::Set(name, value) => self
+                .set_param(name, value.clone())
+                .map(|_| vec![self.params.param(name).unwrap().clone()]),
+        };
+
+        ParamServer::replies(&request, answer)
+            .iter()
+            .for_each(|reply| self.tx_any_payload(PARAM_REPLY_NAME, reply, 0));
+    }
+
+    /// Report the state of something this sock looks after, the status
+    /// name goes under the sock's name (`hid` from robot_fw is `robot_fw/hid`)
+    pub fn diagnose(&mut self, status: DiagnosticStatus) {
+        let topic = self.resolve(DIAGNOSTICS_TOPIC);
+        if self.diagnostics.statuses.is_empty() {
+            self.advertise_schema::<DiagnosticArray>(&topic);
+        }
+
+        let hardware_id = match status.hardware_id.is_empty() {
+            true => self.name.clone(),
+            false => status.hardware_id.clone(),
+        };
+        self.diagnostics.update(DiagnosticStatus {
+            name: join_name(&self.name, &status.name),
+            hardware_id,
+            ..status
+        });
+        self.publish_diagnostics();
+    }
+
+    /// Send our statuses if one changed level or they haven't been sent in a while
+    pub fn publish_diagnostics(&mut self) {
+        if let Some(array) = self.diagnostics.due(&self.name, self.now()) {
+            let topic = self.resolve(DIAGNOSTICS_TOPIC);
+            self.tx_any_payload(&topic, &array, 0);
+        }
+    }
+
+    /// Publish the log records of this process on [`LOG_TOPIC`], one sock
+    /// per process should do it (the logger has to be installed, see [`init`])
+    pub fn forward_logs(&mut self) {
+        match logger() {
+            Some(logger) => {
+                let topic = self.resolve(LOG_TOPIC);
+                self.advertise_schema::<LogRecord>(&topic);
+                logger.forward(true);
+                self.logging = true;
+            }
+            None => warn!("[{}]: no logger to forward", self.name),
+        }
+    }
+
+    /// Send the log records queued since the last call
+    pub fn publish_logs(&mut self) {
+        if let (true, Some(logger)) = (self.logging, logger()) {
+            let topic = self.resolve(LOG_TOPIC);
+            logger.take().into_iter().for_each(|mut record| {
+                record.node = self.name.clone();
+                record.header.frame_id = self.name.clone();
+                self.tx_any_payload(&topic, &record, 0);
+            });
+        }
+    }
+
+    /// Remind everyone of the names of our topics when it's due
+    pub fn announce_topics(&mut self) {
+        self.topics
+            .announcements()
+            .iter()
+            .for_each(|announcement| self.tx_any_payload(TOPIC_ANNOUNCE_NAME, announcement, 0));
+    }
+
     pub fn spin(&mut self) {
         while !*self.shutdown.read().unwrap() {
             let t = Instant::now();
             let mut buffer = [0u8; UDP_PACKET_SIZE];
 
+            self.announce_topics();
+            self.publish_diagnostics();
+            self.publish_logs();
+
             match self.try_rx(&mut buffer) {
                 Some(i) => {
                     self.try_all_tasks(i);
@@ -447,7 +970,7 @@ impl Sock {
             "[{:?}]: {:?}\n\tLifetime: {}s\n\tPackets Tx/Rx <{},{}>",
             self.name,
             self.socket.local_addr().unwrap(),
-            self.lifetime.elapsed().as_micros() as f64 * 1E-6,
+            self.clock.elapsed(self.lifetime) as f64 * 1E-6,
             self.ntx,
             self.nrx,
         )
@@ -455,23 +978,25 @@ impl Sock {
 
     pub fn to_heavy_string(&self) -> String {
         format!(
-            "{}\n\tActivity: {}s\n\tTargets: {:?} ({} active)\n\tMessage Rates: {:.4?} Hz\n\tTasks: {:?}\n\tTask Rates: {:.4?} Hz",
+            "{}\n\tActivity: {}s\n\tClock: {}\n\tTargets: {:?} ({} active)\n\tMessage Rates: {:.4?} Hz\n\tTasks: {:?}\n\tTask Rates: {:.4?} Hz\n\tThrottles: {:?}",
             self.to_string(),
-            self.activity.elapsed().as_micros() as f64 * 1E-6,
+            self.clock.elapsed(self.activity) as f64 * 1E-6,
+            self.time_sync,
             self.targets,
             self.messages.len(),
             (0..self.messages.len()).map(|i| 1E6 / self.messages[i].micros_rate as f64).collect::<Vec<f64>>(),
             (0..self.tasks.len()).map(|i| self.tasks[i].name.clone()).collect::<Vec<String>>(),
-            (0..self.tasks.len()).map(|i| 1E6 / self.tasks[i].timestamp.elapsed().as_micros() as f64).collect::<Vec<f64>>(),
+            (0..self.tasks.len()).map(|i| 1E6 / self.clock.elapsed(self.tasks[i].timestamp) as f64).collect::<Vec<f64>>(),
+            self.topics.throttles.iter().map(|(&id, throttle)| format!("{}: {throttle}", self.topics.name(id).unwrap_or_default())).collect::<Vec<String>>(),
         )
     }
 
     pub fn log<M: std::fmt::Debug>(&self, message: M) {
-        println!("{}\n\tInfo: {:?}", self.to_string(), message)
+        info!("{}\n\tInfo: {:?}", self.to_string(), message)
     }
 
     pub fn log_heavy<M: std::fmt::Debug>(&self, message: M) {
-        println!(
+        info!(
             "\n==[Sock]==\n{}\n\tInfo: {:?}",
             self.to_heavy_string(),
             message
@@ -11,8 +11,11 @@
  *
  ********************************************************************************/
 
-use crate::socks::message::UdpPayload;
-use std::{fmt, time::Instant};
+use crate::socks::{
+    clocks::Clock,
+    message::{MessageStamp, UdpPayload},
+};
+use std::fmt;
 
 pub const TASK_SUCCESS: usize = 0;
 pub const TASK_WARN: usize = 1;
@@ -21,43 +24,53 @@ pub const TASK_IO_ERROR: usize = 3;
 pub const TASK_UNIMPLEMENTED: usize = 4;
 pub const TASK_LABELS: [&str; 5] = ["", "WARN", "ERROR", "IO_ERROR", "UNIMPLEMENTED"];
 
-pub trait GenExecutable<T, U, V>: Fn(T, &mut U, f64) -> (usize, V) {}
-impl<F, T, U, V> GenExecutable<T, U, V> for F where F: Fn(T, &mut U, f64) -> (usize, V) {}
+pub trait GenExecutable<T, U, V>: Fn(T, &mut U, f64, &[MessageStamp]) -> (usize, V) {}
+impl<F, T, U, V> GenExecutable<T, U, V> for F where
+    F: Fn(T, &mut U, f64, &[MessageStamp]) -> (usize, V)
+{
+}
 
 pub trait TaskExecutable: GenExecutable<Vec<UdpPayload>, UdpPayload, UdpPayload> {}
 impl<F> TaskExecutable for F where F: GenExecutable<Vec<UdpPayload>, UdpPayload, UdpPayload> {}
 // Default types and implementations
-pub type TaskFn = fn(Vec<UdpPayload>, &mut UdpPayload, f64) -> (usize, UdpPayload);
-pub fn empty_exe(_: Vec<UdpPayload>, _: &mut UdpPayload, _: f64) -> (usize, UdpPayload) {
+pub type TaskFn = fn(Vec<UdpPayload>, &mut UdpPayload, f64, &[MessageStamp]) -> (usize, UdpPayload);
+pub fn empty_exe(
+    _: Vec<UdpPayload>,
+    _: &mut UdpPayload,
+    _: f64,
+    _: &[MessageStamp],
+) -> (usize, UdpPayload) {
     (0, vec![])
 }
 
+/// Wrap a typed closure as a [`TaskFn`]. Inputs are decoded with the codec
+/// they were sent with, the context and output are always bincode.
 #[macro_export]
 macro_rules! build_fn {
     (|$context:ident: $U:ty, $($target:ident: $T:ty),+| $body:expr) => (
-        build_fn!(|$context: $U, _t, $($target: $T),+| $body)
+        build_fn!(|$context: $U, _t, _s, $($target: $T),+| $body)
     );
-    // makes the timestamp function is called with accessible
-    (|$context:ident: $U:ty, $time:ident, $target:ident: $T:ty| $body:expr) => (
-        |task_input: Vec<Vec<u8>>, task_context: &mut Vec<u8>, $time: f64| -> (usize, Vec<u8>) {
+    // makes the timestamp and source stamps accessible to the function
+    (|$context:ident: $U:ty, $time:ident, $stamps:ident, $target:ident: $T:ty| $body:expr) => (
+        |task_input: Vec<Vec<u8>>, task_context: &mut Vec<u8>, $time: f64, $stamps: &[$crate::socks::message::MessageStamp]| -> (usize, Vec<u8>) {
             let return_code = 0;
             #[allow(unused_mut)]
             let mut $context: $U = bincode::deserialize(&task_context).expect("Failed to deserialize context (build_fn)");
-            let $target: Vec<$T> = task_input.iter().map(|task_in| bincode::deserialize::<$T>(&task_in).expect("Failed to deserialize input (build_fn)")).collect();
+            let $target: Vec<$T> = task_input.iter().enumerate().map(|(i, task_in)| $crate::socks::codec::CodecId::of_input($stamps, i).decode::<$T>(&task_in).expect("Failed to deserialize input (build_fn)")).collect();
 
             let output = $body;
             *task_context = bincode::serialize(&$context).expect("Failed to serialize context (build_fn)");
             (return_code, bincode::serialize(&output).expect("Failed to serialize output (build_fn)"))
         }
     );
-    (|$context:ident: $U:ty, $time:ident, $($target:ident: $T:ty),+| $body:expr) => (
-        |task_input: Vec<Vec<u8>>, task_context: &mut Vec<u8>, $time: f64| -> (usize, Vec<u8>) {
+    (|$context:ident: $U:ty, $time:ident, $stamps:ident, $($target:ident: $T:ty),+| $body:expr) => (
+        |task_input: Vec<Vec<u8>>, task_context: &mut Vec<u8>, $time: f64, $stamps: &[$crate::socks::message::MessageStamp]| -> (usize, Vec<u8>) {
             let mut _argc = 0;
             let return_code = 0;
             #[allow(unused_mut)]
             let mut $context: $U = bincode::deserialize(&task_context).expect("Failed to deserialize context (build_fn)");
             $(
-                let $target: $T = bincode::deserialize(&task_input[_argc]).expect("Failed to deserialize input (build_fn)");
+                let $target: $T = $crate::socks::codec::CodecId::of_input($stamps, _argc).decode(&task_input[_argc]).expect("Failed to deserialize input (build_fn)");
                 _argc += 1;
             )+
 
@@ -66,6 +79,10 @@ macro_rules! build_fn {
             (return_code, bincode::serialize(&output).expect("Failed to serialize output (build_fn)"))
         }
     );
+    // makes the timestamp accessible to the function
+    (|$context:ident: $U:ty, $time:ident, $($target:ident: $T:ty),+| $body:expr) => (
+        build_fn!(|$context: $U, $time, _s, $($target: $T),+| $body)
+    );
 }
 
 #[derive(Debug, Clone)]
@@ -90,8 +107,9 @@ impl fmt::Display for TaskError {
 }
 
 pub struct Task {
-    pub timestamp: Instant,
-    pub lifetime: Instant,
+    pub clock: Clock,
+    pub timestamp: u64,
+    pub lifetime: u64,
 
     pub name: String,
     pub targets: Vec<usize>,
@@ -103,8 +121,9 @@ pub struct Task {
 impl Task {
     pub fn new(name: &str, targets: Vec<usize>, context: UdpPayload, task: TaskFn) -> Task {
         Task {
-            timestamp: Instant::now(),
-            lifetime: Instant::now(),
+            clock: Clock::real(),
+            timestamp: 0,
+            lifetime: 0,
 
             name: name.to_string(),
             targets: targets,
@@ -114,15 +133,26 @@ impl Task {
         }
     }
 
+    /// Run the task on `clock`, this restarts the task's lifetime
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.timestamp = clock.micros();
+        self.lifetime = clock.micros();
+        self.clock = clock;
+    }
+
     pub fn get_context<T: PartialEq + fmt::Debug + for<'a> serde::de::Deserialize<'a>>(&self) -> T {
         bincode::deserialize(&self.context).expect("Failed to deserialize context (user)")
     }
 
-    pub fn execute(&mut self, data: Vec<UdpPayload>) -> Result<UdpPayload, TaskError> {
-        self.timestamp = Instant::now();
+    pub fn execute(
+        &mut self,
+        data: Vec<UdpPayload>,
+        stamps: Vec<MessageStamp>,
+    ) -> Result<UdpPayload, TaskError> {
+        self.timestamp = self.clock.micros();
 
-        let t = self.lifetime.elapsed().as_micros() as f64 * 1E-6;
-        let (code, output) = (self.task)(data, &mut self.context, t);
+        let t = self.clock.elapsed(self.lifetime) as f64 * 1E-6;
+        let (code, output) = (self.task)(data, &mut self.context, t, &stamps);
 
         match code {
             TASK_SUCCESS => Ok(output),
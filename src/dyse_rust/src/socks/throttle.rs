@@ -0,0 +1,142 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::utilities::loaders::*;
+use std::fmt;
+use yaml_rust::Yaml;
+
+/// How often a topic is allowed on the network
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Throttle {
+    /// at most this many messages per second
+    Rate(f64),
+    /// only every nth message
+    Decimate(u64),
+}
+
+impl Throttle {
+    /// Parse a throttle from yaml, either a rate in Hz or a map with a `rate`
+    /// or `decimate` key
+    /// ```yaml
+    /// viz: 50.0
+    /// log: {decimate: 10}
+    /// ```
+    pub fn from_yaml(
+        byu: &BuffYamlUtil,
+        item: &str,
+        data: &Yaml,
+    ) -> Result<Throttle, ByuParseError> {
+        let throttle = match &data[item] {
+            Yaml::Real(_) | Yaml::Integer(_) => Throttle::Rate(byu.parse_float(item, data)?),
+            Yaml::Hash(_) => match byu.parse_float("rate", &data[item]) {
+                Ok(hz) => Throttle::Rate(hz),
+                Err(_) => Throttle::Decimate(byu.parse_int("decimate", &data[item])? as u64),
+            },
+            _ => return Err(ByuParseError::item(item, &byu.yaml_path)),
+        };
+
+        match throttle.is_valid() {
+            true => Ok(throttle),
+            false => Err(ByuParseError::item(item, &byu.yaml_path)),
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Throttle::Rate(hz) => *hz > 0.0 && hz.is_finite(),
+            Throttle::Decimate(n) => *n > 0,
+        }
+    }
+
+    /// Micros between messages of a topic published every `micros` after
+    /// being throttled
+    pub fn period(&self, micros: u64) -> u64 {
+        match self {
+            Throttle::Rate(hz) => micros.max((1E6 / hz) as u64),
+            Throttle::Decimate(n) => micros * n,
+        }
+    }
+}
+
+impl fmt::Display for Throttle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Throttle::Rate(hz) => write!(f, "{hz}Hz"),
+            Throttle::Decimate(n) => write!(f, "1/{n}"),
+        }
+    }
+}
+
+/// Throttle of a single topic and what it let through
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopicThrottle {
+    pub throttle: Throttle,
+    pub offered: u64,
+    pub sent: u64,
+    pub dropped: u64,
+    /// micros the next message is allowed at (rate limits only)
+    pub next: u64,
+}
+
+impl TopicThrottle {
+    pub fn new(throttle: Throttle) -> TopicThrottle {
+        TopicThrottle {
+            throttle,
+            offered: 0,
+            sent: 0,
+            dropped: 0,
+            next: 0,
+        }
+    }
+
+    /// Count a message offered at `micros`, true if it should be sent.
+    /// Rate limits keep their schedule so the average rate stays on target
+    /// when the publisher jitters, but never save up a burst after a pause.
+    pub fn admit(&mut self, micros: u64) -> bool {
+        self.offered += 1;
+
+        let admit = match self.throttle {
+            Throttle::Rate(hz) => {
+                let period = (1E6 / hz) as u64;
+                match micros >= self.next {
+                    true => {
+                        self.next = match self.sent == 0 || micros - self.next >= period {
+                            true => micros + period,
+                            false => self.next + period,
+                        };
+                        true
+                    }
+                    false => false,
+                }
+            }
+            Throttle::Decimate(n) => (self.offered - 1) % n == 0,
+        };
+
+        match admit {
+            true => self.sent += 1,
+            false => self.dropped += 1,
+        }
+
+        admit
+    }
+}
+
+impl fmt::Display for TopicThrottle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} sent {}/{} ({} dropped)",
+            self.throttle, self.sent, self.offered, self.dropped
+        )
+    }
+}
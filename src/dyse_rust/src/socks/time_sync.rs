@@ -0,0 +1,223 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::socks::message::stamp_micros;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// System message names used by the sync protocol
+pub const CLOCK_REQUEST_NAME: &str = "clock/req";
+pub const CLOCK_REPLY_NAME: &str = "clock/rep";
+
+/// How often followers ask the reference for the time
+pub const CLOCK_SYNC_INTERVAL_MS: u128 = 1000;
+/// Number of offset samples used to fit offset and drift
+pub const CLOCK_SYNC_SAMPLES: usize = 16;
+/// Round trips slower than this are too noisy to use
+pub const CLOCK_SYNC_MAX_DELAY_US: i64 = 10_000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ClockRole {
+    Reference,
+    Follower,
+}
+
+/// Sent by a follower at t1 (follower time)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ClockRequest {
+    pub name: String,
+    pub t1: i64,
+}
+
+/// Sent back by the reference, t2 is when the request arrived
+/// and t3 is when the reply left (reference time)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ClockReply {
+    pub name: String,
+    pub t1: i64,
+    pub t2: i64,
+    pub t3: i64,
+}
+
+/// NTP style clock synchronization between socks hosts.
+///
+/// One sock on the network is the reference, every other sock
+/// periodically sends a [`ClockRequest`] and uses the [`ClockReply`]
+/// to estimate the offset (micros) between its clock and the reference.
+/// The last [`CLOCK_SYNC_SAMPLES`] offsets are fit with a line to also
+/// estimate drift, so stamps stay aligned between requests.
+pub struct TimeSync {
+    pub role: ClockRole,
+
+    /// offset (micros) at the anchor time
+    pub offset: f64,
+    /// change in offset per micro of local time
+    pub drift: f64,
+    /// local time (micros) the fit is anchored to
+    pub anchor: i64,
+    /// round trip delay of the last accepted exchange
+    pub delay: i64,
+
+    pub samples: Vec<(i64, f64)>,
+    pub pending: Option<i64>,
+    /// local time (micros) of the last request
+    pub last_request: Option<i64>,
+}
+
+impl TimeSync {
+    pub fn new(role: ClockRole) -> TimeSync {
+        TimeSync {
+            role,
+
+            offset: 0.0,
+            drift: 0.0,
+            anchor: 0,
+            delay: 0,
+
+            samples: vec![],
+            pending: None,
+            last_request: None,
+        }
+    }
+
+    pub fn reference() -> TimeSync {
+        TimeSync::new(ClockRole::Reference)
+    }
+
+    pub fn follower() -> TimeSync {
+        TimeSync::new(ClockRole::Follower)
+    }
+
+    pub fn is_reference(&self) -> bool {
+        self.role == ClockRole::Reference
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.is_reference() || !self.samples.is_empty()
+    }
+
+    /// Convert a local stamp (micros since the UNIX epoch) to reference time
+    pub fn correct(&self, local: i64) -> i64 {
+        local + (self.offset + self.drift * (local - self.anchor) as f64) as i64
+    }
+
+    /// Synchronized time in micros since the UNIX epoch
+    pub fn now(&self) -> i64 {
+        self.correct(stamp_micros())
+    }
+
+    /// Build a request if this is a follower and it's time to ask again
+    pub fn request(&mut self, name: &str) -> Option<ClockRequest> {
+        self.request_at(name, stamp_micros())
+    }
+
+    /// [`TimeSync::request`] at local time `t1` (micros)
+    pub fn request_at(&mut self, name: &str, t1: i64) -> Option<ClockRequest> {
+        let due = match self.last_request {
+            Some(t) => (t1 - t) as u128 >= 1000 * CLOCK_SYNC_INTERVAL_MS,
+            None => true,
+        };
+
+        match !self.is_reference() && due {
+            true => {
+                self.pending = Some(t1);
+                self.last_request = Some(t1);
+                Some(ClockRequest {
+                    name: name.to_string(),
+                    t1,
+                })
+            }
+            false => None,
+        }
+    }
+
+    /// Answer a request that arrived at `t2`, only the reference replies
+    pub fn reply(&self, request: ClockRequest, t2: i64) -> Option<ClockReply> {
+        match self.is_reference() {
+            true => Some(ClockReply {
+                name: request.name,
+                t1: request.t1,
+                t2,
+                t3: stamp_micros(),
+            }),
+            false => None,
+        }
+    }
+
+    /// Use a reply that arrived at `t4` (local time) to update the estimate.
+    /// Returns true if the reply was for us and was accepted.
+    pub fn update(&mut self, name: &str, reply: ClockReply, t4: i64) -> bool {
+        if self.is_reference() || reply.name != name || self.pending != Some(reply.t1) {
+            return false;
+        }
+
+        self.pending = None;
+
+        let delay = (t4 - reply.t1) - (reply.t3 - reply.t2);
+        if delay > CLOCK_SYNC_MAX_DELAY_US {
+            return false;
+        }
+
+        let offset = ((reply.t2 - reply.t1) + (reply.t3 - t4)) as f64 / 2.0;
+
+        self.delay = delay;
+        self.samples.push((t4, offset));
+        if self.samples.len() > CLOCK_SYNC_SAMPLES {
+            self.samples.remove(0);
+        }
+
+        self.fit();
+        true
+    }
+
+    /// Least squares line through the offset samples
+    fn fit(&mut self) {
+        let n = self.samples.len() as f64;
+        let (t_last, offset_last) = self.samples[self.samples.len() - 1];
+
+        let (st, so, stt, sto) =
+            self.samples
+                .iter()
+                .fold((0.0, 0.0, 0.0, 0.0), |(st, so, stt, sto), &(t, offset)| {
+                    let t = (t - t_last) as f64;
+                    (st + t, so + offset, stt + t * t, sto + t * offset)
+                });
+
+        let denominator = n * stt - st * st;
+        match denominator.abs() > f64::EPSILON {
+            true => {
+                self.drift = (n * sto - st * so) / denominator;
+                self.offset = (so - self.drift * st) / n;
+            }
+            false => {
+                self.drift = 0.0;
+                self.offset = offset_last;
+            }
+        }
+
+        self.anchor = t_last;
+    }
+}
+
+impl fmt::Display for TimeSync {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} offset: {:.1}us drift: {:.3}ppm delay: {}us",
+            self.role,
+            self.offset,
+            self.drift * 1E6,
+            self.delay,
+        )
+    }
+}
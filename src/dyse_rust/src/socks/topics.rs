@@ -0,0 +1,250 @@
+/********************************************************************************
+ *
+ *      ____                     ____          __           __       _
+ *     / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *    / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *   / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  )
+ *  /_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/
+ *        /____/
+ *
+ *
+ *
+ ********************************************************************************/
+
+use crate::socks::{
+    codec::CodecId,
+    message::MAX_FRAGMENT_SIZE,
+    schema::Schema,
+    throttle::{Throttle, TopicThrottle},
+};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, time::Instant};
+
+/// System message publishers use to tell everyone the names of their topics
+pub const TOPIC_ANNOUNCE_NAME: &str = "topic/announce";
+/// How often every published topic is announced again (for late joiners)
+pub const TOPIC_ANNOUNCE_INTERVAL_MS: u128 = 2000;
+
+/// Compact topic name carried in sock headers
+pub type TopicId = u32;
+
+/// 32 bit FNV-1a hash of a (resolved) topic name
+pub fn topic_id(name: &str) -> TopicId {
+    name.bytes().fold(0x811c9dc5, |hash: u32, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+/// A published topic, ids are derived from the name
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TopicInfo {
+    pub name: String,
+    pub schema: Option<Schema>,
+}
+
+/// Payload of [`TOPIC_ANNOUNCE_NAME`], system messages are read from one
+/// fragment so each announcement has to fit in [`MAX_FRAGMENT_SIZE`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TopicAnnouncement {
+    pub topics: Vec<TopicInfo>,
+}
+
+impl TopicAnnouncement {
+    pub fn fits(&self) -> bool {
+        bincode::serialized_size(self).is_ok_and(|n| n as usize <= MAX_FRAGMENT_SIZE)
+    }
+}
+
+/// Two different names that hash to the same id
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopicCollision {
+    pub id: TopicId,
+    pub existing: String,
+    pub name: String,
+}
+
+impl fmt::Display for TopicCollision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "topic {} collides with {} (id {:#010x})",
+            self.name, self.existing, self.id
+        )
+    }
+}
+
+/// Names a sock knows by id.
+///
+/// Local names (the sock, its targets and tasks) are registered when they are
+/// created, names (and message schemas) from other socks are learned from
+/// announcements. Registering
+/// a name whose id already belongs to a different name fails, so a collision
+/// is reported instead of silently mixing two topics.
+pub struct TopicRegistry {
+    pub names: HashMap<TopicId, String>,
+    pub schemas: HashMap<TopicId, Schema>,
+    pub codecs: HashMap<TopicId, CodecId>,
+    pub throttles: HashMap<TopicId, TopicThrottle>,
+    pub advertised: Vec<TopicId>,
+    pub collisions: Vec<TopicCollision>,
+    pub last_announce: Option<Instant>,
+}
+
+impl TopicRegistry {
+    /// A registry that already knows `system` names
+    pub fn new(system: &[&str]) -> TopicRegistry {
+        let mut topics = TopicRegistry {
+            names: HashMap::new(),
+            schemas: HashMap::new(),
+            codecs: HashMap::new(),
+            throttles: HashMap::new(),
+            advertised: vec![],
+            collisions: vec![],
+            last_announce: None,
+        };
+
+        system.iter().for_each(|name| {
+            topics.register(name).expect("System topic names collide");
+        });
+
+        topics
+    }
+
+    pub fn register(&mut self, name: &str) -> Result<TopicId, TopicCollision> {
+        let id = topic_id(name);
+
+        match self.names.get(&id) {
+            Some(existing) if existing != name => Err(TopicCollision {
+                id,
+                existing: existing.clone(),
+                name: name.to_string(),
+            }),
+            Some(_) => Ok(id),
+            None => {
+                self.names.insert(id, name.to_string());
+                Ok(id)
+            }
+        }
+    }
+
+    /// Register topics from an announcement, returns collisions not seen before
+    pub fn learn(&mut self, announcement: TopicAnnouncement) -> Vec<TopicCollision> {
+        let mut collisions = vec![];
+
+        announcement
+            .topics
+            .into_iter()
+            .for_each(|topic| match self.register(&topic.name) {
+                Ok(id) => {
+                    if let Some(schema) = topic.schema {
+                        self.schemas.insert(id, schema);
+                    }
+                }
+                Err(collision) => {
+                    if !self.collisions.contains(&collision) {
+                        self.collisions.push(collision.clone());
+                        collisions.push(collision);
+                    }
+                }
+            });
+
+        collisions
+    }
+
+    pub fn schema(&self, id: TopicId) -> Option<&Schema> {
+        self.schemas.get(&id)
+    }
+
+    pub fn set_schema(&mut self, id: TopicId, schema: Schema) {
+        self.schemas.insert(id, schema);
+    }
+
+    pub fn codec(&self, id: TopicId) -> Option<CodecId> {
+        self.codecs.get(&id).copied()
+    }
+
+    pub fn set_codec(&mut self, id: TopicId, codec: CodecId) {
+        self.codecs.insert(id, codec);
+    }
+
+    pub fn throttle(&self, id: TopicId) -> Option<&TopicThrottle> {
+        self.throttles.get(&id)
+    }
+
+    pub fn set_throttle(&mut self, id: TopicId, throttle: Throttle) {
+        self.throttles.insert(id, TopicThrottle::new(throttle));
+    }
+
+    /// Count a message on `id` at `micros`, false if its throttle drops it
+    pub fn admit(&mut self, id: TopicId, micros: u64) -> bool {
+        match self.throttles.get_mut(&id) {
+            Some(throttle) => throttle.admit(micros),
+            None => true,
+        }
+    }
+
+    pub fn info(&self, id: TopicId) -> Option<TopicInfo> {
+        self.names.get(&id).map(|name| TopicInfo {
+            name: name.clone(),
+            schema: self.schemas.get(&id).cloned(),
+        })
+    }
+
+    /// True if two names are known to use `id`, packets on it can't be trusted
+    pub fn is_collided(&self, id: TopicId) -> bool {
+        self.collisions.iter().any(|collision| collision.id == id)
+    }
+
+    pub fn name(&self, id: TopicId) -> Option<&str> {
+        self.names.get(&id).map(|name| name.as_str())
+    }
+
+    /// Mark a topic as published, true the first time
+    pub fn advertise(&mut self, id: TopicId) -> bool {
+        match self.advertised.contains(&id) {
+            true => false,
+            false => {
+                self.advertised.push(id);
+                true
+            }
+        }
+    }
+
+    /// Every advertised topic, if it's time to announce them again. Topics
+    /// are split over announcements that fit in a fragment, a schema too big
+    /// for one is left out (its name still goes).
+    pub fn announcements(&mut self) -> Vec<TopicAnnouncement> {
+        let due = match self.last_announce {
+            Some(t) => t.elapsed().as_millis() >= TOPIC_ANNOUNCE_INTERVAL_MS,
+            None => true,
+        };
+
+        if !due || self.advertised.is_empty() {
+            return vec![];
+        }
+        self.last_announce = Some(Instant::now());
+
+        let mut announcements = vec![TopicAnnouncement { topics: vec![] }];
+        self.advertised
+            .iter()
+            .filter_map(|&id| self.info(id))
+            .for_each(|mut info| {
+                let alone = TopicAnnouncement {
+                    topics: vec![info.clone()],
+                };
+                if !alone.fits() {
+                    warn!("not announcing the schema of {}, it's too big", info.name);
+                    info.schema = None;
+                }
+
+                let announcement = announcements.last_mut().unwrap();
+                announcement.topics.push(info);
+                if announcement.topics.len() > 1 && !announcement.fits() {
+                    let info = announcement.topics.pop().unwrap();
+                    announcements.push(TopicAnnouncement { topics: vec![info] });
+                }
+            });
+        announcements
+    }
+}
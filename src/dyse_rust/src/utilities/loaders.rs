@@ -16,7 +16,7 @@ extern crate yaml_rust;
 // use crate::comms::data_structures::*;
 use glob::glob;
 use std::{env, fmt, fs, path};
-use yaml_rust::{yaml::Yaml, YamlLoader};
+use yaml_rust::{yaml::Yaml, YamlEmitter, YamlLoader};
 
 pub static MAX_RECORDS_PER_CSV: u16 = 10000;
 pub static MAX_FILES_PER_RUN: u16 = 120;
@@ -59,6 +59,10 @@ impl ByuParseError {
         ByuParseError::new(format!("{item}: String"), yaml_file)
     }
 
+    pub fn bool(item: &str, yaml_file: &str) -> ByuParseError {
+        ByuParseError::new(format!("{item}: bool"), yaml_file)
+    }
+
     pub fn item(item: &str, yaml_file: &str) -> ByuParseError {
         ByuParseError::new(format!("{item}: Item"), yaml_file)
     }
@@ -118,6 +122,27 @@ impl BuffYamlUtil {
         }
     }
 
+    /// Wrap yaml that was built in code (nothing to read)
+    pub fn from_yaml(data: Yaml) -> BuffYamlUtil {
+        BuffYamlUtil {
+            yaml_path: "no file".to_string(),
+            data,
+        }
+    }
+
+    /// The data as a yaml document
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        YamlEmitter::new(&mut out)
+            .dump(&self.data)
+            .expect("Failed to dump yaml");
+        out + "\n"
+    }
+
+    pub fn save(&self, yaml_path: &str) -> std::io::Result<()> {
+        fs::write(yaml_path, self.dump())
+    }
+
     pub fn data(&self) -> &Yaml {
         &self.data
     }
@@ -151,6 +176,13 @@ impl BuffYamlUtil {
         }
     }
 
+    pub fn parse_bool(&self, item: &str, data: &Yaml) -> Result<bool, ByuParseError> {
+        match &data[item] {
+            Yaml::Boolean(val) => Ok(*val),
+            _ => Err(ByuParseError::bool(item, &self.yaml_path)),
+        }
+    }
+
     pub fn parse_ints(&self, item: &str, data: &Yaml) -> Result<Vec<i64>, ByuParseError> {
         match &data[item] {
             Yaml::Array(list) => list
@@ -191,6 +223,153 @@ impl BuffYamlUtil {
     }
 }
 
+/// Copy `path` to `path.bak` before it gets overwritten
+pub fn backup_file(path: &str) -> std::io::Result<String> {
+    let backup = format!("{path}.bak");
+    fs::copy(path, &backup)?;
+    Ok(backup)
+}
+
+/// Changes values in yaml text without touching anything else, comments,
+/// ordering and formatting survive (dumping a [`Yaml`] loses all of them).
+/// Only understands top level sections with `key: value` items in them.
+pub struct YamlEditor {
+    pub lines: Vec<String>,
+}
+
+impl YamlEditor {
+    pub fn new(text: &str) -> YamlEditor {
+        YamlEditor {
+            lines: text.lines().map(|line| line.to_string()).collect(),
+        }
+    }
+
+    pub fn read(yaml_path: &str) -> std::io::Result<YamlEditor> {
+        Ok(YamlEditor::new(&fs::read_to_string(yaml_path)?))
+    }
+
+    fn indent(line: &str) -> usize {
+        line.len() - line.trim_start().len()
+    }
+
+    fn is_blank(line: &str) -> bool {
+        line.trim().is_empty()
+    }
+
+    /// Where a value ends and its comment starts (comments inside a
+    /// flow list's brackets don't count)
+    fn comment_start(value: &str) -> usize {
+        let mut depth = 0;
+        let mut prev = ' ';
+        for (i, c) in value.char_indices() {
+            match c {
+                '[' | '{' => depth += 1,
+                ']' | '}' => depth -= 1,
+                '#' if depth <= 0 && prev.is_whitespace() => return i,
+                _ => {}
+            }
+            prev = c;
+        }
+        value.len()
+    }
+
+    /// Lines of a top level section (after its key, up to the next top level line)
+    fn section(&self, section: &str) -> Option<(usize, usize)> {
+        let key = format!("{section}:");
+        let start = self
+            .lines
+            .iter()
+            .position(|line| line.starts_with(&key) && YamlEditor::indent(line) == 0)?;
+        let end = self.lines[start + 1..]
+            .iter()
+            .position(|line| !YamlEditor::is_blank(line) && YamlEditor::indent(line) == 0)
+            .map_or(self.lines.len(), |i| start + 1 + i);
+
+        Some((start + 1, end))
+    }
+
+    /// Set `item` of `section` to a flow list, adding it if it's missing
+    pub fn set_floats(
+        &mut self,
+        section: &str,
+        item: &str,
+        values: &[f64],
+    ) -> Result<(), ByuParseError> {
+        let (start, end) = self
+            .section(section)
+            .ok_or(ByuParseError::item(section, "yaml editor"))?;
+        let list = format!(
+            "[{}]",
+            values
+                .iter()
+                .map(|v| format!("{v:?}"))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+
+        // items are the lines at the section's first indent, deeper ones belong to them
+        let content = (start..end).filter(|&i| {
+            !YamlEditor::is_blank(&self.lines[i]) && !self.lines[i].trim_start().starts_with('#')
+        });
+        let indent = content
+            .clone()
+            .map(|i| YamlEditor::indent(&self.lines[i]))
+            .next()
+            .unwrap_or(2);
+
+        let key = format!("{item}:");
+        let found = content.clone().find(|&i| {
+            YamlEditor::indent(&self.lines[i]) == indent
+                && self.lines[i].trim_start().starts_with(&key)
+        });
+
+        match found {
+            Some(i) => {
+                let line = self.lines[i].clone();
+                let value_start = line.find(&key).unwrap() + key.len();
+                let value = &line[value_start..];
+                let comment = &value[YamlEditor::comment_start(value)..];
+
+                // block lists (`- value` lines) become a flow list
+                let items = self.lines[i + 1..end]
+                    .iter()
+                    .take_while(|next| {
+                        next.trim_start().starts_with('-')
+                            && YamlEditor::indent(next) >= YamlEditor::indent(&line)
+                    })
+                    .count();
+                self.lines.drain(i + 1..i + 1 + items);
+
+                self.lines[i] = match comment.is_empty() {
+                    true => format!("{}{key} {list}", &line[..line.find(&key).unwrap()]),
+                    false => format!(
+                        "{}{key} {list} {}",
+                        &line[..line.find(&key).unwrap()],
+                        comment.trim()
+                    ),
+                };
+            }
+            None => {
+                let at = (start..end)
+                    .rfind(|&i| !YamlEditor::is_blank(&self.lines[i]))
+                    .map_or(start, |i| i + 1);
+                self.lines
+                    .insert(at, format!("{}{key} {list}", " ".repeat(indent)));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n") + "\n"
+    }
+
+    pub fn save(&self, yaml_path: &str) -> std::io::Result<()> {
+        fs::write(yaml_path, self.text())
+    }
+}
+
 // #[derive(Clone)]
 pub struct CsvUtil {
     pub run: u16,
@@ -98,4 +98,31 @@ pub mod byu_tests {
             "wrong float list"
         );
     }
+
+    #[test]
+    pub fn yaml_editor() {
+        let yaml_data = "# tasks\nsignal:\n  driver: SIN\n  parameters: [10.0, 0.5] # [frequency, amplitude]\n  republish:\n    parameters: 5.0\n\n# old:\n#   parameters: [1.0]\nfilter:\n  driver: LPF\n  parameters:\n    - 1.0\n    - 2.0\n  rate: 100.0\nplain:\n  driver: NUL\n";
+
+        let mut editor = YamlEditor::new(yaml_data);
+        editor
+            .set_floats("signal", "parameters", &[2.0, 1.5])
+            .unwrap();
+        editor.set_floats("filter", "parameters", &[3.0]).unwrap();
+        editor
+            .set_floats("plain", "parameters", &[4.0, 5.0])
+            .unwrap();
+        assert!(editor.set_floats("old", "parameters", &[1.0]).is_err());
+
+        assert_eq!(
+            editor.text(),
+            "# tasks\nsignal:\n  driver: SIN\n  parameters: [2.0, 1.5] # [frequency, amplitude]\n  republish:\n    parameters: 5.0\n\n# old:\n#   parameters: [1.0]\nfilter:\n  driver: LPF\n  parameters: [3.0]\n  rate: 100.0\nplain:\n  driver: NUL\n  parameters: [4.0, 5.0]\n"
+        );
+
+        let byu = BuffYamlUtil::new(&editor.text());
+        assert_eq!(
+            byu.parse_floats("parameters", byu.item("filter").unwrap())
+                .unwrap(),
+            vec![3.0]
+        );
+    }
 }
@@ -22,23 +22,55 @@ float pc_read_count = 0;
 float pc_write_count = 0;
 float mcu_read_count = 0;
 float mcu_write_count = 0;
+float pc_dropped_count = 0;
+float pc_corrupt_count = 0;
+
+uint16_t mcu_seq = 0;
+uint16_t pc_seq = 0;
+bool pc_seq_valid = false;
 
 FTYK hid_watch_dog;
 ByteBuffer<64> buffer;
 IntervalTimer hid_interval_timer;
 
 CommsPipeline pipeline;
+TaskSetupPacket* latch_packet = NULL;		// latch being put back together from its chunks
+
+// reports go over raw hid, or framed over usb serial when built with
+// make COMMS=serial (rid mcu_transport: {type: serial})
+int recv_report() {
+#ifdef HID_COMMS_SERIAL
+	return serial_report_recv(buffer.buffer());
+#else
+	return usb_rawhid_available() ? usb_rawhid_recv(buffer.buffer(), 0) : 0;
+#endif
+}
+
+int send_report() {
+#ifdef HID_COMMS_SERIAL
+	return serial_report_send(buffer.buffer());
+#else
+	return usb_rawhid_send(buffer.buffer(), 0);
+#endif
+}
 
 void push_hid() {
+	int report_size = recv_report();
 
-	if(usb_rawhid_available()) {
+	if(report_size != 0) {
 		blink();										// only blink when connected to hid
 		hid_watch_dog.set();
 
-		switch (usb_rawhid_recv(buffer.buffer(), 0)) {
+		switch (report_size) {
 			case 64:
 				mcu_read_count += 1;
 				// printf("Recieved Report %i, %i\n", buffer.get<byte>(0), buffer.get<byte>(1));
+				if (!check_report()) {				// answer with the counts, don't act on it
+					pc_corrupt_count += 1;
+					send_hid_status();
+					return;
+				}
+
 				mcu_time = buffer.get<float>(52);
 				pc_time = buffer.get<float>(56);
 				
@@ -75,6 +107,11 @@ void push_hid() {
 				}
 				break;
 			
+			case -1:									// serial frame that didn't check out
+				pc_corrupt_count += 1;
+				send_hid_status();
+				return;
+
 			default:
 				printf("No packet available\n");
 				// clear_feedback_pipeline();
@@ -111,6 +148,8 @@ void send_hid_status() {
 	buffer.put<byte>(1, 255);
 	buffer.put<float>(2, mcu_write_count);
 	buffer.put<float>(6, mcu_read_count);
+	buffer.put<float>(10, pc_dropped_count);
+	buffer.put<float>(14, pc_corrupt_count);
 
 	send_hid_with_timestamp();
 }
@@ -123,15 +162,20 @@ void send_hid_feedback() {
 		// 		printf("update: %i config: %i\n", pipeline.feedback[task_num]->update, pipeline.feedback[task_num]->configured);
 		// 		pipeline.timestamp.print();
 		// }
-		if (pipeline.feedback[task_num]->update > 0) {
+		TaskFeedback* fb = pipeline.feedback[task_num];
+		if (fb->update > 0 || fb->chunk > 0) {
 			
-			pipeline.feedback[task_num]->update = 0;
+			if (fb->chunk == 0) {		// a new output waits until the last one is all out
+				fb->update = 0;
+				fb->sending.from_array(fb->output.as_array(), min(fb->output.size(), MAX_TASK_DATA));
+			}
 
 			buffer.put<byte>(0, 1);
-			buffer.put<byte>(1, pipeline.feedback[task_num]->latch);
-			buffer.put<byte>(2, pipeline.feedback[task_num]->task_id);
+			buffer.put<byte>(1, fb->latch);
+			buffer.put<byte>(2, fb->task_id);
 			
-			dump_vector(&pipeline.feedback[task_num]->output);
+			dump_vector(&fb->sending, fb->chunk);
+			fb->chunk = (fb->chunk + 1) % task_data_chunks(fb->sending.size());
 			
 			buffer.put<float>(48, pipeline.feedback[task_num]->timestamp);
 			
@@ -155,7 +199,8 @@ void send_hid_with_timestamp() {
 	buffer.put<float>(52, mcu_time);
 	buffer.put<float>(56, pc_time);
 	buffer.put<float>(60, pipeline.timestamp.total_seconds());
-	if (usb_rawhid_send(buffer.buffer(), 0) > 0) {
+	seal_report();
+	if (send_report() > 0) {
 		mcu_write_count += 1;
 	}
 	else {
@@ -170,16 +215,16 @@ void init_task_hid() {
 	
 	task->packet_type = 0;
 	task->task_id = buffer.get<byte>(2);
-	task->rate = buffer.get<uint16_t>(3);
-	task->key[0] = buffer.get<char>(5);
-	task->key[1] = buffer.get<char>(6);
-	task->key[2] = buffer.get<char>(7);
-	task->n_inputs = buffer.get<byte>(10);
+	task->rate = buffer.get<uint16_t>(HID_INIT_RATE_INDEX);
+	task->key[0] = buffer.get<char>(HID_INIT_KEY_INDEX);
+	task->key[1] = buffer.get<char>(HID_INIT_KEY_INDEX + 1);
+	task->key[2] = buffer.get<char>(HID_INIT_KEY_INDEX + 2);
+	task->n_inputs = buffer.get<byte>(HID_INIT_N_INPUTS_INDEX);
 	task->inputs.reset(task->n_inputs);
 
 	// printf("Init task %i %i %i %c%c%c\n", task->task_id, task->n_inputs, task->rate, task->key[0], task->key[1], task->key[2]);
 	for (int i = 0; i < task->n_inputs; i++) {
-		task->inputs[i] = buffer.get<byte>(11 + i);
+		task->inputs[i] = buffer.get<byte>(HID_INIT_INPUTS_INDEX + i);
 	}
 
 	// push to setup queue
@@ -207,21 +252,45 @@ void config_task_hid() {
 
 void overwrite_task_hid() {
 
-	TaskSetupPacket* task = new TaskSetupPacket;
+	int chunk = buffer.get<byte>(3);
+	int total = buffer.get<byte>(4);
+	int start = chunk * MAX_FLOAT_DATA_PER_SEND;
 
-	task->packet_type = 2;
-	task->latch = buffer.get<byte>(1);
-	task->task_id = buffer.get<byte>(2);
-	task->data_len = buffer.get<byte>(3);
-	task->data.reset(task->data_len);
+	if (chunk == 0) {				// start over, a latch that never finished is dropped
+		if (latch_packet) {
+			delete latch_packet;
+		}
+		latch_packet = new TaskSetupPacket;
+		latch_packet->packet_type = 2;
+		latch_packet->latch = buffer.get<byte>(1);
+		latch_packet->task_id = buffer.get<byte>(2);
+		latch_packet->data_len = total;
+		latch_packet->data.reset(total);
+	}
+	else if (!latch_packet || start >= total ||
+			 latch_packet->task_id != buffer.get<byte>(2) ||
+			 latch_packet->data_len != total ||
+			 latch_packet->data.size() != start) {
+		// out of order, wait for the next start
+		if (latch_packet) {
+			delete latch_packet;
+			latch_packet = NULL;
+		}
+		return;
+	}
 
-	// printf("Overwrite task %i %i %i\n", task->task_id, task->latch, task->data_len);
-	for (int i = 0; i < task->data_len; i++) {
-		task->data[i] = buffer.get<float>((4 * i) + 4);
+	// printf("Overwrite task %i %i %i %i\n", latch_packet->task_id, latch_packet->latch, chunk, total);
+	int size = min(total - start, MAX_FLOAT_DATA_PER_SEND);
+	for (int i = 0; i < size; i++) {
+		latch_packet->data[start + i] = buffer.get<float>((4 * i) + 5);
 	}
+	latch_packet->data.set_items(start + size);
 
-	// push config packet to setup queue
-	pipeline.setup_queue.push(task);
+	// push the whole latch to setup queue
+	if (latch_packet->data.size() >= total) {
+		pipeline.setup_queue.push(latch_packet);
+		latch_packet = NULL;
+	}
 }
 
 void nuclear_option() {
@@ -237,20 +306,75 @@ void reset_hid_stats() {
 	pc_read_count = 0;
 	pc_time = 0;
 	hid_errors = 0;
+	pc_dropped_count = 0;
+	pc_corrupt_count = 0;
+	pc_seq_valid = false;
+
+	if (latch_packet) {
+		delete latch_packet;
+		latch_packet = NULL;
+	}
 
 	pipeline.timestamp.set();
 	pipeline.feedback.clear();
 	pipeline.setup_queue.clear();
 }
 
-void dump_vector(Vector<float>* data) {
-	buffer.put<byte>(3, data->size());
-	buffer.put<float>(4, data->size(), data->as_array());
+int task_data_chunks(int total) {
+	return max(1, (total + MAX_FLOAT_DATA_PER_SEND - 1) / MAX_FLOAT_DATA_PER_SEND);
+}
+
+void dump_vector(Vector<float>* data, int chunk) {
+	// chunk id, total length then the chunk's floats (rid get_task_data_packet)
+	int start = chunk * MAX_FLOAT_DATA_PER_SEND;
+	int size = min(data->size() - start, MAX_FLOAT_DATA_PER_SEND);
+	buffer.put<byte>(3, chunk);
+	buffer.put<byte>(4, data->size());
+	buffer.put<float>(5, size, data->as_array() + start);
+}
+
+uint16_t report_crc() {
+	// CRC-16/CCITT-FALSE over the report with the crc bytes zeroed
+	uint16_t crc = 0xFFFF;
+	for (int i = 0; i < 64; i++) {
+		byte b = (i == HID_CRC_INDEX || i == HID_CRC_INDEX + 1) ? 0 : buffer.get<byte>(i);
+		crc ^= b << 8;
+		for (int j = 0; j < 8; j++) {
+			crc = (crc & 0x8000) ? (crc << 1) ^ 0x1021 : crc << 1;
+		}
+	}
+	return crc;
+}
+
+bool check_report() {
+	if (buffer.get<byte>(HID_VERSION_INDEX) != HID_REPORT_VERSION ||
+		buffer.get<uint16_t>(HID_CRC_INDEX) != report_crc()) {
+		return false;
+	}
+
+	// count the pc reports that never made it, big jumps are the pc starting over
+	uint16_t seq = buffer.get<uint16_t>(HID_SEQ_INDEX);
+	uint16_t step = seq - pc_seq;
+	if (pc_seq_valid && step > 0 && step < HID_SEQ_RESTART) {
+		pc_dropped_count += step - 1;
+	}
+	pc_seq = seq;
+	pc_seq_valid = true;
+	return true;
+}
+
+void seal_report() {
+	buffer.put<byte>(HID_VERSION_INDEX, HID_REPORT_VERSION);
+	buffer.put<uint16_t>(HID_SEQ_INDEX, mcu_seq++);
+	buffer.put<uint16_t>(HID_CRC_INDEX, report_crc());
 }
 
 CommsPipeline* enable_hid_interrupts() {
 	pipeline.feedback.reset(0);
 	pipeline.setup_queue.reset(0);
+#ifdef HID_COMMS_SERIAL
+	serial_comms_begin();
+#endif
 	hid_interval_timer.begin(push_hid, HID_REFRESH_RATE);
 	return &pipeline;
 }
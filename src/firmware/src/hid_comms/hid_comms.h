@@ -1,4 +1,5 @@
 #include "bytebuffer.h"
+#include "serial_comms.h"
 #include "utilities/blink.h"
 #include "utilities/timing.h"
 #include "utilities/vector.h"
@@ -7,7 +8,21 @@
 #define HIDCOMMS_H
 
 #define HID_REFRESH_RATE 1000.0
-#define MAX_FLOAT_DATA_PER_SEND 10
+#define MAX_FLOAT_DATA_PER_SEND 9	// task data ends before the report trailer
+#define MAX_TASK_DATA 255			// longer inputs/outputs are sent in chunks, up to this
+
+// report trailer, matches rid::report
+#define HID_REPORT_VERSION 1
+#define HID_VERSION_INDEX 43
+#define HID_SEQ_INDEX 44
+#define HID_CRC_INDEX 46
+#define HID_SEQ_RESTART 0x8000
+
+// task init packet, matches rid::data_structures
+#define HID_INIT_RATE_INDEX 3
+#define HID_INIT_KEY_INDEX 5
+#define HID_INIT_N_INPUTS_INDEX 10
+#define HID_INIT_INPUTS_INDEX 11
 
 extern float hid_errors;
 
@@ -44,6 +59,9 @@ struct TaskFeedback {
 	float mins;
 	float hrs;
 	Vector<float> output;
+
+	int chunk;					// next chunk of sending, 0 when there's nothing left
+	Vector<float> sending;
 };
 
 struct CommsPipeline {
@@ -54,6 +72,8 @@ struct CommsPipeline {
 	Vector<TaskSetupPacket*> setup_queue;
 };
 
+int recv_report();
+int send_report();
 void push_hid();
 void clear_feedback_pipeline();
 void send_hid_status();
@@ -64,7 +84,11 @@ void config_task_hid();
 void overwrite_task_hid();
 void nuclear_option();
 void reset_hid_stats();
-void dump_vector(Vector<float>*);
+int task_data_chunks(int);
+void dump_vector(Vector<float>*, int);
+uint16_t report_crc();
+bool check_report();
+void seal_report();
 CommsPipeline* enable_hid_interrupts();
 
 #endif
@@ -0,0 +1,141 @@
+/********************************************************************************
+ * 
+ *      ____                     ____          __           __       _          
+ *	   / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *	  / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *	 / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  ) 
+ *	/_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/  
+ *	      /____/                                                                
+ * 
+ * 
+ * 
+ ********************************************************************************/
+
+#include "serial_comms.h"
+
+float serial_bad_frames = 0;
+
+byte frame[SERIAL_MAX_FRAME];				// stuffed bytes of the frame coming in
+int frame_size = 0;
+bool frame_overflow = false;
+
+uint16_t crc16(const byte* data, int size) {
+	// CRC-16/CCITT-FALSE (poly 0x1021, init 0xFFFF)
+	uint16_t crc = 0xFFFF;
+	for (int i = 0; i < size; i++) {
+		crc ^= data[i] << 8;
+		for (int j = 0; j < 8; j++) {
+			crc = (crc & 0x8000) ? (crc << 1) ^ 0x1021 : crc << 1;
+		}
+	}
+	return crc;
+}
+
+int cobs_encode(const byte* data, int size, byte* out) {
+	// out needs size + size / 254 + 1 bytes and gets no zeros
+	int code_index = 0;
+	int n = 1;
+
+	for (int i = 0; i < size; i++) {
+		if (data[i] != 0) {
+			out[n++] = data[i];
+		}
+
+		if (data[i] == 0 || n - code_index == 255) {
+			out[code_index] = n - code_index;
+			code_index = n++;
+		}
+	}
+
+	out[code_index] = n - code_index;
+	return n;
+}
+
+int cobs_decode(const byte* data, int size, byte* out) {
+	// -1 if the data wasn't stuffed
+	int n = 0;
+	int i = 0;
+
+	while (i < size) {
+		int code = data[i];
+		if (code == 0 || i + code > size) {
+			return -1;
+		}
+
+		for (int j = i + 1; j < i + code; j++) {
+			out[n++] = data[j];
+		}
+		i += code;
+
+		if (code < 255 && i < size) {
+			out[n++] = 0;
+		}
+	}
+
+	return n;
+}
+
+void serial_comms_begin() {
+	COMMS_SERIAL.begin(SERIAL_COMMS_BAUD);
+	frame_size = 0;
+	frame_overflow = false;
+}
+
+int serial_report_recv(byte* report) {
+	// a report when a whole frame is in, 0 when there isn't one yet, -1 for a corrupt frame
+	while (COMMS_SERIAL.available() > 0) {
+		byte b = COMMS_SERIAL.read();
+
+		if (b != SERIAL_FRAME_END) {
+			if (frame_size < SERIAL_MAX_FRAME) {
+				frame[frame_size++] = b;
+			}
+			else {
+				frame_overflow = true;		// lost an end byte, wait for the next one
+			}
+			continue;
+		}
+
+		if (frame_size == 0 && !frame_overflow) {
+			continue;
+		}
+
+		byte data[SERIAL_MAX_FRAME];
+		int size = frame_overflow ? -1 : cobs_decode(frame, frame_size, data);
+		frame_size = 0;
+		frame_overflow = false;
+
+		if (size != SERIAL_REPORT_SIZE + 2 ||
+			crc16(data, SERIAL_REPORT_SIZE) != (data[SERIAL_REPORT_SIZE] | (data[SERIAL_REPORT_SIZE + 1] << 8))) {
+			serial_bad_frames += 1;
+			return -1;
+		}
+
+		memcpy(report, data, SERIAL_REPORT_SIZE);
+		return SERIAL_REPORT_SIZE;
+	}
+
+	return 0;
+}
+
+int serial_report_send(const byte* report) {
+	byte data[SERIAL_REPORT_SIZE + 2];
+	byte out[SERIAL_MAX_FRAME];
+
+	memcpy(data, report, SERIAL_REPORT_SIZE);
+	uint16_t crc = crc16(report, SERIAL_REPORT_SIZE);
+	data[SERIAL_REPORT_SIZE] = crc & 0xFF;
+	data[SERIAL_REPORT_SIZE + 1] = crc >> 8;
+
+	// an end byte up front too, anything printed before it is a bad frame on the pc
+	// instead of the start of this one
+	out[0] = SERIAL_FRAME_END;
+	int size = 1 + cobs_encode(data, SERIAL_REPORT_SIZE + 2, out + 1);
+	out[size++] = SERIAL_FRAME_END;
+
+	if (COMMS_SERIAL.availableForWrite() < size) {		// don't wait in the interval timer
+		return 0;
+	}
+
+	return (int)COMMS_SERIAL.write(out, size) == size ? SERIAL_REPORT_SIZE : 0;
+}
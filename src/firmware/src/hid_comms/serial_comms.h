@@ -0,0 +1,39 @@
+/********************************************************************************
+ * 
+ *      ____                     ____          __           __       _          
+ *	   / __ \__  __________     /  _/___  ____/ /_  _______/ /______(_)__  _____
+ *	  / / / / / / / ___/ _ \    / // __ \/ __  / / / / ___/ __/ ___/ / _ \/ ___/
+ *	 / /_/ / /_/ (__  )  __/  _/ // / / / /_/ / /_/ (__  ) /_/ /  / /  __(__  ) 
+ *	/_____/\__, /____/\___/  /___/_/ /_/\__,_/\__,_/____/\__/_/  /_/\___/____/  
+ *	      /____/                                                                
+ * 
+ * 
+ * 
+ ********************************************************************************/
+
+#include <Arduino.h>
+
+#ifndef SERIALCOMMS_H
+#define SERIALCOMMS_H
+
+// reports framed for a serial port, matches rid::serial on the pc:
+// the report, its crc16 (little endian), COBS stuffed and ended with a zero
+#define SERIAL_REPORT_SIZE 64
+#define SERIAL_MAX_FRAME 128		// a stuffed report is 68 bytes, longer is junk from a bad link
+#define SERIAL_FRAME_END 0
+#define SERIAL_COMMS_BAUD 115200	// usb serial ignores it, a uart doesn't
+
+#ifndef COMMS_SERIAL
+#define COMMS_SERIAL Serial			// usb serial, define as Serial1 (...) for a uart
+#endif
+
+extern float serial_bad_frames;
+
+uint16_t crc16(const byte*, int);
+int cobs_encode(const byte*, int, byte*);
+int cobs_decode(const byte*, int, byte*);
+void serial_comms_begin();
+int serial_report_recv(byte*);
+int serial_report_send(const byte*);
+
+#endif
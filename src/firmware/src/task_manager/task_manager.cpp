@@ -75,6 +75,8 @@ void add_task(TaskSetupPacket* task_init) {
 		task_fb->configured = 0;
 		task_fb->task_id = index;
 		task_fb->output.reset(0);
+		task_fb->chunk = 0;
+		task_fb->sending.reset(0);
 		task_fb->timestamp = -1;
 
 		noInterrupts();
@@ -100,8 +100,6 @@ template <typename T> class Vector {
 				@param:
 					size: (int) length of the buffer with type T
 			*/
-			size = uint8_t(size);
-
 			if (length == size) {
 				return;
 			}
@@ -12,9 +12,12 @@
  ********************************************************************************/
 use dyse_rust::{
     rid::robot_firmware::TaskCommunication,
-    socks::{message::UDP_PACKET_SIZE, socks::Sock},
+    socks::{logging, message::UDP_PACKET_SIZE, schema::Value, socks::Sock},
+};
+use pyo3::{
+    prelude::*,
+    types::{PyDict, PyList},
 };
-use pyo3::{prelude::*, types::PyDict};
 use std::time::Instant;
 
 #[pyfunction]
@@ -24,6 +27,28 @@ fn send(name: &str, data: Vec<u8>) -> PyResult<()> {
     Ok(())
 }
 
+/// Python version of a decoded message (dicts, lists and scalars)
+fn value_to_py(py: Python, value: &Value) -> PyObject {
+    match value {
+        Value::Null => py.None(),
+        Value::Bool(v) => v.to_object(py),
+        Value::Int(v) => v.to_object(py),
+        Value::UInt(v) => v.to_object(py),
+        Value::Float(v) => v.to_object(py),
+        Value::String(v) => v.to_object(py),
+        Value::Array(items) => {
+            PyList::new(py, items.iter().map(|item| value_to_py(py, item))).to_object(py)
+        }
+        Value::Object(fields) => {
+            let dict = PyDict::new(py);
+            fields.iter().for_each(|(name, field)| {
+                dict.set_item(name, value_to_py(py, field)).unwrap();
+            });
+            dict.to_object(py)
+        }
+    }
+}
+
 #[pyclass]
 struct PySock {
     sock: Sock,
@@ -48,16 +73,36 @@ impl PySock {
         match self.sock.try_rx(&mut buffer) {
             Some(_) => Ok(self
                 .sock
-                .recv_available()
-                .iter()
-                .map(|payload| {
-                    bincode::deserialize::<Vec<f64>>(payload)
+                .available_messages()
+                .into_iter()
+                .map(|i| {
+                    self.sock
+                        .decode_message::<Vec<f64>>(i)
                         .expect("Failed to serialize payload (pysock)")
                 })
                 .collect()),
             _ => Ok(vec![]),
         }
     }
+    /// Decode whatever arrives using the schemas publishers announce,
+    /// returns {topic: message} (topics without a schema are skipped)
+    pub fn recv_any(&mut self) -> PyResult<Py<PyDict>> {
+        Python::with_gil(|py| {
+            let messages = PyDict::new(py);
+            let mut buffer = [0u8; UDP_PACKET_SIZE];
+
+            if let Some(i) = self.sock.try_rx(&mut buffer) {
+                if let Some(Ok(value)) = self.sock.decode(i) {
+                    messages
+                        .set_item(&self.sock.targets[i], value_to_py(py, &value))
+                        .unwrap();
+                }
+            }
+
+            Ok(messages.into())
+        })
+    }
+
     // pub struct TaskCommunication {
     //     pub name: String,
     //     pub latch: u8,
@@ -78,9 +123,11 @@ impl PySock {
             while !rx && t.elapsed().as_secs() < 1 && !*self.sock.shutdown.read().unwrap() {
                 match self.sock.try_rx(&mut buffer) {
                     Some(_) => {
-                        self.sock.recv_available().iter().for_each(|payload| {
+                        self.sock.available_messages().into_iter().for_each(|i| {
                             let task_dict = PyDict::new(py);
-                            let packet = bincode::deserialize::<TaskCommunication>(payload)
+                            let packet = self
+                                .sock
+                                .decode_message::<TaskCommunication>(i)
                                 .expect("Failed to serialize payload (pysock)");
 
                             task_dict.set_item("latch", packet.latch).unwrap();
@@ -105,6 +152,7 @@ impl PySock {
 
 #[pymodule]
 fn socks(_py: Python, m: &PyModule) -> PyResult<()> {
+    logging::init_from_env();
     m.add_function(wrap_pyfunction!(send, m)?)?;
     m.add_class::<PySock>()?;
     Ok(())